@@ -0,0 +1,45 @@
+-- Migration: Create pending_allocations table
+-- Description: Record chip deficits that receive_chips could not fulfil (e.g. NFT pool exhausted)
+--              and notify the backend when new NFTs/chips are imported so deficits can be fulfilled
+
+CREATE TABLE IF NOT EXISTS pending_allocations (
+    id              BIGSERIAL PRIMARY KEY,
+    user_address    VARCHAR(42) NOT NULL,
+    chips_deficit   BIGINT NOT NULL CHECK (chips_deficit > 0),
+    reason          VARCHAR(32) NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    fulfilled_at    TIMESTAMPTZ
+);
+
+COMMENT ON COLUMN pending_allocations.chips_deficit IS 'Chips the user should have received but did not';
+COMMENT ON COLUMN pending_allocations.reason IS 'pool_exhausted: no free NFTs left, receive_limit: per-receive or loop limit reached';
+COMMENT ON COLUMN pending_allocations.fulfilled_at IS 'Set when the deficit is cleared (NULL = still open)';
+
+-- At most one open deficit per user
+CREATE UNIQUE INDEX IF NOT EXISTS idx_pending_allocations_open_user
+ON pending_allocations(user_address) WHERE fulfilled_at IS NULL;
+
+CREATE TRIGGER update_pending_allocations_updated_at
+    BEFORE UPDATE ON pending_allocations
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- Notify listeners when NFTs or chips are imported
+CREATE OR REPLACE FUNCTION notify_nfts_imported()
+RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('nfts_imported', TG_TABLE_NAME);
+    RETURN NULL;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER nfts_imported_notify
+    AFTER INSERT ON nfts
+    FOR EACH STATEMENT
+    EXECUTE FUNCTION notify_nfts_imported();
+
+CREATE TRIGGER chips_imported_notify
+    AFTER INSERT ON chips
+    FOR EACH STATEMENT
+    EXECUTE FUNCTION notify_nfts_imported();
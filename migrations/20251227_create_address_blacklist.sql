@@ -0,0 +1,20 @@
+-- Migration: Create address_blacklist table
+-- Description: Addresses excluded from chip allocation (contracts, exchanges, bridges...)
+--              Seeded at startup from configured contract addresses, managed via admin API
+
+CREATE TABLE IF NOT EXISTS address_blacklist (
+    address         VARCHAR(42) PRIMARY KEY,      -- lowercase
+    reason          TEXT,
+    source          VARCHAR(16) NOT NULL DEFAULT 'admin' CHECK (source IN ('config', 'admin')),
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    removed_at      TIMESTAMPTZ
+);
+
+COMMENT ON COLUMN address_blacklist.source IS 'config: seeded from .env contract addresses, admin: added via admin API';
+COMMENT ON COLUMN address_blacklist.removed_at IS 'Soft delete; removed config entries are not re-seeded on restart';
+
+CREATE TRIGGER update_address_blacklist_updated_at
+    BEFORE UPDATE ON address_blacklist
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,21 @@
+-- Migration: Create token_transfers table
+-- Description: HakuToken transfer history recorded from UserTransfer events
+--              Used to compute time-weighted average balances (time_weighted allocation mode)
+
+CREATE TABLE IF NOT EXISTS token_transfers (
+    id                  BIGSERIAL PRIMARY KEY,
+    from_address        VARCHAR(42) NOT NULL,      -- lowercase
+    to_address          VARCHAR(42) NOT NULL,      -- lowercase
+    value_raw           NUMERIC(78,0) NOT NULL,
+    block_number        BIGINT NOT NULL,
+    block_timestamp     TIMESTAMPTZ NOT NULL,
+    tx_hash             VARCHAR(66),
+    mint_remark         TEXT,                      -- set when the transfer is part of a userMint
+    created_at          TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_token_transfers_from_time ON token_transfers(from_address, block_timestamp);
+CREATE INDEX IF NOT EXISTS idx_token_transfers_to_time ON token_transfers(to_address, block_timestamp);
+CREATE INDEX IF NOT EXISTS idx_token_transfers_time ON token_transfers(block_timestamp);
+
+COMMENT ON COLUMN token_transfers.block_timestamp IS 'Timestamp emitted by the UserTransfer event';
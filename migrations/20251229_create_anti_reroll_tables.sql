@@ -0,0 +1,46 @@
+-- Migration: Anti-reroll protection
+-- Description: released_chips remembers which chips a user lost on transfer-out so they can be
+--              restored (instead of re-rolled) when tokens come back during the cooldown;
+--              reroll_flags records rapid back-and-forth transfers for admin review
+
+CREATE TABLE IF NOT EXISTS released_chips (
+    id              BIGSERIAL PRIMARY KEY,
+    user_address    VARCHAR(42) NOT NULL,      -- lowercase
+    chip_id         INTEGER NOT NULL REFERENCES chips(id),
+    nft_id          INTEGER REFERENCES nfts(id),
+    released_at     TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    restored_at     TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_released_chips_user_time
+ON released_chips(user_address, released_at) WHERE restored_at IS NULL;
+
+COMMENT ON COLUMN released_chips.restored_at IS 'Set when the chip was given back to the same user during the cooldown';
+
+CREATE TABLE IF NOT EXISTS reroll_flags (
+    id              BIGSERIAL PRIMARY KEY,
+    address_a       VARCHAR(42) NOT NULL,      -- lowercase, address_a < address_b
+    address_b       VARCHAR(42) NOT NULL,
+    round_trips     INTEGER NOT NULL DEFAULT 1,
+    transfer_count  INTEGER NOT NULL DEFAULT 0,
+    first_seen_at   TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_seen_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    status          VARCHAR(16) NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'reviewed', 'dismissed')),
+    review_note     TEXT,
+    reviewed_at     TIMESTAMPTZ,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- At most one open flag per address pair
+CREATE UNIQUE INDEX IF NOT EXISTS idx_reroll_flags_open_pair
+ON reroll_flags(address_a, address_b) WHERE status = 'open';
+
+CREATE INDEX IF NOT EXISTS idx_reroll_flags_status ON reroll_flags(status);
+
+COMMENT ON COLUMN reroll_flags.round_trips IS 'min(transfers a->b, transfers b->a) within the detection window';
+
+CREATE TRIGGER update_reroll_flags_updated_at
+    BEFORE UPDATE ON reroll_flags
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
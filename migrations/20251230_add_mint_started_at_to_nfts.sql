@@ -0,0 +1,13 @@
+-- Migration: Add mint_started_at to nfts
+-- Description: Records when an NFT entered the "applying" state (is_mint = 1) so stale
+--              applications can be swept back to 0 after a timeout
+
+ALTER TABLE nfts ADD COLUMN IF NOT EXISTS mint_started_at TIMESTAMPTZ;
+
+-- Existing applying rows: best guess is the last update time
+UPDATE nfts SET mint_started_at = updated_at WHERE is_mint = 1 AND mint_started_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_nfts_mint_started_at
+ON nfts(mint_started_at) WHERE is_mint = 1;
+
+COMMENT ON COLUMN nfts.mint_started_at IS 'When is_mint was set to 1 (applying); NULL otherwise';
@@ -0,0 +1,55 @@
+-- Migration: Mint state machine and attempt log
+-- Description: Enforces the allowed nfts.is_mint transitions in the database and records
+--              every mint attempt (self-pay or backend) with its outcome
+
+-- is_mint: 0 = unminted, 1 = applying, 2 = minted
+-- Allowed: 0 -> 1 (apply), 1 -> 0 (failed / expired), 1 -> 2 and 0 -> 2 (UserMint seen on chain)
+-- Minted is terminal.
+CREATE OR REPLACE FUNCTION enforce_nft_mint_transition()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.is_mint IS DISTINCT FROM OLD.is_mint AND NOT (
+        (OLD.is_mint = 0 AND NEW.is_mint IN (1, 2)) OR
+        (OLD.is_mint = 1 AND NEW.is_mint IN (0, 2))
+    ) THEN
+        RAISE EXCEPTION 'illegal is_mint transition % -> % for nft %', OLD.is_mint, NEW.is_mint, OLD.id
+            USING ERRCODE = 'check_violation';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS enforce_nfts_mint_transition ON nfts;
+CREATE TRIGGER enforce_nfts_mint_transition
+    BEFORE UPDATE OF is_mint ON nfts
+    FOR EACH ROW
+    EXECUTE FUNCTION enforce_nft_mint_transition();
+
+CREATE TABLE IF NOT EXISTS mint_attempts (
+    id              BIGSERIAL PRIMARY KEY,
+    nft_id          INTEGER NOT NULL REFERENCES nfts(id),
+    user_address    VARCHAR(42) NOT NULL,      -- lowercase
+    mode            VARCHAR(16) NOT NULL CHECK (mode IN ('self_pay', 'backend')),
+    status          VARCHAR(16) NOT NULL DEFAULT 'pending'
+                    CHECK (status IN ('pending', 'submitted', 'succeeded', 'failed', 'expired')),
+    tx_hash         VARCHAR(66),
+    token_id        BIGINT,
+    error           TEXT,
+    started_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    finished_at     TIMESTAMPTZ,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_mint_attempts_nft ON mint_attempts(nft_id, started_at DESC);
+CREATE INDEX IF NOT EXISTS idx_mint_attempts_user ON mint_attempts(user_address, started_at DESC);
+
+-- 每个 NFT 同时最多一个未结束的 attempt
+CREATE UNIQUE INDEX IF NOT EXISTS idx_mint_attempts_open_nft
+ON mint_attempts(nft_id) WHERE status IN ('pending', 'submitted');
+
+DROP TRIGGER IF EXISTS update_mint_attempts_updated_at ON mint_attempts;
+CREATE TRIGGER update_mint_attempts_updated_at
+    BEFORE UPDATE ON mint_attempts
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,8 @@
+-- Migration: EIP-712 mint vouchers
+-- Description: Self-pay attempts carry a server-signed voucher; the attempt id is the voucher
+--              nonce and the deadline is kept for auditing
+
+ALTER TABLE mint_attempts ADD COLUMN IF NOT EXISTS voucher_deadline TIMESTAMPTZ;
+ALTER TABLE mint_attempts ADD COLUMN IF NOT EXISTS voucher_signature VARCHAR(132);
+
+COMMENT ON COLUMN mint_attempts.voucher_deadline IS 'Deadline of the EIP-712 voucher (nonce = id); NULL for backend mints';
@@ -0,0 +1,21 @@
+-- Migration: Sign-In-With-Ethereum (EIP-4361)
+-- Description: One-time login nonces and the session tokens issued after a valid signature
+
+CREATE TABLE IF NOT EXISTS siwe_nonces (
+    nonce           VARCHAR(64) PRIMARY KEY,
+    issued_at       TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at      TIMESTAMPTZ NOT NULL,
+    used_at         TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_siwe_nonces_expires_at ON siwe_nonces(expires_at);
+
+CREATE TABLE IF NOT EXISTS user_sessions (
+    token_hash      VARCHAR(66) PRIMARY KEY,   -- keccak256 of the bearer token
+    user_address    VARCHAR(42) NOT NULL,      -- lowercase
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at      TIMESTAMPTZ NOT NULL,
+    revoked_at      TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_sessions_user ON user_sessions(user_address);
@@ -0,0 +1,10 @@
+-- Migration: Backend mint job queue
+-- Description: Backend-paid attempts double as queued mint jobs; the signer worker needs the
+--              contract uint256 parameter and picks pending jobs in id order
+
+ALTER TABLE mint_attempts ADD COLUMN IF NOT EXISTS mint_param BIGINT;
+
+CREATE INDEX IF NOT EXISTS idx_mint_attempts_backend_queue
+ON mint_attempts(id) WHERE mode = 'backend' AND status = 'pending';
+
+COMMENT ON COLUMN mint_attempts.mint_param IS 'uint256 parameter passed to the NFT contract (from nfts.file_name)';
@@ -0,0 +1,12 @@
+-- Migration: Batch minting
+-- Description: Attempts opened together by a batch mint share a batch_id; the backend worker
+--              submits all pending jobs of a batch in a single multicall transaction
+
+CREATE SEQUENCE IF NOT EXISTS mint_batch_id_seq;
+
+ALTER TABLE mint_attempts ADD COLUMN IF NOT EXISTS batch_id BIGINT;
+
+CREATE INDEX IF NOT EXISTS idx_mint_attempts_batch_id
+ON mint_attempts(batch_id) WHERE batch_id IS NOT NULL;
+
+COMMENT ON COLUMN mint_attempts.batch_id IS 'Shared by attempts opened in one batch mint (from mint_batch_id_seq), NULL for single mints';
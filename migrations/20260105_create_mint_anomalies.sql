@@ -0,0 +1,20 @@
+-- Migration: Mint anomalies
+-- Description: UserMint events that fail validation (unparseable remark, unknown NFT, minter
+--              not holding the NFT's chips or not its recorded owner) are recorded here for
+--              admin review instead of blindly overwriting nfts.user_address
+
+CREATE TABLE IF NOT EXISTS mint_anomalies (
+    id              BIGSERIAL PRIMARY KEY,
+    kind            VARCHAR(32) NOT NULL CHECK (kind IN ('invalid_remark', 'unknown_nft', 'owner_mismatch', 'chips_not_held')),
+    remark          TEXT NOT NULL,
+    nft_id          INTEGER,                   -- NULL when the remark could not be parsed
+    token_id        BIGINT,
+    minter          VARCHAR(42) NOT NULL,      -- lowercase, user of the UserMint event
+    recorded_owner  VARCHAR(255),              -- nfts.user_address at the time of the event
+    detail          TEXT NOT NULL,
+    block_number    BIGINT,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_mint_anomalies_kind_time ON mint_anomalies(kind, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_mint_anomalies_nft_id ON mint_anomalies(nft_id);
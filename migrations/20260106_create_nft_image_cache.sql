@@ -0,0 +1,21 @@
+-- Migration: NFT image cache
+-- Description: Persistent token_url -> image URL cache filled from IPFS metadata, so the minted
+--              gallery does not depend on IPFS at request time
+
+CREATE TABLE IF NOT EXISTS nft_image_cache (
+    token_url       VARCHAR(512) PRIMARY KEY,
+    image_url       TEXT,                      -- NULL while the metadata could not be resolved
+    last_error      TEXT,
+    attempts        INTEGER NOT NULL DEFAULT 0,
+    fetched_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_nft_image_cache_unresolved
+ON nft_image_cache(fetched_at) WHERE image_url IS NULL;
+
+CREATE TRIGGER update_nft_image_cache_updated_at
+    BEFORE UPDATE ON nft_image_cache
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,15 @@
+-- Migration: Minted gallery
+-- Description: minted_at records when the UserMint event was processed so the gallery can sort
+--              and filter by mint date (updated_at changes on any later update)
+
+ALTER TABLE nfts ADD COLUMN IF NOT EXISTS minted_at TIMESTAMPTZ;
+
+UPDATE nfts SET minted_at = updated_at WHERE is_mint = 2 AND minted_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_nfts_minted_gallery
+ON nfts(minted_at DESC, id DESC) WHERE is_mint = 2;
+
+CREATE INDEX IF NOT EXISTS idx_nfts_minted_token_id
+ON nfts(token_id, id) WHERE is_mint = 2;
+
+COMMENT ON COLUMN nfts.minted_at IS 'When the UserMint event for this NFT was processed';
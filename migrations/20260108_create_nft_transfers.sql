@@ -0,0 +1,34 @@
+-- Migration: ERC-721 ownership tracking
+-- Description: Transfer events of the NFT contract are indexed into nft_transfers (ownership
+--              history keyed by token id); nfts.owner_address holds the current on-chain owner.
+--              nfts.user_address keeps the address the NFT was minted for.
+
+CREATE TABLE IF NOT EXISTS nft_transfers (
+    id              BIGSERIAL PRIMARY KEY,
+    token_id        BIGINT NOT NULL,
+    from_address    VARCHAR(42) NOT NULL,      -- lowercase, zero address for mints
+    to_address      VARCHAR(42) NOT NULL,      -- lowercase, zero address for burns
+    block_number    BIGINT NOT NULL,
+    log_index       BIGINT NOT NULL,
+    tx_hash         VARCHAR(66) NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (tx_hash, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_nft_transfers_token ON nft_transfers (token_id, block_number DESC, log_index DESC);
+CREATE INDEX IF NOT EXISTS idx_nft_transfers_block ON nft_transfers (block_number DESC);
+
+ALTER TABLE nfts ADD COLUMN IF NOT EXISTS owner_address VARCHAR(42);
+
+-- Until Transfer events are indexed the minter is the best known owner
+UPDATE nfts SET owner_address = LOWER(user_address)
+WHERE is_mint = 2 AND owner_address IS NULL AND user_address IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_nfts_owner_address ON nfts (owner_address) WHERE is_mint = 2;
+
+-- Last block scanned by the Transfer catch-up task (single row)
+CREATE TABLE IF NOT EXISTS nft_transfer_sync (
+    id              BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    last_block      BIGINT NOT NULL,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,44 @@
+-- Migration: Burn-to-redeem
+-- Description: When a minted NFT is burned (ERC-721 Transfer to the zero address) its chips can
+--              return to circulation, either by resetting the NFT to the unassigned pool or by
+--              importing a new edition of it. Every burn is recorded in nft_redemptions.
+
+ALTER TABLE nfts ADD COLUMN IF NOT EXISTS edition INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE nfts ADD COLUMN IF NOT EXISTS burned_at TIMESTAMPTZ;                      -- new_edition: the burned predecessor
+ALTER TABLE nfts ADD COLUMN IF NOT EXISTS previous_nft_id INTEGER REFERENCES nfts(id);  -- new_edition: edition this one replaces
+
+-- Minted stays terminal, except for a burn reset which must bump the edition (2 -> 0)
+CREATE OR REPLACE FUNCTION enforce_nft_mint_transition()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.is_mint IS DISTINCT FROM OLD.is_mint AND NOT (
+        (OLD.is_mint = 0 AND NEW.is_mint IN (1, 2)) OR
+        (OLD.is_mint = 1 AND NEW.is_mint IN (0, 2)) OR
+        (OLD.is_mint = 2 AND NEW.is_mint = 0 AND NEW.edition > OLD.edition)
+    ) THEN
+        RAISE EXCEPTION 'illegal is_mint transition % -> % for nft %', OLD.is_mint, NEW.is_mint, OLD.id
+            USING ERRCODE = 'check_violation';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TABLE IF NOT EXISTS nft_redemptions (
+    id              BIGSERIAL PRIMARY KEY,
+    token_id        BIGINT NOT NULL,
+    nft_id          INTEGER REFERENCES nfts(id),   -- NULL when the token is unknown
+    burner          VARCHAR(42) NOT NULL,          -- lowercase, owner at burn time
+    mode            VARCHAR(16) NOT NULL CHECK (mode IN ('reset', 'new_edition')),
+    status          VARCHAR(16) NOT NULL CHECK (status IN ('redeemed', 'rejected')),
+    reason          TEXT,                          -- why a burn was rejected
+    new_nft_id      INTEGER REFERENCES nfts(id),   -- new_edition: the imported NFT
+    chips_released  INTEGER NOT NULL DEFAULT 0,
+    tx_hash         VARCHAR(66) NOT NULL,
+    log_index       BIGINT NOT NULL,
+    block_number    BIGINT NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (tx_hash, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_nft_redemptions_status ON nft_redemptions (status, id DESC);
+CREATE INDEX IF NOT EXISTS idx_nft_redemptions_nft ON nft_redemptions (nft_id);
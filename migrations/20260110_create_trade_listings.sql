@@ -0,0 +1,16 @@
+-- Migration: Opt-in chip trade directory
+-- Description: Users who mark themselves open to trade are listed here; only listed addresses
+--              are shown to other users looking for the chips they are missing
+
+CREATE TABLE IF NOT EXISTS trade_listings (
+    user_address    VARCHAR(42) PRIMARY KEY,   -- lowercase
+    note            TEXT,                      -- optional contact / trade note shown to other users
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+DROP TRIGGER IF EXISTS update_trade_listings_updated_at ON trade_listings;
+CREATE TRIGGER update_trade_listings_updated_at
+    BEFORE UPDATE ON trade_listings
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
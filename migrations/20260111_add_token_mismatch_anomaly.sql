@@ -0,0 +1,8 @@
+-- Migration: Token mismatch anomaly
+-- Description: a second UserMint for an NFT that is already minted under another token id is
+--              recorded as an anomaly instead of overwriting the minted token
+
+ALTER TABLE mint_anomalies DROP CONSTRAINT IF EXISTS mint_anomalies_kind_check;
+
+ALTER TABLE mint_anomalies ADD CONSTRAINT mint_anomalies_kind_check
+CHECK (kind IN ('invalid_remark', 'unknown_nft', 'owner_mismatch', 'chips_not_held', 'token_mismatch'));
@@ -0,0 +1,10 @@
+-- Migration: Redeemed token anomaly
+-- Description: a reset redemption keeps the NFT id, so a UserMint of the burned token replayed
+--              later (catch-up scan, stale mint sweep) is recorded instead of completing the new edition
+
+ALTER TABLE mint_anomalies DROP CONSTRAINT IF EXISTS mint_anomalies_kind_check;
+
+ALTER TABLE mint_anomalies ADD CONSTRAINT mint_anomalies_kind_check
+CHECK (kind IN ('invalid_remark', 'unknown_nft', 'owner_mismatch', 'chips_not_held', 'token_mismatch', 'redeemed_token'));
+
+CREATE INDEX IF NOT EXISTS idx_nft_redemptions_nft_token ON nft_redemptions(nft_id, token_id) WHERE status = 'redeemed';
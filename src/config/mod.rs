@@ -1,4 +1,6 @@
 use alloy::primitives::{Address, Uint, Signed};
+use bigdecimal::{BigDecimal, RoundingMode, ToPrimitive, Zero};
+use std::str::FromStr;
 use tracing::info;
 
 /// Pool 配置结构体
@@ -206,6 +208,795 @@ pub fn get_pool_config() -> Result<PoolConfig, String> {
     Ok(config)
 }
 
+/// Token → chip 兑换比例
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChipRatio {
+    /// N chips per whole token (CHIPS_PER_TOKEN, e.g. "2" or "0.5")
+    ChipsPerToken(BigDecimal),
+    /// One chip per N tokens (TOKENS_PER_CHIP, e.g. "0.25" or "10")
+    TokensPerChip(BigDecimal),
+}
+
+/// Chip 分配模式 (CHIP_ALLOCATION_MODE)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChipAllocationMode {
+    /// 按链上余额分配，转出时回收（默认）
+    Balance,
+    /// 按累计 swap 交易量发放，转账不回收
+    SwapVolume,
+    /// 按时间加权平均余额（TWAB）分配，定时任务发放/回收
+    TimeWeighted,
+}
+
+impl FromStr for ChipAllocationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "balance" => Ok(Self::Balance),
+            "swap_volume" => Ok(Self::SwapVolume),
+            "time_weighted" => Ok(Self::TimeWeighted),
+            other => Err(format!("Invalid CHIP_ALLOCATION_MODE: {} (expected balance, swap_volume or time_weighted)", other)),
+        }
+    }
+}
+
+/// 交易量模式：每累计多少交易量（amountIn，可读单位）获得 1 chip，按方向分别配置
+/// 未配置的方向不计入
+#[derive(Debug, Clone, Default)]
+pub struct SwapVolumeUnits {
+    pub buy_unit: Option<BigDecimal>,    // zero_for_one = true (STT -> HakuToken)，单位 STT
+    pub sell_unit: Option<BigDecimal>,   // zero_for_one = false (HakuToken -> STT)，单位 HakuToken
+}
+
+impl SwapVolumeUnits {
+    /// Chips earned from cumulative raw buy/sell volume (floor per direction)
+    pub fn chips_for_volume(&self, buy_volume_raw: &BigDecimal, sell_volume_raw: &BigDecimal, token_decimals: u32) -> i64 {
+        let divisor = BigDecimal::from(10u64.pow(token_decimals));
+        let chips_for = |volume_raw: &BigDecimal, unit: &Option<BigDecimal>| -> i64 {
+            match unit {
+                Some(unit) => (volume_raw / &divisor / unit)
+                    .with_scale_round(0, RoundingMode::Floor)
+                    .to_i64()
+                    .unwrap_or(0)
+                    .max(0),
+                None => 0,
+            }
+        };
+        chips_for(buy_volume_raw, &self.buy_unit) + chips_for(sell_volume_raw, &self.sell_unit)
+    }
+}
+
+/// Chip 分配配置，receive_chips / revert_chips 共用
+#[derive(Debug, Clone)]
+pub struct ChipRatioConfig {
+    pub mode: ChipAllocationMode,
+    pub ratio: ChipRatio,
+    pub min_holding: BigDecimal,   // 最低持仓（可读单位），低于此值不分配 chips
+    pub token_decimals: u32,
+    pub swap_volume: SwapVolumeUnits,
+    pub twab_window_secs: i64,         // time_weighted 模式：TWAB 统计窗口
+    pub twab_sync_interval_secs: u64,  // time_weighted 模式：定时任务间隔
+}
+
+impl Default for ChipRatioConfig {
+    fn default() -> Self {
+        Self {
+            mode: ChipAllocationMode::Balance,
+            ratio: ChipRatio::ChipsPerToken(BigDecimal::from(1)),
+            min_holding: BigDecimal::zero(),
+            token_decimals: 18,
+            swap_volume: SwapVolumeUnits::default(),
+            twab_window_secs: 86400,
+            twab_sync_interval_secs: 3600,
+        }
+    }
+}
+
+impl ChipRatioConfig {
+    /// 从环境变量加载 chip 兑换配置
+    /// CHIPS_PER_TOKEN 与 TOKENS_PER_CHIP 只能设置一个，都未设置时为 1 chip / token
+    pub fn from_env() -> Result<Self, String> {
+        dotenv::dotenv().ok();
+
+        let parse_positive = |key: &str| -> Result<Option<BigDecimal>, String> {
+            match std::env::var(key) {
+                Ok(value) if !value.trim().is_empty() => {
+                    let parsed = BigDecimal::from_str(value.trim())
+                        .map_err(|e| format!("Invalid {}: {}", key, e))?;
+                    if parsed <= BigDecimal::zero() {
+                        return Err(format!("Invalid {}: {} (must be > 0)", key, value));
+                    }
+                    Ok(Some(parsed))
+                }
+                _ => Ok(None),
+            }
+        };
+
+        let ratio = match (parse_positive("CHIPS_PER_TOKEN")?, parse_positive("TOKENS_PER_CHIP")?) {
+            (Some(_), Some(_)) => {
+                return Err("CHIPS_PER_TOKEN and TOKENS_PER_CHIP are mutually exclusive".to_string());
+            }
+            (Some(chips), None) => ChipRatio::ChipsPerToken(chips),
+            (None, Some(tokens)) => ChipRatio::TokensPerChip(tokens),
+            (None, None) => ChipRatio::ChipsPerToken(BigDecimal::from(1)),
+        };
+
+        let min_holding = match std::env::var("MIN_HOLDING_TOKENS") {
+            Ok(value) if !value.trim().is_empty() => BigDecimal::from_str(value.trim())
+                .map_err(|e| format!("Invalid MIN_HOLDING_TOKENS: {}", e))?,
+            _ => BigDecimal::zero(),
+        };
+
+        let token_decimals = std::env::var("TOKEN_DECIMALS")
+            .ok()
+            .and_then(|s| s.parse::<u32>().ok())
+            .unwrap_or(18);
+
+        let mode = match std::env::var("CHIP_ALLOCATION_MODE") {
+            Ok(value) if !value.trim().is_empty() => value.parse::<ChipAllocationMode>()?,
+            _ => ChipAllocationMode::Balance,
+        };
+
+        let swap_volume = SwapVolumeUnits {
+            buy_unit: parse_positive("SWAP_VOLUME_UNIT_BUY")?,
+            sell_unit: parse_positive("SWAP_VOLUME_UNIT_SELL")?,
+        };
+
+        if mode == ChipAllocationMode::SwapVolume
+            && swap_volume.buy_unit.is_none()
+            && swap_volume.sell_unit.is_none()
+        {
+            return Err("swap_volume mode requires SWAP_VOLUME_UNIT_BUY and/or SWAP_VOLUME_UNIT_SELL".to_string());
+        }
+
+        let twab_window_secs = std::env::var("TWAB_WINDOW_SECS")
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(86400);
+
+        let twab_sync_interval_secs = std::env::var("TWAB_SYNC_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(3600);
+
+        Ok(Self {
+            mode,
+            ratio,
+            min_holding,
+            token_decimals,
+            swap_volume,
+            twab_window_secs,
+            twab_sync_interval_secs,
+        })
+    }
+
+    /// Convert a raw token balance into the number of chips it entitles to (floor)
+    pub fn chips_for_balance(&self, balance_raw: &BigDecimal) -> i64 {
+        let divisor = BigDecimal::from(10u64.pow(self.token_decimals));
+        let balance = balance_raw / &divisor;
+
+        if balance <= BigDecimal::zero() || balance < self.min_holding {
+            return 0;
+        }
+
+        let chips = match &self.ratio {
+            ChipRatio::ChipsPerToken(n) => &balance * n,
+            ChipRatio::TokensPerChip(n) => &balance / n,
+        };
+
+        // 向下取整 (floor)
+        chips.with_scale_round(0, RoundingMode::Floor)
+            .to_i64()
+            .unwrap_or(0)
+    }
+
+    /// 每个 token 对应的 chips 数量（展示用）
+    pub fn chips_per_token(&self) -> BigDecimal {
+        match &self.ratio {
+            ChipRatio::ChipsPerToken(n) => n.clone(),
+            ChipRatio::TokensPerChip(n) => BigDecimal::from(1) / n,
+        }
+    }
+
+    /// 每个 chip 需要的 token 数量（展示用）
+    pub fn tokens_per_chip(&self) -> BigDecimal {
+        match &self.ratio {
+            ChipRatio::ChipsPerToken(n) => BigDecimal::from(1) / n,
+            ChipRatio::TokensPerChip(n) => n.clone(),
+        }
+    }
+}
+
+/// 获取 chip 兑换配置
+pub fn get_chip_ratio_config() -> Result<ChipRatioConfig, String> {
+    ChipRatioConfig::from_env()
+}
+
+/// 防刷（reroll）配置
+#[derive(Debug, Clone)]
+pub struct AntiRerollConfig {
+    pub detection_window_secs: i64,   // 来回转账检测窗口
+    pub cooldown_secs: i64,           // 释放 chips 后的冷却期，期间优先归还原 chips（0 = 关闭）
+}
+
+impl AntiRerollConfig {
+    /// 从环境变量加载防刷配置（REROLL_WINDOW_SECS / REROLL_COOLDOWN_SECS）
+    pub fn from_env() -> Self {
+        dotenv::dotenv().ok();
+
+        let detection_window_secs = std::env::var("REROLL_WINDOW_SECS")
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(600);
+
+        let cooldown_secs = std::env::var("REROLL_COOLDOWN_SECS")
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+            .filter(|secs| *secs >= 0)
+            .unwrap_or(3600);
+
+        Self {
+            detection_window_secs,
+            cooldown_secs,
+        }
+    }
+}
+
+/// 获取防刷配置
+pub fn get_anti_reroll_config() -> AntiRerollConfig {
+    AntiRerollConfig::from_env()
+}
+
+/// 卡在 is_mint = 1（申请中）的 NFT 清理配置
+#[derive(Debug, Clone)]
+pub struct MintSweepConfig {
+    pub timeout_secs: i64,        // 申请中超过该时长视为过期
+    pub interval_secs: u64,       // 清理任务运行间隔
+    pub lookback_blocks: u64,     // 链上查找 UserMint 事件的回溯区块数
+}
+
+impl MintSweepConfig {
+    /// 从环境变量加载（MINT_PENDING_TIMEOUT_SECS / MINT_SWEEP_INTERVAL_SECS / MINT_SWEEP_LOOKBACK_BLOCKS）
+    pub fn from_env() -> Self {
+        dotenv::dotenv().ok();
+
+        let timeout_secs = std::env::var("MINT_PENDING_TIMEOUT_SECS")
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(900);
+
+        let interval_secs = std::env::var("MINT_SWEEP_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(60);
+
+        let lookback_blocks = std::env::var("MINT_SWEEP_LOOKBACK_BLOCKS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|blocks| *blocks > 0)
+            .unwrap_or(50_000);
+
+        Self {
+            timeout_secs,
+            interval_secs,
+            lookback_blocks,
+        }
+    }
+}
+
+/// 获取申请中 NFT 清理配置
+pub fn get_mint_sweep_config() -> MintSweepConfig {
+    MintSweepConfig::from_env()
+}
+
+/// EIP-712 mint voucher 配置
+#[derive(Debug, Clone)]
+pub struct VoucherConfig {
+    pub signer_key: String,          // 签名私钥（VOUCHER_SIGNER_KEY，未设置时使用 PRIVATE_KEY）
+    pub domain_name: String,         // EIP-712 domain name
+    pub domain_version: String,      // EIP-712 domain version
+    pub chain_id: Option<u64>,       // 未设置时从 RPC 查询
+    pub ttl_secs: i64,               // voucher 有效期
+}
+
+impl VoucherConfig {
+    /// 从环境变量加载 voucher 配置
+    pub fn from_env() -> Result<Self, String> {
+        dotenv::dotenv().ok();
+
+        let signer_key = std::env::var("VOUCHER_SIGNER_KEY")
+            .or_else(|_| std::env::var("PRIVATE_KEY"))
+            .map_err(|_| "VOUCHER_SIGNER_KEY or PRIVATE_KEY not set")?;
+
+        let domain_name = std::env::var("VOUCHER_DOMAIN_NAME")
+            .unwrap_or_else(|_| "HakuNFT".to_string());
+
+        let domain_version = std::env::var("VOUCHER_DOMAIN_VERSION")
+            .unwrap_or_else(|_| "1".to_string());
+
+        let chain_id = match std::env::var("CHAIN_ID") {
+            Ok(value) => Some(value.trim().parse::<u64>()
+                .map_err(|e| format!("Invalid CHAIN_ID: {}", e))?),
+            Err(_) => None,
+        };
+
+        let ttl_secs = std::env::var("VOUCHER_TTL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(600);
+
+        Ok(Self {
+            signer_key,
+            domain_name,
+            domain_version,
+            chain_id,
+            ttl_secs,
+        })
+    }
+}
+
+/// 获取 voucher 配置
+pub fn get_voucher_config() -> Result<VoucherConfig, String> {
+    VoucherConfig::from_env()
+}
+
+/// Sign-In-With-Ethereum 配置
+#[derive(Debug, Clone)]
+pub struct SiweConfig {
+    pub domain: String,              // 期望的消息 domain（SIWE_DOMAIN，必填）
+    pub uri: String,                 // 期望的消息 URI 前缀（SIWE_URI，默认 https://{domain}）
+    pub chain_id: u64,               // 期望的链 ID（CHAIN_ID，必填）
+    pub nonce_ttl_secs: i64,         // nonce 有效期
+    pub session_ttl_secs: i64,       // 登录会话有效期
+}
+
+impl SiweConfig {
+    /// 从环境变量加载 SIWE 配置（domain 与链 ID 缺失时拒绝登录，不做降级）
+    pub fn from_env() -> Result<Self, String> {
+        dotenv::dotenv().ok();
+
+        let domain = std::env::var("SIWE_DOMAIN")
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .ok_or("SIWE_DOMAIN not set")?;
+
+        let uri = std::env::var("SIWE_URI")
+            .ok()
+            .map(|s| s.trim().trim_end_matches('/').to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| format!("https://{}", domain));
+
+        let chain_id = std::env::var("CHAIN_ID")
+            .map_err(|_| "CHAIN_ID not set")?
+            .trim()
+            .parse::<u64>()
+            .map_err(|e| format!("Invalid CHAIN_ID: {}", e))?;
+
+        let nonce_ttl_secs = std::env::var("SIWE_NONCE_TTL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(600);
+
+        let session_ttl_secs = std::env::var("SIWE_SESSION_TTL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(86400);
+
+        Ok(Self {
+            domain,
+            uri,
+            chain_id,
+            nonce_ttl_secs,
+            session_ttl_secs,
+        })
+    }
+}
+
+/// 获取 SIWE 配置
+pub fn get_siwe_config() -> Result<SiweConfig, String> {
+    SiweConfig::from_env()
+}
+
+/// 批量 mint 配置
+#[derive(Debug, Clone)]
+pub struct MintBatchConfig {
+    pub max_size: usize,          // 单次批量 mint 最多的 NFT 数量
+}
+
+impl MintBatchConfig {
+    /// 从环境变量加载（MINT_BATCH_MAX_SIZE）
+    pub fn from_env() -> Self {
+        dotenv::dotenv().ok();
+
+        let max_size = std::env::var("MINT_BATCH_MAX_SIZE")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|size| *size > 0)
+            .unwrap_or(10);
+
+        Self { max_size }
+    }
+}
+
+/// 获取批量 mint 配置
+pub fn get_mint_batch_config() -> MintBatchConfig {
+    MintBatchConfig::from_env()
+}
+
+/// IPFS 客户端配置
+#[derive(Debug, Clone)]
+pub struct IpfsConfig {
+    pub api_url: Option<String>,      // IPFS HTTP API（如 http://127.0.0.1:5001），设置后优先使用
+    pub gateway: String,              // 未设置 API 时使用的 HTTP 网关
+    pub metadata_cid: String,
+    pub timeout_secs: u64,            // 单次请求超时
+    pub fetch_concurrency: usize,     // 并发获取元数据的上限
+    pub refresh_interval_secs: u64,   // 后台预取图片缓存的间隔
+}
+
+impl IpfsConfig {
+    /// 从环境变量加载（IPFS_API_URL / IPFS_GATEWAY / IPFS_METADATA_CID / IPFS_TIMEOUT_SECS /
+    /// IPFS_FETCH_CONCURRENCY / IPFS_CACHE_REFRESH_SECS）
+    pub fn from_env() -> Result<Self, String> {
+        dotenv::dotenv().ok();
+
+        let api_url = std::env::var("IPFS_API_URL")
+            .ok()
+            .map(|url| url.trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty());
+        let gateway = std::env::var("IPFS_GATEWAY")
+            .unwrap_or_else(|_| "https://nftstorage.link/ipfs".to_string())
+            .trim_end_matches('/')
+            .to_string();
+        let metadata_cid = std::env::var("IPFS_METADATA_CID")
+            .map_err(|_| "IPFS_METADATA_CID not set")?;
+
+        let timeout_secs = std::env::var("IPFS_TIMEOUT_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(10);
+
+        let fetch_concurrency = std::env::var("IPFS_FETCH_CONCURRENCY")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(8);
+
+        let refresh_interval_secs = std::env::var("IPFS_CACHE_REFRESH_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(300);
+
+        Ok(Self {
+            api_url,
+            gateway,
+            metadata_cid,
+            timeout_secs,
+            fetch_concurrency,
+            refresh_interval_secs,
+        })
+    }
+}
+
+/// 获取 IPFS 客户端配置
+pub fn get_ipfs_config() -> Result<IpfsConfig, String> {
+    IpfsConfig::from_env()
+}
+
+/// ERC-721 Transfer 索引配置（NFT 当前持有者与持有历史）
+#[derive(Debug, Clone)]
+pub struct NftTransferSyncConfig {
+    pub start_block: u64,         // 首次补扫的起始区块（通常为 NFT 合约部署区块）
+    pub chunk_blocks: u64,        // 每次 eth_getLogs 查询的区块跨度
+    pub interval_secs: u64,       // 补扫任务运行间隔（弥补 WebSocket 断线期间漏掉的事件）
+}
+
+impl NftTransferSyncConfig {
+    /// 从环境变量加载（NFT_TRANSFER_START_BLOCK / NFT_TRANSFER_CHUNK_BLOCKS / NFT_TRANSFER_SYNC_INTERVAL_SECS）
+    pub fn from_env() -> Self {
+        dotenv::dotenv().ok();
+
+        let start_block = std::env::var("NFT_TRANSFER_START_BLOCK")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(0);
+
+        let chunk_blocks = std::env::var("NFT_TRANSFER_CHUNK_BLOCKS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|blocks| *blocks > 0)
+            .unwrap_or(5_000);
+
+        let interval_secs = std::env::var("NFT_TRANSFER_SYNC_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(300);
+
+        Self {
+            start_block,
+            chunk_blocks,
+            interval_secs,
+        }
+    }
+}
+
+/// 获取 ERC-721 Transfer 索引配置
+pub fn get_nft_transfer_sync_config() -> NftTransferSyncConfig {
+    NftTransferSyncConfig::from_env()
+}
+
+/// NFT 被销毁（burn）后 chips 重新流通的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BurnRedeemMode {
+    /// 原 NFT 重置为未分配（edition + 1），chips 回到未分配池
+    Reset,
+    /// 保留已销毁的 NFT 作为历史，导入一份新 edition（复制 chips），新 chips 进入未分配池
+    NewEdition,
+}
+
+impl BurnRedeemMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Reset => "reset",
+            Self::NewEdition => "new_edition",
+        }
+    }
+}
+
+impl FromStr for BurnRedeemMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "reset" => Ok(Self::Reset),
+            "new_edition" => Ok(Self::NewEdition),
+            other => Err(format!("Invalid BURN_REDEEM_MODE: {} (expected reset or new_edition)", other)),
+        }
+    }
+}
+
+/// Burn-to-redeem 规则
+#[derive(Debug, Clone)]
+pub struct BurnRedeemConfig {
+    pub enabled: bool,                // 关闭时 burn 只记录为 rejected，chips 不释放
+    pub mode: BurnRedeemMode,
+    pub min_mint_age_secs: i64,       // mint 后至少经过该时长才允许赎回（0 = 不限制）
+    pub max_editions: Option<i32>,    // 每个 NFT 最多的 edition 数（含第一版），未设置 = 不限制
+}
+
+impl BurnRedeemConfig {
+    /// 从环境变量加载（BURN_REDEEM_ENABLED / BURN_REDEEM_MODE / BURN_REDEEM_MIN_AGE_SECS / BURN_REDEEM_MAX_EDITIONS）
+    pub fn from_env() -> Result<Self, String> {
+        dotenv::dotenv().ok();
+
+        let enabled = std::env::var("BURN_REDEEM_ENABLED")
+            .map(|s| matches!(s.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+
+        let mode = match std::env::var("BURN_REDEEM_MODE") {
+            Ok(value) if !value.trim().is_empty() => value.parse()?,
+            _ => BurnRedeemMode::Reset,
+        };
+
+        let min_mint_age_secs = std::env::var("BURN_REDEEM_MIN_AGE_SECS")
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+            .filter(|secs| *secs >= 0)
+            .unwrap_or(0);
+
+        let max_editions = std::env::var("BURN_REDEEM_MAX_EDITIONS")
+            .ok()
+            .and_then(|s| s.parse::<i32>().ok())
+            .filter(|editions| *editions > 0);
+
+        Ok(Self {
+            enabled,
+            mode,
+            min_mint_age_secs,
+            max_editions,
+        })
+    }
+}
+
+/// 获取 burn-to-redeem 配置
+pub fn get_burn_redeem_config() -> Result<BurnRedeemConfig, String> {
+    BurnRedeemConfig::from_env()
+}
+
+/// 元数据属性模板：`trait_type[|display_type]=value 模板`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeTemplate {
+    pub trait_type: String,
+    pub display_type: Option<String>,  // number / boost_number / boost_percentage / date
+    pub value: String,
+}
+
+/// 解析 METADATA_ATTRIBUTES（分号分隔，如 `Chips|number={chip_count};Rarity=Common`）
+pub fn parse_attribute_templates(value: &str) -> Result<Vec<AttributeTemplate>, String> {
+    value.split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (name, template) = entry.split_once('=')
+                .ok_or_else(|| format!("Invalid attribute template (missing '='): {}", entry))?;
+            let (trait_type, display_type) = match name.split_once('|') {
+                Some((trait_type, display_type)) => (trait_type.trim(), Some(display_type.trim().to_string())),
+                None => (name.trim(), None),
+            };
+            if trait_type.is_empty() {
+                return Err(format!("Invalid attribute template (empty trait_type): {}", entry));
+            }
+            Ok(AttributeTemplate {
+                trait_type: trait_type.to_string(),
+                display_type,
+                value: template.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// ERC-721 元数据（GET /api/metadata/{token_id}）配置
+#[derive(Debug, Clone)]
+pub struct MetadataConfig {
+    pub name_template: String,                  // 如 `Haku #{token_id}`
+    pub description: String,
+    pub image_template: String,                 // 默认 `ipfs://{image_cid}/{file_name}`
+    pub external_url_template: Option<String>,
+    pub image_cid: String,                      // IPFS_IMAGE_CID
+    pub attributes: Vec<AttributeTemplate>,
+}
+
+impl MetadataConfig {
+    /// 从环境变量加载（METADATA_NAME_TEMPLATE / METADATA_DESCRIPTION / METADATA_IMAGE_TEMPLATE /
+    /// METADATA_EXTERNAL_URL_TEMPLATE / METADATA_ATTRIBUTES）
+    pub fn from_env() -> Result<Self, String> {
+        dotenv::dotenv().ok();
+
+        let name_template = std::env::var("METADATA_NAME_TEMPLATE")
+            .unwrap_or_else(|_| "Haku #{token_id}".to_string());
+        let description = std::env::var("METADATA_DESCRIPTION")
+            .unwrap_or_else(|_| "A Haku puzzle NFT, assembled from all of its chips.".to_string());
+        let image_template = std::env::var("METADATA_IMAGE_TEMPLATE")
+            .unwrap_or_else(|_| "ipfs://{image_cid}/{file_name}".to_string());
+        let external_url_template = std::env::var("METADATA_EXTERNAL_URL_TEMPLATE")
+            .ok()
+            .filter(|s| !s.is_empty());
+        let image_cid = std::env::var("IPFS_IMAGE_CID").unwrap_or_default();
+        let attributes = parse_attribute_templates(
+            &std::env::var("METADATA_ATTRIBUTES").unwrap_or_else(|_| {
+                "Chips|number={chip_count};Mint Block|number={mint_block};Completed|date={completed_at}".to_string()
+            }),
+        )?;
+
+        Ok(Self {
+            name_template,
+            description,
+            image_template,
+            external_url_template,
+            image_cid,
+            attributes,
+        })
+    }
+}
+
+/// 获取元数据配置
+pub fn get_metadata_config() -> Result<MetadataConfig, String> {
+    MetadataConfig::from_env()
+}
+
+/// 后端签名账户池与 gas 策略配置
+#[derive(Debug, Clone)]
+pub struct SignerConfig {
+    pub keys: Vec<String>,                   // 签名私钥（SIGNER_KEYS 逗号分隔，未设置时使用 PRIVATE_KEY）
+    pub rpc_url: String,
+    pub max_fee_per_gas_cap: Option<u128>,   // wei，MAX_FEE_PER_GAS_GWEI
+    pub max_priority_fee_cap: Option<u128>,  // wei，MAX_PRIORITY_FEE_GWEI
+    pub gas_limit_buffer_percent: u64,       // 在 estimate_gas 基础上增加的比例
+    pub stuck_tx_timeout_secs: u64,          // 超过该时长未确认则加价替换
+    pub fee_bump_percent: u64,               // 每次替换的加价比例（节点要求 >= 10%，更低时加载失败）
+    pub max_replacements: u32,               // 最多替换次数
+}
+
+fn gwei_to_wei(value: &str) -> Result<u128, String> {
+    let gwei = BigDecimal::from_str(value.trim())
+        .map_err(|e| format!("Invalid gwei value {}: {}", value, e))?;
+    (gwei * BigDecimal::from(1_000_000_000u64))
+        .with_scale_round(0, RoundingMode::Floor)
+        .to_u128()
+        .ok_or_else(|| format!("Invalid gwei value: {}", value))
+}
+
+/// 解析替换加价比例，默认 15；低于 10 的替换交易会被节点拒绝，直接报错
+fn parse_fee_bump_percent(value: Option<&str>) -> Result<u64, String> {
+    let Some(value) = value else {
+        return Ok(15);
+    };
+    let percent = value.trim().parse::<u64>()
+        .map_err(|e| format!("Invalid FEE_BUMP_PERCENT {}: {}", value, e))?;
+    if percent < 10 {
+        return Err(format!("FEE_BUMP_PERCENT must be at least 10, got {}", percent));
+    }
+    Ok(percent)
+}
+
+impl SignerConfig {
+    /// 从环境变量加载签名账户池配置
+    pub fn from_env() -> Result<Self, String> {
+        dotenv::dotenv().ok();
+
+        let keys: Vec<String> = std::env::var("SIGNER_KEYS")
+            .or_else(|_| std::env::var("PRIVATE_KEY"))
+            .map_err(|_| "SIGNER_KEYS or PRIVATE_KEY not set")?
+            .split(',')
+            .map(|k| k.trim().to_string())
+            .filter(|k| !k.is_empty())
+            .collect();
+        if keys.is_empty() {
+            return Err("SIGNER_KEYS is empty".to_string());
+        }
+
+        let rpc_url = std::env::var("RPC_URL")
+            .unwrap_or_else(|_| "https://dream-rpc.somnia.network".to_string());
+
+        let max_fee_per_gas_cap = std::env::var("MAX_FEE_PER_GAS_GWEI")
+            .ok()
+            .map(|v| gwei_to_wei(&v))
+            .transpose()?;
+
+        let max_priority_fee_cap = std::env::var("MAX_PRIORITY_FEE_GWEI")
+            .ok()
+            .map(|v| gwei_to_wei(&v))
+            .transpose()?;
+
+        let gas_limit_buffer_percent = std::env::var("GAS_LIMIT_BUFFER_PERCENT")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(20);
+
+        let stuck_tx_timeout_secs = std::env::var("STUCK_TX_TIMEOUT_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(120);
+
+        let fee_bump_percent = parse_fee_bump_percent(std::env::var("FEE_BUMP_PERCENT").ok().as_deref())?;
+
+        let max_replacements = std::env::var("MAX_TX_REPLACEMENTS")
+            .ok()
+            .and_then(|s| s.parse::<u32>().ok())
+            .unwrap_or(3);
+
+        Ok(Self {
+            keys,
+            rpc_url,
+            max_fee_per_gas_cap,
+            max_priority_fee_cap,
+            gas_limit_buffer_percent,
+            stuck_tx_timeout_secs,
+            fee_bump_percent,
+            max_replacements,
+        })
+    }
+}
+
+/// 获取签名账户池配置
+pub fn get_signer_config() -> Result<SignerConfig, String> {
+    SignerConfig::from_env()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -236,5 +1027,81 @@ mod tests {
             }
         }
     }
-}
 
+    fn ratio_config(ratio: ChipRatio, min_holding: &str) -> ChipRatioConfig {
+        ChipRatioConfig {
+            ratio,
+            min_holding: BigDecimal::from_str(min_holding).unwrap(),
+            ..ChipRatioConfig::default()
+        }
+    }
+
+    fn tokens(amount: &str) -> BigDecimal {
+        BigDecimal::from_str(amount).unwrap() * BigDecimal::from(10u64.pow(18))
+    }
+
+    #[test]
+    fn test_chips_for_balance_default_ratio() {
+        let config = ChipRatioConfig::default();
+        assert_eq!(config.chips_for_balance(&tokens("0")), 0);
+        assert_eq!(config.chips_for_balance(&tokens("0.99")), 0);
+        assert_eq!(config.chips_for_balance(&tokens("5")), 5);
+        assert_eq!(config.chips_for_balance(&tokens("5.7")), 5);
+    }
+
+    #[test]
+    fn test_chips_for_balance_custom_ratio() {
+        let config = ratio_config(ChipRatio::ChipsPerToken(BigDecimal::from(3)), "0");
+        assert_eq!(config.chips_for_balance(&tokens("2.5")), 7);
+
+        let config = ratio_config(ChipRatio::TokensPerChip(BigDecimal::from_str("0.25").unwrap()), "0");
+        assert_eq!(config.chips_for_balance(&tokens("1.3")), 5);
+
+        let config = ratio_config(ChipRatio::TokensPerChip(BigDecimal::from(10)), "0");
+        assert_eq!(config.chips_for_balance(&tokens("29.9")), 2);
+    }
+
+    #[test]
+    fn test_chips_for_balance_min_holding() {
+        let config = ratio_config(ChipRatio::ChipsPerToken(BigDecimal::from(1)), "10");
+        assert_eq!(config.chips_for_balance(&tokens("9.99")), 0);
+        assert_eq!(config.chips_for_balance(&tokens("10")), 10);
+    }
+
+    #[test]
+    fn test_chips_for_volume_per_direction() {
+        let units = SwapVolumeUnits {
+            buy_unit: Some(BigDecimal::from(10)),
+            sell_unit: Some(BigDecimal::from_str("2.5").unwrap()),
+        };
+        assert_eq!(units.chips_for_volume(&tokens("35"), &tokens("6"), 18), 3 + 2);
+
+        let buy_only = SwapVolumeUnits {
+            buy_unit: Some(BigDecimal::from(10)),
+            sell_unit: None,
+        };
+        assert_eq!(buy_only.chips_for_volume(&tokens("35"), &tokens("1000"), 18), 3);
+    }
+
+    #[test]
+    fn test_parse_attribute_templates() {
+        let templates = parse_attribute_templates("Chips|number={chip_count}; Rarity=Common;").unwrap();
+        assert_eq!(templates.len(), 2);
+        assert_eq!(templates[0].trait_type, "Chips");
+        assert_eq!(templates[0].display_type.as_deref(), Some("number"));
+        assert_eq!(templates[0].value, "{chip_count}");
+        assert_eq!(templates[1].display_type, None);
+
+        assert!(parse_attribute_templates("Chips").is_err());
+        assert!(parse_attribute_templates("=x").is_err());
+    }
+
+    #[test]
+    fn test_parse_fee_bump_percent() {
+        assert_eq!(parse_fee_bump_percent(None), Ok(15));
+        assert_eq!(parse_fee_bump_percent(Some("10")), Ok(10));
+        assert_eq!(parse_fee_bump_percent(Some(" 25 ")), Ok(25));
+        assert!(parse_fee_bump_percent(Some("9")).is_err());
+        assert!(parse_fee_bump_percent(Some("abc")).is_err());
+    }
+}
@@ -64,6 +64,8 @@ pub enum AppEvent {
     KlineUpdate(KlineUpdateEvent),
     UserMint(UserMintEvent),
     Transfer(TransferEvent),
+    MintJob(MintJobEvent),
+    NftTransfer(NftTransferEvent),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -106,6 +108,18 @@ pub struct UserMintEvent {
     pub token_url: String,
 }
 
+/// ERC-721 Transfer of the NFT contract (from = zero address for mints)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NftTransferEvent {
+    pub from: String,
+    pub to: String,
+    pub token_id: String,
+    pub block_number: u64,
+    pub log_index: u64,
+    pub tx_hash: String,
+    pub block_timestamp: Option<u64>,  // 节点未在日志中返回时为 None
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TransferEvent {
     pub from: String,
@@ -115,4 +129,106 @@ pub struct TransferEvent {
     pub timestamp_str: String,
     pub block_number: u64,
     pub mint_remark: Option<String>,  // ✅ 新增：来自 HakuNFTMint 事件的 remark
+    pub tx_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct BlacklistEntry {
+    pub address: String,
+    pub reason: Option<String>,
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct RerollFlag {
+    pub id: i64,
+    pub address_a: String,
+    pub address_b: String,
+    pub round_trips: i32,
+    pub transfer_count: i32,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub status: String,
+    pub review_note: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct MintAnomaly {
+    pub id: i64,
+    pub kind: String,
+    pub remark: String,
+    pub nft_id: Option<i32>,
+    pub token_id: Option<i64>,
+    pub minter: String,
+    pub recorded_owner: Option<String>,
+    pub detail: String,
+    pub block_number: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct NftTransfer {
+    pub id: i64,
+    pub token_id: i64,
+    pub from_address: String,
+    pub to_address: String,
+    pub block_number: i64,
+    pub log_index: i64,
+    pub tx_hash: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct NftRedemption {
+    pub id: i64,
+    pub token_id: i64,
+    pub nft_id: Option<i32>,
+    pub burner: String,
+    pub mode: String,
+    pub status: String,
+    pub reason: Option<String>,
+    pub new_nft_id: Option<i32>,
+    pub chips_released: i32,
+    pub tx_hash: String,
+    pub log_index: i64,
+    pub block_number: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct TradeListing {
+    pub user_address: String,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct MintAttempt {
+    pub id: i64,
+    pub nft_id: i32,
+    pub user_address: String,
+    pub mode: String,
+    pub status: String,
+    pub tx_hash: Option<String>,
+    pub token_id: Option<i64>,
+    pub error: Option<String>,
+    pub mint_param: Option<i64>,
+    pub batch_id: Option<i64>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MintJobEvent {
+    pub job_id: i64,
+    pub batch_id: Option<i64>,
+    pub nft_id: i32,
+    pub user: String,
+    pub status: String,          // confirmed / failed
+    pub tx_hash: Option<String>,
+    pub error: Option<String>,
 }
@@ -2,36 +2,49 @@ use alloy::{
     providers::{Provider, ProviderBuilder, WsConnect},
     sol,
     rpc::types::Filter,
-    primitives::Address,
-    signers::local::PrivateKeySigner,
-    network::EthereumWallet,
+    primitives::{Address, Bytes},
 };
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, Mutex, Notify};
 use tracing::{info, error, warn};
 use axum::{
     Router,
-    extract::{State, WebSocketUpgrade, ws::{Message, WebSocket}, Path},
-    routing::{get, post},
+    extract::{State, WebSocketUpgrade, ws::{Message, WebSocket}, Path, FromRequestParts},
+    routing::{get, post, delete},
     response::{Json, IntoResponse, Response},
-    http::{StatusCode, header},
+    http::{StatusCode, header, HeaderMap, request::Parts},
     body::Body,
     extract::Query,
 };
 use serde::{Serialize, Deserialize};
 use moka::future::Cache;
 use moka::Expiry;
-use std::{sync::Arc, time::{Duration, Instant}};
+use std::{collections::HashSet, sync::Arc, time::{Duration, Instant}};
 use futures::stream::StreamExt;
 use chrono::{DateTime, TimeZone, Utc};
 use sqlx::PgPool;
-use bigdecimal::BigDecimal;
+use sqlx::postgres::PgListener;
+use bigdecimal::{BigDecimal, Signed};
 use tokio::fs::File;
 use tokio_util::io::ReaderStream;
 
 use crate::services::service::root;
 use crate::services::service::insert_swap_request;
 use crate::services::service::update_kline;
-use crate::entitys::entity::{AppEvent, SwapEvent, AirdropEvent, KlineUpdateEvent, UserMintEvent, TransferEvent};
+use crate::services::mint_state::{MintState, MintMode, list_mint_attempts, find_attempt_by_tx_hash, open_mint_attempt, begin_mint_attempt, mark_attempt_submitted, attach_attempt_voucher, fail_mint_attempt, abandon_mint_attempt, next_backend_mint_job, get_mint_attempt, MintBatchStart, begin_mint_batch, pending_batch_jobs};
+use crate::services::mint_voucher::{SignedMintVoucher, VoucherDomain, sign_mint_voucher};
+use crate::services::siwe::SiweError;
+use crate::services::signer::{SentTransaction, get_signer_pool};
+use crate::services::mint_simulation::{RevertReason, simulate_call};
+use crate::services::mint_remark::MintRemark;
+use crate::services::ipfs::{IpfsClient, resolve_image_urls, refresh_image_cache};
+use crate::services::gallery::{GalleryCursor, GalleryFilter, GallerySort, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE, query_gallery};
+use crate::config::BurnRedeemConfig;
+use crate::services::nft_ownership::{decode_nft_transfer, is_burn, record_nft_transfer, sync_nft_transfers, find_minted_nft, nfts_owned_by, ownership_history, NftOwnership};
+use crate::services::mint_eligibility::explain_mint_eligibility;
+use crate::services::trade_directory::{MAX_TRADE_NOTE_LEN, open_to_trade, close_to_trade, get_trade_listing, missing_chip_holders};
+use crate::services::nft_metadata::{build_metadata, load_metadata_source};
+use crate::services::mint_status::{MintReceiptStatus, MintTxOutcome, fetch_mint_receipt, check_mint_transaction};
+use crate::entitys::entity::{AppEvent, SwapEvent, AirdropEvent, KlineUpdateEvent, UserMintEvent, TransferEvent, BlacklistEntry, RerollFlag, MintAttempt, MintJobEvent, MintAnomaly, NftTransfer, NftRedemption, TradeListing};
 // Define the Airdropped event using the sol! macro
 sol! {
     #[derive(Debug)]
@@ -115,7 +128,9 @@ impl Expiry<String, (Expiration, (Vec<u8>, Vec<u8>))> for MyExpiry {
     }
 }
 
-pub fn get_app_cache() -> Cache<String, (Expiration, (Vec<u8>, Vec<u8>))> {
+pub type AppCache = Cache<String, (Expiration, (Vec<u8>, Vec<u8>))>;
+
+pub fn get_app_cache() -> AppCache {
     let eviction_listener = |key: Arc<String>, _value: (Expiration, (Vec<u8>, Vec<u8>)), cause: moka::notification::RemovalCause| {
         info!("======== Evicted key {key}. Cause: {cause:?} =========");
     };
@@ -172,6 +187,20 @@ pub struct UserMintResponse {
     pub nfts: Vec<NftDetail>,
 }
 
+// Query parameters for the minted NFTs gallery (all optional)
+#[derive(Debug, Deserialize)]
+pub struct MintedNftsQuery {
+    pub owner: Option<String>,
+    pub token_id_min: Option<i64>,
+    pub token_id_max: Option<i64>,
+    pub minted_from: Option<DateTime<Utc>>,  // RFC 3339，含
+    pub minted_to: Option<DateTime<Utc>>,    // RFC 3339，不含
+    pub file_name: Option<String>,           // 文件名子串，不区分大小写
+    pub sort: Option<String>,                // newest（默认） / oldest / token_id_asc / token_id_desc
+    pub cursor: Option<String>,              // 上一页返回的 next_cursor
+    pub limit: Option<i64>,                  // 默认 10，最大 100
+}
+
 // Response structure for minted NFTs query
 #[derive(Debug, Serialize)]
 pub struct MintedNftItem {
@@ -179,12 +208,33 @@ pub struct MintedNftItem {
     pub token_id: Option<String>,
     pub token_url: Option<String>,
     pub image_url: Option<String>,  // 新增：NFT的图片URL
+    pub owner: Option<String>,
+    pub file_name: Option<String>,
+    pub block_number: Option<i64>,
+    pub minted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct MintedNftsResponse {
-    pub total: i32,
+    pub total: i64,                   // 满足筛选条件的总数
     pub nfts: Vec<MintedNftItem>,
+    pub next_cursor: Option<String>,  // 为空表示没有下一页
+}
+
+// Response structure for NFT detail (current owner and ownership history)
+#[derive(Debug, Serialize)]
+pub struct NftDetailResponse {
+    #[serde(flatten)]
+    pub nft: NftOwnership,
+    pub transfers: Vec<NftTransfer>,   // 按区块顺序，第一条通常为 mint（from 为零地址）
+}
+
+// Response structure for NFTs held by an address
+#[derive(Debug, Serialize)]
+pub struct OwnerNftsResponse {
+    pub owner: String,
+    pub total: usize,
+    pub nfts: Vec<NftOwnership>,
 }
 
 // Response structure for user safe mint
@@ -193,6 +243,8 @@ pub struct UserSafeMintResponse {
     pub success: bool,
     pub message: String,
     pub tx_hash: Option<String>,
+    pub job_id: Option<i64>,        // Backend mint job id (GET /api/mint-jobs/{id})
+    pub revert_reason: Option<RevertReason>,  // Decoded revert if the pre-flight simulation failed
     pub nft_id: String,
     pub user_address: String,
 }
@@ -205,6 +257,46 @@ pub struct MintEligibilityResponse {
     pub contract_address: Option<String>,
     pub token_id: Option<String>,
     pub uint256_param: Option<u64>,
+    pub voucher: Option<SignedMintVoucher>,  // EIP-712 voucher signed by the backend (self-pay mode)
+}
+
+// Request body for batch mint (backend or self-pay)
+#[derive(Debug, Deserialize)]
+pub struct BatchMintRequest {
+    pub user_address: String,
+    pub nft_ids: Vec<String>,
+}
+
+// Response structure for batch backend mint
+#[derive(Debug, Serialize)]
+pub struct BatchSafeMintResponse {
+    pub success: bool,
+    pub message: String,
+    pub batch_id: Option<i64>,
+    pub job_ids: Vec<i64>,                    // One backend mint job per NFT, in request order
+    pub nft_ids: Vec<String>,
+    pub ineligible: Vec<String>,              // NFTs that blocked the batch
+    pub revert_reason: Option<RevertReason>,  // Decoded revert if the pre-flight simulation failed
+    pub user_address: String,
+}
+
+// One NFT of a batch self-pay mint
+#[derive(Debug, Serialize)]
+pub struct BatchMintItem {
+    pub token_id: String,
+    pub uint256_param: u64,
+    pub voucher: SignedMintVoucher,
+}
+
+// Response structure for batch mint eligibility (self-pay mode)
+#[derive(Debug, Serialize)]
+pub struct BatchMintEligibilityResponse {
+    pub eligible: bool,
+    pub message: String,
+    pub contract_address: Option<String>,
+    pub batch_id: Option<i64>,
+    pub items: Vec<BatchMintItem>,
+    pub ineligible: Vec<String>,
 }
 
 // Request for mint failed notification
@@ -213,6 +305,7 @@ pub struct MintFailedRequest {
     pub user_address: String,
     pub nft_id: String,
     pub error: Option<String>,
+    pub tx_hash: Option<String>,    // Mint transaction sent by the wallet, if any
 }
 
 // Simple response
@@ -222,6 +315,28 @@ pub struct SimpleResponse {
     pub message: String,
 }
 
+// Query parameters for mint status lookup (one of nft_id / tx_hash)
+#[derive(Debug, Deserialize)]
+pub struct MintStatusQuery {
+    pub nft_id: Option<i32>,
+    pub tx_hash: Option<String>,
+}
+
+// Response structure for mint status lookup
+#[derive(Debug, Serialize)]
+pub struct MintStatusResponse {
+    pub success: bool,
+    pub message: String,
+    pub nft_id: Option<i32>,
+    pub user_address: Option<String>,
+    pub state: Option<String>,                  // unminted / applying / minted (nfts.is_mint)
+    pub token_id: Option<i64>,
+    pub block_number: Option<i64>,
+    pub mint_started_at: Option<DateTime<Utc>>,
+    pub attempts: Vec<MintAttempt>,             // newest first
+    pub receipt: Option<MintReceiptStatus>,     // only when queried by tx_hash
+}
+
 // Query parameters for NFT user chips
 #[derive(Debug, Deserialize)]
 pub struct NftUserChipsQuery {
@@ -275,6 +390,154 @@ pub struct ChipInfoWithBase64 {
     pub base64: Option<String>, // base64 encoded image data URI
 }
 
+// Query parameters for mint eligibility explanation
+#[derive(Debug, Deserialize)]
+pub struct MintExplainQuery {
+    pub user_address: String,
+    pub nft_id: i32,
+}
+
+// Request body for joining the chip trade directory
+#[derive(Debug, Deserialize)]
+pub struct TradeListingRequest {
+    pub note: Option<String>,  // 可选：联系方式或交易说明，最多 280 字符
+}
+
+// Response structure for the caller's trade directory status
+#[derive(Debug, Serialize)]
+pub struct TradeListingResponse {
+    pub listed: bool,
+    pub listing: Option<TradeListing>,
+}
+
+// Query parameters for missing chip holders
+#[derive(Debug, Deserialize)]
+pub struct MissingChipHoldersQuery {
+    pub nft_id: i32,
+}
+
+// Query parameters for revert preview
+#[derive(Debug, Deserialize)]
+pub struct RevertPreviewQuery {
+    pub user_address: String,
+    pub amount: String,  // Human-readable token amount (e.g., "5" or "2.5")
+}
+
+// Response structure for revert preview
+#[derive(Debug, Serialize)]
+pub struct RevertPreviewResponse {
+    pub success: bool,
+    pub message: String,
+    pub user_address: String,
+    pub amount: String,
+    pub token_balance: Option<String>,  // Current raw balance
+    pub balance_after: Option<String>,  // Raw balance after sending `amount`; null when the allocation mode ignores it
+    pub chips_before: i64,
+    pub chips_after: i64,
+    pub released_chips: Vec<ReleasedChipInfo>,
+    pub released_nfts: Vec<i32>,  // NFTs that would be reverted entirely
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ReleasedChipInfo {
+    pub id: i32,
+    pub nft_id: Option<i32>,
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    pub w: Option<i32>,
+    pub h: Option<i32>,
+    pub file_name: Option<String>,
+}
+
+// Request body for adding a blacklist entry
+#[derive(Debug, Deserialize)]
+pub struct BlacklistAddRequest {
+    pub address: String,
+    pub reason: Option<String>,
+}
+
+// Response structure for SIWE nonce issuance
+#[derive(Debug, Serialize)]
+pub struct AuthNonceResponse {
+    pub nonce: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+// Request body for SIWE sign-in
+#[derive(Debug, Deserialize)]
+pub struct AuthVerifyRequest {
+    pub message: String,    // EIP-4361 message
+    pub signature: String,  // personal_sign signature (0x...)
+}
+
+// Response structure for SIWE sign-in
+#[derive(Debug, Serialize)]
+pub struct AuthVerifyResponse {
+    pub success: bool,
+    pub message: String,
+    pub token: Option<String>,       // Bearer token for state-changing user endpoints
+    pub address: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+// Query parameters for listing reroll flags
+#[derive(Debug, Deserialize)]
+pub struct RerollFlagsQuery {
+    pub status: Option<String>,  // open / reviewed / dismissed
+}
+
+// Query parameters for listing mint anomalies
+#[derive(Debug, Deserialize)]
+pub struct MintAnomaliesQuery {
+    pub kind: Option<String>,  // invalid_remark / unknown_nft / owner_mismatch / chips_not_held / token_mismatch / redeemed_token
+}
+
+// Query parameters for listing burn redemptions
+#[derive(Debug, Deserialize)]
+pub struct NftRedemptionsQuery {
+    pub status: Option<String>,  // redeemed / rejected
+}
+
+// Request body for reviewing a reroll flag
+#[derive(Debug, Deserialize)]
+pub struct RerollReviewRequest {
+    pub status: String,  // reviewed / dismissed
+    pub note: Option<String>,
+}
+
+// Response structure for token → chip conversion config
+#[derive(Debug, Serialize)]
+pub struct ChipRatioResponse {
+    pub success: bool,
+    pub message: String,
+    pub allocation_mode: Option<String>,  // "balance", "swap_volume" or "time_weighted"
+    pub twab_window_secs: Option<i64>,
+    pub swap_volume_unit_buy: Option<String>,
+    pub swap_volume_unit_sell: Option<String>,
+    pub mode: Option<String>,             // "chips_per_token" or "tokens_per_chip"
+    pub chips_per_token: Option<String>,
+    pub tokens_per_chip: Option<String>,
+    pub min_holding_tokens: Option<String>,
+    pub token_decimals: Option<u32>,
+}
+
+// Response structure for NFT/chip pool inventory
+#[derive(Debug, Serialize)]
+pub struct InventoryResponse {
+    pub nfts: InventoryCounts,
+    pub chips: InventoryCounts,
+    pub pending_users: i64,   // Users with an open allocation deficit
+    pub pending_chips: i64,   // Sum of all open deficits
+}
+
+#[derive(Debug, Serialize)]
+pub struct InventoryCounts {
+    pub total: i64,
+    pub assigned: i64,  // received = true
+    pub free: i64,      // received = false
+    pub minted: i64,    // is_mint = 2
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NftDetail {
     pub nft_id: i32,
@@ -316,9 +579,10 @@ pub struct SwapRequestRecord {
 
 #[derive(Clone)]
 pub struct AppStatus {
-    pub cache: Cache<String, (Expiration, (Vec<u8>, Vec<u8>))>,
+    pub cache: AppCache,
     pub tx: broadcast::Sender<AppEvent>,
     pub db_pool: PgPool,
+    pub mint_jobs: Arc<Notify>,   // Wakes a mint job worker when a job is queued
 }
 
 pub async fn app_map() -> Router {
@@ -328,6 +592,9 @@ pub async fn app_map() -> Router {
     // 1️⃣ Start WebSocket server
     let (tx, _rx) = broadcast::channel::<AppEvent>(100);
 
+    // Query cache shared by the HTTP handlers and every worker that invalidates it
+    let app_cache = get_app_cache();
+
     // 2️⃣ Initialize database connection pool
     let database_url = std::env::var("DATABASE_URL")
         .expect("DATABASE_URL must be set in .env file");
@@ -337,6 +604,11 @@ pub async fn app_map() -> Router {
     
     info!("Database connected successfully");
 
+    // Seed address blacklist from configured contract addresses
+    if let Err(e) = crate::services::blacklist::seed_blacklist_from_config(&db_pool).await {
+        error!("Failed to seed address blacklist: {:?}", e);
+    }
+
     // 3️⃣ Start Alloy WebSocket Provider (Listen for chain events)
     // 从配置加载合约地址
     let pool_config = crate::config::get_pool_config()
@@ -360,7 +632,7 @@ pub async fn app_map() -> Router {
     // 4️⃣ Spawn database worker task
     let db_pool_clone = db_pool.clone();
     let tx_for_db = tx.clone();
-    let cache_for_db = get_app_cache();
+    let cache_for_db = app_cache.clone();
     tokio::spawn(async move {
         swap_requests_worker(db_pool_clone, tx_for_db, cache_for_db).await;
     });
@@ -380,7 +652,7 @@ pub async fn app_map() -> Router {
     });
 
     // 7️⃣ Spawn Cache Invalidation worker task
-    let cache_clone = get_app_cache();
+    let cache_clone = app_cache.clone();
     let tx_for_cache = tx.clone();
     tokio::spawn(async move {
         cache_invalidation_worker(cache_clone, tx_for_cache).await;
@@ -389,16 +661,81 @@ pub async fn app_map() -> Router {
     // 8️⃣ Spawn User Transfer worker task
     let db_pool_transfer = db_pool.clone();
     let tx_for_transfer = tx.clone();
-    let cache_for_transfer = get_app_cache();
+    let cache_for_transfer = app_cache.clone();
     tokio::spawn(async move {
         user_transfer_worker(db_pool_transfer, tx_for_transfer, cache_for_transfer).await;
     });
 
+    // 9️⃣ Spawn Pending Allocation worker task (fulfil deficits when new NFTs are imported)
+    let db_pool_pending = db_pool.clone();
+    let cache_for_pending = app_cache.clone();
+    tokio::spawn(async move {
+        pending_allocation_worker(db_pool_pending, cache_for_pending).await;
+    });
+
+    // 🔟 Spawn Time-weighted sync worker task (time_weighted allocation mode)
+    let db_pool_twab = db_pool.clone();
+    let cache_for_twab = app_cache.clone();
+    tokio::spawn(async move {
+        time_weighted_sync_worker(db_pool_twab, cache_for_twab).await;
+    });
+
+    // 1️⃣1️⃣ Spawn stale mint sweeper task (NFTs stuck in is_mint=1)
+    let db_pool_sweeper = db_pool.clone();
+    let cache_for_sweeper = app_cache.clone();
+    tokio::spawn(async move {
+        stale_mint_sweeper_worker(db_pool_sweeper, cache_for_sweeper).await;
+    });
+
+    // 1️⃣2️⃣ Spawn backend mint job workers (one per signer; the only tasks that sign safeMint transactions)
+    let mint_jobs = Arc::new(Notify::new());
+    let mint_job_claims: MintJobClaims = Arc::new(Mutex::new(HashSet::new()));
+    // 签名账户池加载失败时仍启动一个 worker，由它把任务标记为失败
+    let signer_count = get_signer_pool().map(|pool| pool.signer_count()).unwrap_or(1);
+    for slot in 0..signer_count {
+        let db_pool_mint_jobs = db_pool.clone();
+        let tx_for_mint_jobs = tx.clone();
+        let cache_for_mint_jobs = app_cache.clone();
+        let mint_jobs_for_worker = mint_jobs.clone();
+        let claims_for_worker = mint_job_claims.clone();
+        tokio::spawn(async move {
+            mint_job_worker(slot, db_pool_mint_jobs, tx_for_mint_jobs, cache_for_mint_jobs, mint_jobs_for_worker, claims_for_worker).await;
+        });
+    }
+
+    // 1️⃣3️⃣ Spawn image cache worker (resolves minted NFTs' image URLs from IPFS in the background)
+    let db_pool_image_cache = db_pool.clone();
+    tokio::spawn(async move {
+        image_cache_worker(db_pool_image_cache).await;
+    });
+
+    // 1️⃣4️⃣ Spawn NFT Transfer worker (current owner and ownership history of minted tokens, burn-to-redeem)
+    let burn_redeem_config = crate::config::get_burn_redeem_config()
+        .expect("Failed to load burn redeem config");
+    info!("🔥 Burn-to-redeem: enabled={}, mode={}, min_age={}s, max_editions={:?}",
+        burn_redeem_config.enabled, burn_redeem_config.mode.as_str(),
+        burn_redeem_config.min_mint_age_secs, burn_redeem_config.max_editions);
+    let db_pool_nft_transfer = db_pool.clone();
+    let tx_for_nft_transfer = tx.clone();
+    let cache_for_nft_transfer = app_cache.clone();
+    let redeem_for_nft_transfer = burn_redeem_config.clone();
+    tokio::spawn(async move {
+        nft_transfer_worker(db_pool_nft_transfer, tx_for_nft_transfer, cache_for_nft_transfer, redeem_for_nft_transfer).await;
+    });
+
+    // 1️⃣5️⃣ Spawn NFT Transfer catch-up task (indexes Transfers missed while the listener was down)
+    let db_pool_nft_transfer_sync = db_pool.clone();
+    let cache_for_nft_transfer_sync = app_cache.clone();
+    tokio::spawn(async move {
+        nft_transfer_sync_worker(db_pool_nft_transfer_sync, cache_for_nft_transfer_sync, burn_redeem_config).await;
+    });
+
     // Shared state
     let shared_state: Arc<AppStatus> = Arc::new(AppStatus {
-        cache: get_app_cache(),
+        cache: app_cache,
         tx,
         db_pool,
+        mint_jobs,
     });
 
     Router::new()
@@ -411,10 +748,32 @@ pub async fn app_map() -> Router {
         .route("/api/verify-mint-eligibility", post(verify_mint_eligibility_api))
         .route("/api/mint-failed", post(mint_failed))
         .route("/api/user-safe-mint", post(user_safe_mint))  // 保留旧接口（后端代付模式）
+        .route("/api/user-safe-mint-batch", post(user_safe_mint_batch))  // 批量后端代付（单笔 multicall 交易）
+        .route("/api/verify-mint-eligibility-batch", post(verify_mint_eligibility_batch))  // 批量自付
+        .route("/api/mint-jobs/{id}", get(get_mint_job))
+        .route("/api/metadata/{token_id}", get(get_token_metadata))  // tokenURI: <base>/api/metadata/{token_id}
+        .route("/api/nfts/{token_id}", get(get_nft_detail))  // 当前持有者 + 持有历史
+        .route("/api/owners/{address}/nfts", get(get_owner_nfts))  // 按当前持有者查询
+        .route("/api/mint-status", get(query_mint_status))  // 按 nft_id 或 tx_hash 查询 mint 进度
+        .route("/api/mint-explain", get(mint_explain))  // 逐项列出不满足的 mint 条件及缺失的 chips
+        .route("/api/trade-directory", get(get_trade_directory_status).put(join_trade_directory).delete(leave_trade_directory))  // 自愿加入的交易目录
+        .route("/api/missing-chip-holders", get(query_missing_chip_holders))  // 仅列出已加入交易目录的持有者
         .route("/api/images/{file_name}", get(serve_image))
         .route("/api/tiles/{file_name}/{tile_name}", get(serve_tile))
         .route("/api/nft-user-chips", get(get_nft_user_chips))
         .route("/api/nft-user-chips-batch", post(get_nft_user_chips_batch))
+        .route("/api/revert-preview", get(revert_preview))
+        .route("/api/inventory", get(query_inventory))
+        .route("/api/chip-ratio", get(query_chip_ratio))
+        .route("/api/auth/nonce", get(auth_nonce))
+        .route("/api/auth/verify", post(auth_verify))
+        .route("/api/auth/logout", post(auth_logout))
+        .route("/api/admin/blacklist", get(list_blacklist).post(add_blacklist))
+        .route("/api/admin/blacklist/{address}", delete(remove_blacklist))
+        .route("/api/admin/reroll-flags", get(list_reroll_flags))
+        .route("/api/admin/reroll-flags/{id}/review", post(review_reroll_flag))
+        .route("/api/admin/mint-anomalies", get(list_mint_anomalies))
+        .route("/api/admin/nft-redemptions", get(list_nft_redemptions))
         .with_state(shared_state)
 }
 
@@ -589,16 +948,14 @@ async fn query_mint(
         let can_mint = 0;
         let nfts: Vec<NftDetail> = vec![];
         
-        if cache_enabled {
-            if let (Ok(can_mint_bytes), Ok(nfts_bytes)) = (
-                serde_json::to_vec(&can_mint),
-                serde_json::to_vec(&nfts)
-            ) {
-                state.cache.insert(
-                    cache_key,
-                    (Expiration::AfterLongTime, (can_mint_bytes, nfts_bytes))
-                ).await;
-            }
+        if cache_enabled && let (Ok(can_mint_bytes), Ok(nfts_bytes)) = (
+            serde_json::to_vec(&can_mint),
+            serde_json::to_vec(&nfts)
+        ) {
+            state.cache.insert(
+                cache_key,
+                (Expiration::AfterLongTime, (can_mint_bytes, nfts_bytes))
+            ).await;
         }
         
         return Json(UserMintResponse {
@@ -726,117 +1083,247 @@ async fn query_mint(
     })
 }
 
-// 辅助函数：从本地 IPFS 节点获取 NFT 元数据并提取 image URL
-async fn fetch_nft_image_url(token_url: &str) -> Option<String> {
-    // 从环境变量读取 IPFS Metadata CID（必须配置，否则报错）
-    let ipfs_metadata_cid = match std::env::var("IPFS_METADATA_CID") {
-        Ok(cid) => cid,
+// ✅ API Handler: ERC-721 metadata generated from the database
+// GET /api/metadata/{token_id} (a trailing ".json" is accepted)
+async fn get_token_metadata(
+    State(state): State<Arc<AppStatus>>,
+    Path(token_id): Path<String>,
+) -> Response {
+    let token_id_num: i64 = match token_id.strip_suffix(".json").unwrap_or(&token_id).parse() {
+        Ok(id) => id,
         Err(_) => {
-            error!("❌ IPFS_METADATA_CID not set in .env file! Please configure it.");
-            return None;
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(SimpleResponse {
+                    success: false,
+                    message: format!("Invalid token_id: {}", token_id),
+                })
+            ).into_response();
         }
     };
-    
-    let ipfs_metadata_path = format!("{}/{}.json", ipfs_metadata_cid, token_url);
-    
-    // 使用本地 IPFS 节点执行 ipfs cat 命令
-    match tokio::process::Command::new("ipfs")
-        .arg("cat")
-        .arg(&ipfs_metadata_path)
-        .output()
-        .await
-    {
-        Ok(output) => {
-            if output.status.success() {
-                // 解析 JSON 输出
-                match serde_json::from_slice::<serde_json::Value>(&output.stdout) {
-                    Ok(json) => {
-                        // 提取 image 字段
-                        if let Some(image) = json.get("image").and_then(|v| v.as_str()) {
-                            info!("✅ Fetched image URL from local IPFS for {}: {}", token_url, image);
-                            return Some(image.to_string());
-                        } else {
-                            warn!("⚠️ No 'image' field found in metadata for token_url: {}", token_url);
-                        }
-                    }
-                    Err(e) => {
-                        error!("❌ Failed to parse JSON from IPFS path {}: {:?}", ipfs_metadata_path, e);
-                    }
-                }
-            } else {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                warn!("⚠️ IPFS cat failed for {}: {}", ipfs_metadata_path, stderr);
-            }
+
+    let config = match crate::config::get_metadata_config() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Failed to load metadata config: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SimpleResponse {
+                    success: false,
+                    message: format!("Configuration error: {}", e),
+                })
+            ).into_response();
+        }
+    };
+
+    match load_metadata_source(&state.db_pool, token_id_num).await {
+        Ok(Some(source)) => (
+            [(header::CACHE_CONTROL, "public, max-age=300")],
+            Json(build_metadata(&source, &config)),
+        ).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(SimpleResponse {
+                success: false,
+                message: format!("Token {} has not been minted", token_id_num),
+            })
+        ).into_response(),
+        Err(e) => {
+            error!("Failed to load metadata for token {}: {:?}", token_id_num, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SimpleResponse {
+                    success: false,
+                    message: format!("Failed to load metadata: {}", e),
+                })
+            ).into_response()
+        }
+    }
+}
+
+// ✅ API Handler: NFT detail with current owner and ownership history
+// GET /api/nfts/{token_id}
+async fn get_nft_detail(
+    State(state): State<Arc<AppStatus>>,
+    Path(token_id): Path<i64>,
+) -> Response {
+    let nft = match find_minted_nft(&state.db_pool, token_id).await {
+        Ok(Some(nft)) => nft,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(SimpleResponse {
+                    success: false,
+                    message: format!("Token {} has not been minted", token_id),
+                })
+            ).into_response();
+        }
+        Err(e) => {
+            error!("Failed to load NFT for token {}: {:?}", token_id, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SimpleResponse {
+                    success: false,
+                    message: format!("Database error: {}", e),
+                })
+            ).into_response();
+        }
+    };
+
+    match ownership_history(&state.db_pool, token_id).await {
+        Ok(transfers) => Json(NftDetailResponse { nft, transfers }).into_response(),
+        Err(e) => {
+            error!("Failed to load ownership history for token {}: {:?}", token_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SimpleResponse {
+                    success: false,
+                    message: format!("Database error: {}", e),
+                })
+            ).into_response()
         }
+    }
+}
+
+// ✅ API Handler: Minted NFTs currently held by an address
+// GET /api/owners/{address}/nfts
+async fn get_owner_nfts(
+    State(state): State<Arc<AppStatus>>,
+    Path(address): Path<String>,
+) -> Response {
+    if address.parse::<Address>().is_err() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(SimpleResponse {
+                success: false,
+                message: format!("Invalid address: {}", address),
+            })
+        ).into_response();
+    }
+
+    let owner = address.to_lowercase();
+    match nfts_owned_by(&state.db_pool, &owner).await {
+        Ok(nfts) => Json(OwnerNftsResponse { owner, total: nfts.len(), nfts }).into_response(),
         Err(e) => {
-            error!("❌ Failed to execute ipfs cat for {}: {:?}", ipfs_metadata_path, e);
+            error!("Failed to load NFTs owned by {}: {:?}", owner, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SimpleResponse {
+                    success: false,
+                    message: format!("Database error: {}", e),
+                })
+            ).into_response()
         }
     }
-    None
 }
 
 // ✅ API Handler: Query All Minted NFTs
-// Returns the latest 10 NFTs that have been successfully minted (is_mint = 2, received = true)
+// Returns one page of minted NFTs (is_mint = 2, received = true), filtered and sorted by the query
 async fn query_minted_nfts(
+    Query(params): Query<MintedNftsQuery>,
     State(state): State<Arc<AppStatus>>,
-) -> Json<MintedNftsResponse> {
-    info!("Querying latest 10 minted NFTs");
+) -> Response {
+    let bad_request = |message: &str| (
+        StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({ "error": message }))
+    ).into_response();
+
+    let Some(sort) = GallerySort::parse(params.sort.as_deref().unwrap_or("newest")) else {
+        return bad_request("sort must be one of newest, oldest, token_id_asc, token_id_desc");
+    };
+    let cursor = match params.cursor.as_deref().filter(|c| !c.is_empty()) {
+        Some(raw) => match GalleryCursor::decode(raw) {
+            Some(cursor) if cursor.sort == sort => Some(cursor),
+            Some(_) => return bad_request("cursor was issued for a different sort"),
+            None => return bad_request("Invalid cursor"),
+        },
+        None => None,
+    };
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return bad_request(&format!("limit must be between 1 and {}", MAX_PAGE_SIZE));
+    }
 
-    // Query the latest 10 NFTs with is_mint = 2 and received = true, ordered by updated_at DESC
-    let minted_nfts = sqlx::query!(
-        r#"
-        SELECT id, token_id, token_url
-        FROM nfts
-        WHERE is_mint = 2 AND received = true
-        ORDER BY updated_at DESC
-        LIMIT 10
-        "#
-    )
-    .fetch_all(&state.db_pool)
-    .await
-    .unwrap_or_else(|e| {
-        error!("Failed to fetch minted NFTs: {:?}", e);
-        vec![]
-    });
+    let filter = GalleryFilter {
+        owner: params.owner,
+        token_id_min: params.token_id_min,
+        token_id_max: params.token_id_max,
+        minted_from: params.minted_from,
+        minted_to: params.minted_to,
+        file_name: params.file_name.filter(|f| !f.is_empty()),
+    };
+    info!("Querying minted NFTs: {:?}, sort={}, limit={}", filter, sort.as_str(), limit);
 
-    let total = minted_nfts.len() as i32;
-    
-    // 并发获取所有 NFT 的图片 URL
-    let mut nft_items = Vec::new();
-    for record in minted_nfts {
-        let token_id_str = record.token_id.map(|id| id.to_string());
-        let token_url = record.token_url.clone();
-        
-        // 如果有 token_url，尝试获取 image_url
-        let image_url = if let Some(ref url) = token_url {
-            fetch_nft_image_url(url).await
-        } else {
-            None
-        };
-        
-        nft_items.push(MintedNftItem {
-            nft_id: record.id,
-            token_id: token_id_str,
-            token_url,
-            image_url,
+    let page = match query_gallery(&state.db_pool, &filter, sort, cursor, limit).await {
+        Ok(page) => page,
+        Err(e) => {
+            error!("Failed to fetch minted NFTs: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Failed to fetch minted NFTs", "details": e.to_string() }))
+            ).into_response();
+        }
+    };
+
+    // 图片 URL 优先读数据库缓存，仅对从未获取过的 token_url 并发请求 IPFS
+    let token_urls: Vec<String> = page.items.iter()
+        .filter_map(|item| item.token_url.clone())
+        .collect();
+    let ipfs_client = crate::config::get_ipfs_config()
+        .map_err(|e| e.to_string())
+        .and_then(|config| IpfsClient::new(config).map_err(|e| e.to_string()))
+        .inspect_err(|e| warn!("IPFS client unavailable, serving cached image URLs only: {}", e))
+        .ok();
+    let image_urls = resolve_image_urls(&state.db_pool, ipfs_client.as_ref(), &token_urls)
+        .await
+        .unwrap_or_else(|e| {
+            error!("Failed to resolve image URLs: {:?}", e);
+            Default::default()
         });
-    }
 
-    info!("Found {} minted NFTs (limited to 10) with image URLs", total);
+    let nft_items: Vec<MintedNftItem> = page.items.into_iter()
+        .map(|item| MintedNftItem {
+            nft_id: item.nft_id,
+            token_id: item.token_id.map(|id| id.to_string()),
+            image_url: item.token_url.as_ref().and_then(|url| image_urls.get(url).cloned()),
+            token_url: item.token_url,
+            owner: item.owner,
+            file_name: item.file_name,
+            block_number: item.block_number,
+            minted_at: item.minted_at,
+        })
+        .collect();
+
+    info!("Found {} minted NFTs on this page ({} matching)", nft_items.len(), page.total);
 
     Json(MintedNftsResponse {
-        total,
+        total: page.total,
         nfts: nft_items,
-    })
+        next_cursor: page.next_cursor,
+    }).into_response()
 }
 
 // ✅ API Handler: User Safe Mint
 async fn user_safe_mint(
     State(state): State<Arc<AppStatus>>,
+    session: AuthSession,
     axum::extract::Json(request): axum::extract::Json<UserSafeMintRequest>,
 ) -> Json<UserSafeMintResponse> {
     let user_address = request.user_address.to_lowercase();
     let nft_id = request.nft_id.clone();
+
+    if session.address != user_address {
+        warn!("Safe mint rejected: session {} does not match user_address {}", session.address, user_address);
+        return Json(UserSafeMintResponse {
+            success: false,
+            message: "user_address does not match the signed-in address".to_string(),
+            tx_hash: None,
+            job_id: None,
+            revert_reason: None,
+            nft_id,
+            user_address,
+        });
+    }
     
     info!("Processing safe mint for user: {}, nft_id: {}", user_address, nft_id);
 
@@ -845,32 +1332,19 @@ async fn user_safe_mint(
         Ok(false) => {
             warn!("User {} is not eligible to mint nft_id: {}", user_address, nft_id);
             
-            // Check specific reason for better error message
+            // Report every failed precondition instead of a generic message
             let nft_id_num: i32 = nft_id.parse().unwrap_or(0);
-            let is_mint_status = sqlx::query!(
-                r#"SELECT is_mint FROM nfts WHERE id = $1"#,
-                nft_id_num
-            )
-            .fetch_optional(&state.db_pool)
-            .await
-            .ok()
-            .and_then(|r| r)
-            .map(|r| r.is_mint)
-            .unwrap_or(0);
-            
-            let message = match is_mint_status {
-                1 => format!("NFT {} is already being minted, please wait", nft_id),
-                2 => format!("NFT {} has already been minted", nft_id),
-                _ => format!(
-                    "Cannot mint: NFT {} either doesn't belong to you or its chips are incomplete",
-                    nft_id
-                ),
+            let message = match explain_mint_eligibility(&state.db_pool, &user_address, nft_id_num).await {
+                Ok(report) if !report.failures.is_empty() => format!("Cannot mint: {}", report.summary()),
+                _ => format!("Cannot mint NFT {}", nft_id),
             };
             
             return Json(UserSafeMintResponse {
                 success: false,
                 message,
                 tx_hash: None,
+                job_id: None,
+                revert_reason: None,
                 nft_id,
                 user_address,
             });
@@ -881,6 +1355,8 @@ async fn user_safe_mint(
                 success: false,
                 message: format!("Failed to verify mint eligibility: {}", e),
                 tx_hash: None,
+                job_id: None,
+                revert_reason: None,
                 nft_id,
                 user_address,
             });
@@ -890,161 +1366,407 @@ async fn user_safe_mint(
         }
     }
 
-    // Load private key from environment
-    dotenv::dotenv().ok();
-    let private_key = match std::env::var("PRIVATE_KEY") {
-        Ok(key) => key,
-        Err(_) => {
-            error!("PRIVATE_KEY not found in environment");
+    // 🔄 Step 1: Query file_name and extract number for uint256 parameter
+    info!("Step 1: Querying NFT file_name for uint256 parameter");
+    let nft_id_num: i32 = match nft_id.parse() {
+        Ok(id) => id,
+        Err(e) => {
+            error!("Failed to parse nft_id: {:?}", e);
             return Json(UserSafeMintResponse {
                 success: false,
-                message: "Server configuration error: PRIVATE_KEY not set".to_string(),
+                message: format!("Invalid nft_id: {}", e),
                 tx_hash: None,
+                job_id: None,
+                revert_reason: None,
                 nft_id,
                 user_address,
             });
         }
     };
 
-    // Load NFT contract address from configuration
-    let pool_config = match crate::config::get_pool_config() {
-        Ok(config) => config,
-        Err(e) => {
-            error!("Failed to load pool config: {}", e);
+    let uint256_param = query_mint_param(&state.db_pool, nft_id_num).await;
+
+    // 🔄 Step 2: Simulate safeMint with eth_call; a revert is reported without touching is_mint
+    info!("Step 2: Simulating safeMint before queueing");
+    let simulation = match (crate::config::get_pool_config(), user_address.parse::<Address>()) {
+        (Ok(pool_config), Ok(to_address)) => {
+            simulate_mint_call(None, pool_config.nft_contract, safe_mint_calldata(to_address, &nft_id, uint256_param)).await
+        }
+        (Err(e), _) => Err(e.to_string().into()),
+        (_, Err(e)) => Err(format!("Invalid user address: {}", e).into()),
+    };
+    match simulation {
+        Ok(None) => {}
+        Ok(Some(reason)) => {
+            warn!("safeMint simulation for nft_id {} reverted: {}", nft_id, reason.message);
             return Json(UserSafeMintResponse {
                 success: false,
-                message: format!("Configuration error: {}", e),
+                message: format!("Mint would fail: {}", reason.message),
                 tx_hash: None,
+                job_id: None,
+                revert_reason: Some(reason),
                 nft_id,
                 user_address,
             });
         }
-    };
-    let contract_address = pool_config.nft_contract;
-
-    // Parse user address
-    let to_address: Address = match user_address.parse() {
-        Ok(addr) => addr,
         Err(e) => {
-            error!("Failed to parse user address: {:?}", e);
+            error!("Failed to simulate safeMint: {:?}", e);
             return Json(UserSafeMintResponse {
                 success: false,
-                message: format!("Invalid user address: {}", e),
+                message: format!("Failed to simulate mint: {}", e),
                 tx_hash: None,
+                job_id: None,
+                revert_reason: None,
                 nft_id,
                 user_address,
             });
         }
-    };
+    }
 
-    // 🔄 Step 1: Query file_name and extract number for uint256 parameter
-    info!("Step 1: Querying NFT file_name for uint256 parameter");
-    let nft_id_num: i32 = match nft_id.parse() {
-        Ok(id) => id,
-        Err(e) => {
-            error!("Failed to parse nft_id: {:?}", e);
+    // 🔄 Step 3: Update database first - set is_mint = 1 (申请中) and queue the mint job
+    info!("Step 3: Updating database status to 'applying' (is_mint=1)");
+    let attempt_id = match begin_mint_attempt(&state.db_pool, &user_address, nft_id_num, MintMode::Backend, uint256_param).await {
+        Ok(Some(attempt_id)) => {
+            info!("✅ Updated NFT is_mint status to 1 for nft_id: {} (attempt {})", nft_id, attempt_id);
+            attempt_id
+        }
+        Ok(None) => {
             return Json(UserSafeMintResponse {
                 success: false,
-                message: format!("Invalid nft_id: {}", e),
+                message: format!("NFT {} cannot be minted in its current state", nft_id),
                 tx_hash: None,
+                job_id: None,
+                revert_reason: None,
                 nft_id,
                 user_address,
             });
         }
-    };
-
-    let uint256_param = match sqlx::query!(
-        r#"SELECT file_name FROM nfts WHERE id = $1"#,
-        nft_id_num
-    )
-    .fetch_one(&state.db_pool)
-    .await
-    {
-        Ok(record) => {
-            let file_name = record.file_name.unwrap_or_else(|| nft_id.clone());
-            // Extract number from file_name (e.g., "27.png" -> 27)
-            let uint_value = std::path::Path::new(&file_name)
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .and_then(|s| s.parse::<u64>().ok())
-                .unwrap_or(nft_id_num as u64);
-            info!("✅ Uint256 parameter prepared: {} (from file_name: {})", uint_value, file_name);
-            uint_value
-        }
-        Err(e) => {
-            error!("Failed to query file_name: {:?}", e);
-            // Use nft_id as fallback
-            nft_id_num as u64
-        }
-    };
-
-    // 🔄 Step 2: Update database first - set is_mint = 1 (申请中)
-    info!("Step 2: Updating database status to 'applying' (is_mint=1)");
-    match update_nft_mint_status(&state.db_pool, &user_address, &nft_id, 1).await {
-        Ok(_) => {
-            info!("✅ Updated NFT is_mint status to 1 for nft_id: {}", nft_id);
-        }
         Err(e) => {
             error!("Failed to update NFT mint status: {:?}", e);
             return Json(UserSafeMintResponse {
                 success: false,
                 message: format!("Failed to update database: {}", e),
                 tx_hash: None,
+                job_id: None,
+                revert_reason: None,
                 nft_id,
                 user_address,
             });
         }
+    };
+
+    // 🔄 Step 4: Hand the job to the mint job worker (one job per signer at a time, no nonce races)
+    state.mint_jobs.notify_one();
+    info!("📬 Queued backend mint job {} for nft_id: {}", attempt_id, nft_id);
+
+    Json(UserSafeMintResponse {
+        success: true,
+        message: "Mint job queued, poll /api/mint-jobs/{id} or wait for the MintJob WS event".to_string(),
+        tx_hash: None,
+        job_id: Some(attempt_id),
+        revert_reason: None,
+        nft_id,
+        user_address,
+    })
+}
+
+/// Check the session and parse a batch mint request
+/// Returns the user and `(nft_id, uint256_param)` for each distinct NFT, in request order
+async fn prepare_mint_batch(
+    pool: &PgPool,
+    session: &AuthSession,
+    request: &BatchMintRequest,
+) -> Result<(String, Vec<(i32, u64)>), String> {
+    let user_address = request.user_address.to_lowercase();
+    if session.address != user_address {
+        warn!("Batch mint rejected: session {} does not match user_address {}", session.address, user_address);
+        return Err("user_address does not match the signed-in address".to_string());
     }
 
-    // 🔄 Step 3: Call contract safeMint function
-    info!("Step 3: Calling smart contract safeMint");
-    match call_safe_mint_contract(contract_address, to_address, nft_id.clone(), uint256_param, private_key).await {
-        Ok(tx_hash) => {
-            info!("✅ SafeMint transaction sent: {}", tx_hash);
-            
-            // Invalidate mint cache after successful transaction
-            let cache_key = format!("mint:{}", user_address);
-            state.cache.invalidate(&cache_key).await;
-            info!("🗑️  Invalidated mint cache for user: {}", user_address);
-            
-            Json(UserSafeMintResponse {
-                success: true,
-                message: "Mint transaction submitted successfully".to_string(),
-                tx_hash: Some(tx_hash),
-                nft_id,
-                user_address,
-            })
+    let mut nft_ids: Vec<i32> = Vec::with_capacity(request.nft_ids.len());
+    for nft_id in &request.nft_ids {
+        let nft_id_num: i32 = nft_id.parse()
+            .map_err(|e| format!("Invalid nft_id {}: {}", nft_id, e))?;
+        if !nft_ids.contains(&nft_id_num) {
+            nft_ids.push(nft_id_num);
+        }
+    }
+
+    let max_size = crate::config::get_mint_batch_config().max_size;
+    if nft_ids.is_empty() {
+        return Err("nft_ids is empty".to_string());
+    }
+    if nft_ids.len() > max_size {
+        return Err(format!("Too many NFTs in one batch: {} (max {})", nft_ids.len(), max_size));
+    }
+
+    let mut items = Vec::with_capacity(nft_ids.len());
+    for nft_id in nft_ids {
+        items.push((nft_id, query_mint_param(pool, nft_id).await));
+    }
+    Ok((user_address, items))
+}
+
+/// NFTs of a batch that are not eligible right now (read-only pre-check)
+async fn find_ineligible_nfts(pool: &PgPool, user_address: &str, items: &[(i32, u64)]) -> Result<Vec<String>, sqlx::Error> {
+    let mut ineligible = Vec::new();
+    for (nft_id, _) in items {
+        if !verify_nft_mint_eligibility(pool, user_address, &nft_id.to_string()).await? {
+            ineligible.push(nft_id.to_string());
+        }
+    }
+    Ok(ineligible)
+}
+
+// ✅ API Handler: Batch User Safe Mint (backend mode, one multicall transaction)
+async fn user_safe_mint_batch(
+    State(state): State<Arc<AppStatus>>,
+    session: AuthSession,
+    axum::extract::Json(request): axum::extract::Json<BatchMintRequest>,
+) -> Json<BatchSafeMintResponse> {
+    let failure = |message: String, ineligible: Vec<String>, revert_reason: Option<RevertReason>| Json(BatchSafeMintResponse {
+        success: false,
+        message,
+        batch_id: None,
+        job_ids: vec![],
+        nft_ids: request.nft_ids.clone(),
+        ineligible,
+        revert_reason,
+        user_address: request.user_address.to_lowercase(),
+    });
+
+    // 🔒 Step 1: Check the session, parse the NFT ids and compute the uint256 parameters
+    let (user_address, items) = match prepare_mint_batch(&state.db_pool, &session, &request).await {
+        Ok(prepared) => prepared,
+        Err(message) => return failure(message, vec![], None),
+    };
+    info!("Processing batch safe mint for user: {}, nft_ids: {:?}", user_address, items);
+
+    match find_ineligible_nfts(&state.db_pool, &user_address, &items).await {
+        Ok(ineligible) if !ineligible.is_empty() => {
+            return failure(
+                format!("Cannot mint: NFTs {} are not ready to mint", ineligible.join(", ")),
+                ineligible,
+                None,
+            );
         }
+        Ok(_) => {}
         Err(e) => {
-            error!("❌ Failed to call safeMint: {:?}", e);
-            
-            // Rollback database status if contract call fails
-            warn!("Attempting to rollback database status due to contract call failure");
-            if let Err(rollback_err) = update_nft_mint_status(&state.db_pool, &user_address, &nft_id, 0).await {
-                error!("Failed to rollback NFT mint status: {:?}", rollback_err);
-            } else {
-                info!("✅ Rolled back NFT is_mint status to 0");
-            }
-            
-            Json(UserSafeMintResponse {
+            error!("Failed to verify mint eligibility: {:?}", e);
+            return failure(format!("Failed to verify mint eligibility: {}", e), vec![], None);
+        }
+    }
+
+    // 🔄 Step 2: Simulate the multicall; a revert is reported without touching is_mint
+    let simulation = match (crate::config::get_pool_config(), user_address.parse::<Address>()) {
+        (Ok(pool_config), Ok(to_address)) => {
+            let calls = items.iter()
+                .map(|(nft_id, param)| safe_mint_calldata(to_address, &nft_id.to_string(), *param))
+                .collect();
+            simulate_mint_call(None, pool_config.nft_contract, mint_calldata(calls)).await
+        }
+        (Err(e), _) => Err(e.to_string().into()),
+        (_, Err(e)) => Err(format!("Invalid user address: {}", e).into()),
+    };
+    match simulation {
+        Ok(None) => {}
+        Ok(Some(reason)) => {
+            warn!("Batch safeMint simulation reverted: {}", reason.message);
+            return failure(format!("Mint would fail: {}", reason.message), vec![], Some(reason));
+        }
+        Err(e) => {
+            error!("Failed to simulate batch safeMint: {:?}", e);
+            return failure(format!("Failed to simulate mint: {}", e), vec![], None);
+        }
+    }
+
+    // 🔄 Step 3: Verify again and move all NFTs to applying (is_mint=1) in one transaction
+    let (batch_id, job_ids) = match begin_mint_batch(&state.db_pool, &user_address, &items, MintMode::Backend).await {
+        Ok(MintBatchStart::Started { batch_id, attempt_ids }) => (batch_id, attempt_ids),
+        Ok(MintBatchStart::Ineligible(nft_ids)) => {
+            let ineligible: Vec<String> = nft_ids.iter().map(|id| id.to_string()).collect();
+            return failure(
+                format!("NFTs {} cannot be minted in their current state", ineligible.join(", ")),
+                ineligible,
+                None,
+            );
+        }
+        Err(e) => {
+            error!("Failed to open mint batch: {:?}", e);
+            return failure(format!("Failed to update database: {}", e), vec![], None);
+        }
+    };
+
+    // 🔄 Step 4: Hand the batch to the mint job worker
+    state.mint_jobs.notify_one();
+    info!("📬 Queued backend mint batch {} (jobs {:?})", batch_id, job_ids);
+
+    Json(BatchSafeMintResponse {
+        success: true,
+        message: "Mint batch queued, poll /api/mint-jobs/{id} or wait for the MintJob WS events".to_string(),
+        batch_id: Some(batch_id),
+        job_ids,
+        nft_ids: items.iter().map(|(nft_id, _)| nft_id.to_string()).collect(),
+        ineligible: vec![],
+        revert_reason: None,
+        user_address,
+    })
+}
+
+// ✅ API Handler: Backend mint job status
+async fn get_mint_job(
+    State(state): State<Arc<AppStatus>>,
+    Path(job_id): Path<i64>,
+) -> Response {
+    match get_mint_attempt(&state.db_pool, job_id).await {
+        Ok(Some(job)) if job.mode == MintMode::Backend.as_str() => Json::<MintAttempt>(job).into_response(),
+        Ok(_) => (
+            StatusCode::NOT_FOUND,
+            Json(SimpleResponse {
                 success: false,
-                message: format!("Failed to mint: {}", e),
-                tx_hash: None,
-                nft_id,
-                user_address,
+                message: format!("Mint job {} not found", job_id),
             })
+        ).into_response(),
+        Err(e) => {
+            error!("Failed to query mint job {}: {:?}", job_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SimpleResponse {
+                    success: false,
+                    message: format!("Failed to query mint job: {}", e),
+                })
+            ).into_response()
+        }
+    }
+}
+
+// ✅ API Handler: Mint status by nft_id or tx_hash
+async fn query_mint_status(
+    Query(params): Query<MintStatusQuery>,
+    State(state): State<Arc<AppStatus>>,
+) -> Response {
+    let failure = |status: StatusCode, message: String| (
+        status,
+        Json(MintStatusResponse {
+            success: false,
+            message,
+            nft_id: None,
+            user_address: None,
+            state: None,
+            token_id: None,
+            block_number: None,
+            mint_started_at: None,
+            attempts: vec![],
+            receipt: None,
+        })
+    ).into_response();
+
+    // 按 tx_hash 查询时先取链上收据，再从 attempt 或 UserMint 的 remark 找到 nft_id
+    let mut nft_id = params.nft_id;
+    let mut receipt = None;
+    if let Some(tx_hash) = params.tx_hash.as_deref() {
+        match fetch_mint_receipt(tx_hash).await {
+            Ok(status) => receipt = Some(status),
+            Err(e) => {
+                error!("Failed to fetch receipt for {}: {:?}", tx_hash, e);
+                return failure(StatusCode::BAD_GATEWAY, format!("Failed to fetch receipt: {}", e));
+            }
+        }
+        if nft_id.is_none() {
+            nft_id = match find_attempt_by_tx_hash(&state.db_pool, tx_hash).await {
+                Ok(Some(attempt)) => Some(attempt.nft_id),
+                Ok(None) => receipt.as_ref()
+                    .and_then(|r| r.user_mint.as_ref())
+                    .and_then(|m| MintRemark::parse(&m.remark).ok())
+                    .map(|remark| remark.nft_id),
+                Err(e) => {
+                    error!("Failed to look up attempt for {}: {:?}", tx_hash, e);
+                    return failure(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to query mint attempts: {}", e));
+                }
+            };
         }
     }
+
+    let Some(nft_id) = nft_id else {
+        if receipt.is_some() {
+            return Json(MintStatusResponse {
+                success: true,
+                message: "Transaction is not linked to any known NFT".to_string(),
+                nft_id: None,
+                user_address: None,
+                state: None,
+                token_id: None,
+                block_number: None,
+                mint_started_at: None,
+                attempts: vec![],
+                receipt,
+            }).into_response();
+        }
+        return failure(StatusCode::BAD_REQUEST, "nft_id or tx_hash is required".to_string());
+    };
+
+    let nft = match sqlx::query!(
+        r#"
+        SELECT user_address, is_mint, token_id, block_number, mint_started_at
+        FROM nfts
+        WHERE id = $1
+        "#,
+        nft_id
+    )
+    .fetch_optional(&state.db_pool)
+    .await
+    {
+        Ok(Some(nft)) => nft,
+        Ok(None) => return failure(StatusCode::NOT_FOUND, format!("NFT {} not found", nft_id)),
+        Err(e) => {
+            error!("Failed to query NFT {}: {:?}", nft_id, e);
+            return failure(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to query NFT: {}", e));
+        }
+    };
+
+    let attempts = match list_mint_attempts(&state.db_pool, nft_id).await {
+        Ok(attempts) => attempts,
+        Err(e) => {
+            error!("Failed to query mint attempts of NFT {}: {:?}", nft_id, e);
+            return failure(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to query mint attempts: {}", e));
+        }
+    };
+
+    Json(MintStatusResponse {
+        success: true,
+        message: "OK".to_string(),
+        nft_id: Some(nft_id),
+        user_address: nft.user_address.map(|a| a.to_lowercase()),
+        state: MintState::from_i32(nft.is_mint).map(|s| s.as_str().to_string()),
+        token_id: nft.token_id,
+        block_number: nft.block_number,
+        mint_started_at: nft.mint_started_at,
+        attempts,
+        receipt,
+    }).into_response()
 }
 
 // ✅ API Handler: Verify Mint Eligibility (User Self-Pay Mode)
 // This endpoint verifies eligibility and returns contract parameters for frontend to call
 async fn verify_mint_eligibility_api(
     State(state): State<Arc<AppStatus>>,
+    session: AuthSession,
     axum::extract::Json(request): axum::extract::Json<UserSafeMintRequest>,
 ) -> Json<MintEligibilityResponse> {
     let user_address = request.user_address.to_lowercase();
     let nft_id = request.nft_id.clone();
+
+    if session.address != user_address {
+        warn!("Mint eligibility rejected: session {} does not match user_address {}", session.address, user_address);
+        return Json(MintEligibilityResponse {
+            eligible: false,
+            message: "user_address does not match the signed-in address".to_string(),
+            contract_address: None,
+            token_id: None,
+            uint256_param: None,
+            voucher: None,
+        });
+    }
     
     info!("Verifying mint eligibility for user: {}, nft_id: {}", user_address, nft_id);
 
@@ -1053,26 +1775,11 @@ async fn verify_mint_eligibility_api(
         Ok(false) => {
             warn!("User {} is not eligible to mint nft_id: {}", user_address, nft_id);
             
-            // Check specific reason for better error message
+            // Report every failed precondition instead of a generic message
             let nft_id_num: i32 = nft_id.parse().unwrap_or(0);
-            let is_mint_status = sqlx::query!(
-                r#"SELECT is_mint FROM nfts WHERE id = $1"#,
-                nft_id_num
-            )
-            .fetch_optional(&state.db_pool)
-            .await
-            .ok()
-            .and_then(|r| r)
-            .map(|r| r.is_mint)
-            .unwrap_or(0);
-            
-            let message = match is_mint_status {
-                1 => format!("NFT {} is already being minted, please wait", nft_id),
-                2 => format!("NFT {} has already been minted", nft_id),
-                _ => format!(
-                    "Cannot mint: NFT {} either doesn't belong to you or its chips are incomplete",
-                    nft_id
-                ),
+            let message = match explain_mint_eligibility(&state.db_pool, &user_address, nft_id_num).await {
+                Ok(report) if !report.failures.is_empty() => format!("Cannot mint: {}", report.summary()),
+                _ => format!("Cannot mint NFT {}", nft_id),
             };
             
             return Json(MintEligibilityResponse {
@@ -1081,6 +1788,7 @@ async fn verify_mint_eligibility_api(
                 contract_address: None,
                 token_id: None,
                 uint256_param: None,
+                voucher: None,
             });
         }
         Err(e) => {
@@ -1091,6 +1799,7 @@ async fn verify_mint_eligibility_api(
                 contract_address: None,
                 token_id: None,
                 uint256_param: None,
+                voucher: None,
             });
         }
         Ok(true) => {
@@ -1109,38 +1818,29 @@ async fn verify_mint_eligibility_api(
                 contract_address: None,
                 token_id: None,
                 uint256_param: None,
+                voucher: None,
             });
         }
     };
 
-    let uint256_param = match sqlx::query!(
-        r#"SELECT file_name FROM nfts WHERE id = $1"#,
-        nft_id_num
-    )
-    .fetch_one(&state.db_pool)
-    .await
-    {
-        Ok(record) => {
-            let file_name = record.file_name.unwrap_or_else(|| nft_id.clone());
-            let uint_value = std::path::Path::new(&file_name)
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .and_then(|s| s.parse::<u64>().ok())
-                .unwrap_or(nft_id_num as u64);
-            info!("✅ Uint256 parameter: {} (from file_name: {})", uint_value, file_name);
-            uint_value
-        }
-        Err(e) => {
-            error!("Failed to query file_name: {:?}", e);
-            nft_id_num as u64
-        }
-    };
+    let uint256_param = query_mint_param(&state.db_pool, nft_id_num).await;
 
     // Step 3: Update database status to "applying" (is_mint=1)
     info!("Updating NFT status to 'applying' (is_mint=1)");
-    match update_nft_mint_status(&state.db_pool, &user_address, &nft_id, 1).await {
-        Ok(_) => {
-            info!("✅ Updated NFT is_mint status to 1 for nft_id: {}", nft_id);
+    let attempt_id = match begin_mint_attempt(&state.db_pool, &user_address, nft_id_num, MintMode::SelfPay, uint256_param).await {
+        Ok(Some(attempt_id)) => {
+            info!("✅ Updated NFT is_mint status to 1 for nft_id: {} (attempt {})", nft_id, attempt_id);
+            attempt_id
+        }
+        Ok(None) => {
+            return Json(MintEligibilityResponse {
+                eligible: false,
+                message: format!("NFT {} cannot be minted in its current state", nft_id),
+                contract_address: None,
+                token_id: None,
+                uint256_param: None,
+                voucher: None,
+            });
         }
         Err(e) => {
             error!("Failed to update NFT mint status: {:?}", e);
@@ -1150,9 +1850,10 @@ async fn verify_mint_eligibility_api(
                 contract_address: None,
                 token_id: None,
                 uint256_param: None,
+                voucher: None,
             });
         }
-    }
+    };
 
     // Step 4: Load NFT contract address from configuration and return contract parameters
     let pool_config = match crate::config::get_pool_config() {
@@ -1165,6 +1866,26 @@ async fn verify_mint_eligibility_api(
                 contract_address: None,
                 token_id: None,
                 uint256_param: None,
+                voucher: None,
+            });
+        }
+    };
+
+    // Step 5: Sign an EIP-712 voucher so the contract can verify the backend approved this mint
+    let voucher = match issue_mint_voucher(&state.db_pool, &user_address, nft_id_num, uint256_param, attempt_id).await {
+        Ok(voucher) => voucher,
+        Err(e) => {
+            error!("Failed to sign mint voucher: {}", e);
+            if let Err(rollback_err) = fail_mint_attempt(&state.db_pool, &user_address, nft_id_num, &e).await {
+                error!("Failed to rollback NFT mint status: {:?}", rollback_err);
+            }
+            return Json(MintEligibilityResponse {
+                eligible: false,
+                message: format!("Failed to sign mint voucher: {}", e),
+                contract_address: None,
+                token_id: None,
+                uint256_param: None,
+                voucher: None,
             });
         }
     };
@@ -1175,38 +1896,301 @@ async fn verify_mint_eligibility_api(
         contract_address: Some(format!("{}", pool_config.nft_contract)),
         token_id: Some(nft_id),
         uint256_param: Some(uint256_param),
+        voucher: Some(voucher),
+    })
+}
+
+// ✅ API Handler: Batch Mint Eligibility (User Self-Pay Mode)
+// Moves all NFTs to applying together and returns one voucher per NFT
+async fn verify_mint_eligibility_batch(
+    State(state): State<Arc<AppStatus>>,
+    session: AuthSession,
+    axum::extract::Json(request): axum::extract::Json<BatchMintRequest>,
+) -> Json<BatchMintEligibilityResponse> {
+    let failure = |message: String, ineligible: Vec<String>| Json(BatchMintEligibilityResponse {
+        eligible: false,
+        message,
+        contract_address: None,
+        batch_id: None,
+        items: vec![],
+        ineligible,
+    });
+
+    // Step 1: Check the session, parse the NFT ids and compute the uint256 parameters
+    let (user_address, items) = match prepare_mint_batch(&state.db_pool, &session, &request).await {
+        Ok(prepared) => prepared,
+        Err(message) => return failure(message, vec![]),
+    };
+    info!("Verifying batch mint eligibility for user: {}, nft_ids: {:?}", user_address, items);
+
+    let pool_config = match crate::config::get_pool_config() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Failed to load pool config: {}", e);
+            return failure(format!("Configuration error: {}", e), vec![]);
+        }
+    };
+
+    // Step 2: Verify ownership and chips and move all NFTs to applying (is_mint=1) in one transaction
+    let (batch_id, attempt_ids) = match begin_mint_batch(&state.db_pool, &user_address, &items, MintMode::SelfPay).await {
+        Ok(MintBatchStart::Started { batch_id, attempt_ids }) => (batch_id, attempt_ids),
+        Ok(MintBatchStart::Ineligible(nft_ids)) => {
+            let ineligible: Vec<String> = nft_ids.iter().map(|id| id.to_string()).collect();
+            return failure(
+                format!("Cannot mint: NFTs {} are not ready to mint", ineligible.join(", ")),
+                ineligible,
+            );
+        }
+        Err(e) => {
+            error!("Failed to open mint batch: {:?}", e);
+            return failure(format!("Failed to update database: {}", e), vec![]);
+        }
+    };
+
+    // Step 3: Sign one EIP-712 voucher per NFT; if any fails, roll back the whole batch
+    let mut batch_items = Vec::with_capacity(items.len());
+    for ((nft_id, uint256_param), attempt_id) in items.iter().zip(&attempt_ids) {
+        match issue_mint_voucher(&state.db_pool, &user_address, *nft_id, *uint256_param, *attempt_id).await {
+            Ok(voucher) => batch_items.push(BatchMintItem {
+                token_id: nft_id.to_string(),
+                uint256_param: *uint256_param,
+                voucher,
+            }),
+            Err(e) => {
+                error!("Failed to sign mint voucher for nft_id {}: {}", nft_id, e);
+                for (nft_id, _) in &items {
+                    if let Err(rollback_err) = fail_mint_attempt(&state.db_pool, &user_address, *nft_id, &e).await {
+                        error!("Failed to rollback NFT {} mint status: {:?}", nft_id, rollback_err);
+                    }
+                }
+                return failure(format!("Failed to sign mint voucher: {}", e), vec![]);
+            }
+        }
+    }
+
+    Json(BatchMintEligibilityResponse {
+        eligible: true,
+        message: "You can proceed with minting. Use your wallet to call the contract for each NFT.".to_string(),
+        contract_address: Some(format!("{}", pool_config.nft_contract)),
+        batch_id: Some(batch_id),
+        items: batch_items,
+        ineligible: vec![],
     })
 }
 
+/// uint256 parameter passed to safeMint: the number in the NFT's file_name (e.g. "27.png" -> 27),
+/// falling back to the NFT id
+async fn query_mint_param(pool: &PgPool, nft_id: i32) -> u64 {
+    match sqlx::query!(
+        r#"SELECT file_name FROM nfts WHERE id = $1"#,
+        nft_id
+    )
+    .fetch_one(pool)
+    .await
+    {
+        Ok(record) => {
+            let file_name = record.file_name.unwrap_or_else(|| nft_id.to_string());
+            let uint_value = std::path::Path::new(&file_name)
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse::<u64>().ok())
+                .unwrap_or(nft_id as u64);
+            info!("✅ Uint256 parameter prepared: {} (from file_name: {})", uint_value, file_name);
+            uint_value
+        }
+        Err(e) => {
+            error!("Failed to query file_name: {:?}", e);
+            // Use nft_id as fallback
+            nft_id as u64
+        }
+    }
+}
+
+/// Sign the self-pay voucher for a mint attempt and store it on the attempt
+async fn issue_mint_voucher(
+    pool: &PgPool,
+    user_address: &str,
+    nft_id: i32,
+    uint256_param: u64,
+    attempt_id: i64,
+) -> Result<SignedMintVoucher, String> {
+    let voucher_config = crate::config::get_voucher_config()?;
+    let user: Address = user_address.parse()
+        .map_err(|e| format!("Invalid user address: {}", e))?;
+    let deadline = Utc::now() + chrono::Duration::seconds(voucher_config.ttl_secs);
+
+    let voucher_domain = VoucherDomain::resolve(&voucher_config)
+        .await
+        .map_err(|e| e.to_string())?;
+    let voucher = sign_mint_voucher(&voucher_config, &voucher_domain, user, nft_id, uint256_param, attempt_id, deadline)
+        .map_err(|e| e.to_string())?;
+
+    attach_attempt_voucher(pool, attempt_id, deadline, &voucher.signature)
+        .await
+        .map_err(|e| format!("Failed to store voucher: {}", e))?;
+
+    Ok(voucher)
+}
+
+/// How long after an attempt is opened a missing transaction may still be propagating
+const MINT_TX_NOT_FOUND_GRACE_SECS: i64 = 120;
+
 // ✅ API Handler: Mint Failed Notification
 // Called by frontend when user cancels or transaction fails
+// Only rolls back if the recorded tx (or a reported tx sent by the user to the NFT contract)
+// reverted, or is still unknown to the node after a grace period; backend jobs are never rolled back here
 async fn mint_failed(
     State(state): State<Arc<AppStatus>>,
+    session: AuthSession,
     axum::extract::Json(request): axum::extract::Json<MintFailedRequest>,
 ) -> Json<SimpleResponse> {
     let user_address = request.user_address.to_lowercase();
     let nft_id = request.nft_id;
     let error_msg = request.error.unwrap_or_else(|| "User cancelled or transaction failed".to_string());
+
+    if session.address != user_address {
+        warn!("Mint failed notification rejected: session {} does not match user_address {}", session.address, user_address);
+        return Json(SimpleResponse {
+            success: false,
+            message: "user_address does not match the signed-in address".to_string(),
+        });
+    }
     
     warn!("Mint failed notification: user={}, nft_id={}, error={}", user_address, nft_id, error_msg);
     
-    // Rollback status to is_mint=0
-    match update_nft_mint_status(&state.db_pool, &user_address, &nft_id, 0).await {
-        Ok(_) => {
-            info!("✅ Rolled back is_mint to 0 for failed mint: nft_id={}", nft_id);
-            
-            // Invalidate cache
-            let cache_key = format!("mint:{}", user_address);
-            state.cache.invalidate(&cache_key).await;
-            info!("🗑️  Invalidated mint cache for user: {}", user_address);
-            
-            Json(SimpleResponse {
-                success: true,
-                message: "Status rolled back successfully".to_string(),
-            })
-        }
+    let nft_id_num: i32 = match nft_id.parse() {
+        Ok(id) => id,
         Err(e) => {
-            error!("Failed to rollback status: {:?}", e);
+            return Json(SimpleResponse {
+                success: false,
+                message: format!("Invalid nft_id: {}", e),
+            });
+        }
+    };
+
+    // 🔍 Check the chain before rolling back: the transaction may still land or already have landed
+    let open_attempt = match open_mint_attempt(&state.db_pool, nft_id_num).await {
+        Ok(attempt) => attempt,
+        Err(e) => {
+            error!("Failed to query open mint attempt: {:?}", e);
+            return Json(SimpleResponse {
+                success: false,
+                message: format!("Failed to query mint attempt: {}", e),
+            });
+        }
+    };
+    // 后端代付任务由 worker（或超时清理）收尾，不接受前端回滚
+    if let Some(attempt) = &open_attempt
+        && attempt.mode == MintMode::Backend.as_str() {
+        return Json(SimpleResponse {
+            success: false,
+            message: format!("Backend mint job {} is in progress, nothing to roll back", attempt.id),
+        });
+    }
+
+    // 优先使用 attempt 记录的 tx hash；前端上报的 hash 只有确认是该用户发往 NFT 合约的交易才采用
+    let recorded_hash = open_attempt.as_ref().and_then(|a| a.tx_hash.clone());
+    let (tx_hash, sender) = match (recorded_hash, request.tx_hash.clone()) {
+        (Some(recorded), reported) => {
+            if let Some(reported) = reported
+                && !reported.eq_ignore_ascii_case(&recorded) {
+                warn!("Ignoring reported tx {} for nft_id={}, attempt recorded {}", reported, nft_id, recorded);
+            }
+            (Some(recorded), None)
+        }
+        (None, Some(reported)) => match user_address.parse::<Address>() {
+            Ok(sender) => (Some(reported), Some(sender)),
+            Err(e) => {
+                return Json(SimpleResponse {
+                    success: false,
+                    message: format!("Invalid user address: {}", e),
+                });
+            }
+        },
+        (None, None) => (None, None),
+    };
+
+    let error_msg = match tx_hash {
+        None => error_msg,
+        Some(tx_hash) => match check_mint_transaction(&tx_hash, sender).await {
+            Ok(MintTxOutcome::Succeeded(Some(user_mint))) if MintRemark::is_for(&user_mint.remark, nft_id_num) => {
+                info!("Mint tx {} for nft_id={} succeeded, deferring to the UserMint event", tx_hash, nft_id);
+                return Json(SimpleResponse {
+                    success: false,
+                    message: format!("Transaction {} succeeded, the mint will be confirmed shortly", tx_hash),
+                });
+            }
+            Ok(MintTxOutcome::Succeeded(_)) => {
+                warn!("Mint failed notification with tx {} that did not mint nft_id={}", tx_hash, nft_id);
+                return Json(SimpleResponse {
+                    success: false,
+                    message: format!("Transaction {} did not mint NFT {}", tx_hash, nft_id),
+                });
+            }
+            Ok(MintTxOutcome::Unrelated) => {
+                warn!("Mint failed notification with tx {} not sent by {} to the NFT contract", tx_hash, user_address);
+                return Json(SimpleResponse {
+                    success: false,
+                    message: format!("Transaction {} was not sent by this user to the NFT contract", tx_hash),
+                });
+            }
+            Ok(MintTxOutcome::Pending) => {
+                if let Some(attempt) = &open_attempt
+                    && let Err(e) = mark_attempt_submitted(&state.db_pool, attempt.id, &tx_hash).await {
+                    error!("Failed to record tx hash for attempt {}: {:?}", attempt.id, e);
+                }
+                return Json(SimpleResponse {
+                    success: false,
+                    message: format!("Transaction {} is still pending, not rolling back", tx_hash),
+                });
+            }
+            Ok(MintTxOutcome::Reverted) => format!("{} (tx {} reverted)", error_msg, tx_hash),
+            Ok(MintTxOutcome::NotFound) => {
+                // 刚发出的交易可能还未传播到节点，宽限期内不视为已丢弃
+                if let Some(attempt) = &open_attempt
+                    && Utc::now() - attempt.started_at < chrono::Duration::seconds(MINT_TX_NOT_FOUND_GRACE_SECS) {
+                    return Json(SimpleResponse {
+                        success: false,
+                        message: format!("Transaction {} is not visible yet, retry in a minute", tx_hash),
+                    });
+                }
+                format!("{} (tx {} not found)", error_msg, tx_hash)
+            }
+            Err(e) => {
+                // 无法确认链上状态时不回滚，交给 UserMint 事件或超时清理处理
+                error!("Failed to check mint tx {}: {:?}", tx_hash, e);
+                return Json(SimpleResponse {
+                    success: false,
+                    message: format!("Could not verify transaction {}: {}", tx_hash, e),
+                });
+            }
+        },
+    };
+
+    // Rollback status to is_mint=0 (only legal from the applying state)
+    match fail_mint_attempt(&state.db_pool, &user_address, nft_id_num, &error_msg).await {
+        Ok(false) => {
+            warn!("Rejected rollback for nft_id={}: not in applying state for user {}", nft_id, user_address);
+            Json(SimpleResponse {
+                success: false,
+                message: format!("NFT {} is not being minted by this user, nothing to roll back", nft_id),
+            })
+        }
+        Ok(true) => {
+            info!("✅ Rolled back is_mint to 0 for failed mint: nft_id={}", nft_id);
+            
+            // Invalidate cache
+            let cache_key = format!("mint:{}", user_address);
+            state.cache.invalidate(&cache_key).await;
+            info!("🗑️  Invalidated mint cache for user: {}", user_address);
+            
+            Json(SimpleResponse {
+                success: true,
+                message: "Status rolled back successfully".to_string(),
+            })
+        }
+        Err(e) => {
+            error!("Failed to rollback status: {:?}", e);
             Json(SimpleResponse {
                 success: false,
                 message: format!("Failed to rollback: {}", e),
@@ -1257,6 +2241,9 @@ async fn listen_for_events(
         .unwrap_or_else(|_| ws_url.replace("wss://", "https://").replace("ws://", "http://"));
     let rpc_url_clone = rpc_url.clone();
 
+    // ERC-721 Transfer 只接受 NFT 合约发出的日志
+    let nft_contract = crate::config::get_pool_config()?.nft_contract;
+
     // Create filter for the contract addresses
     let filter = Filter::new()
         .address(contract_addresses);
@@ -1354,6 +2341,15 @@ async fn listen_for_events(
                 info!("No clients connected, skipping broadcast");
             }
         }
+        // ERC-721 Transfer（NFT 持有者变更，含 mint / burn）
+        else if let Some(transfer) = decode_nft_transfer(&log, nft_contract) {
+            info!("🖼️  NFT Transfer: token {} {} -> {} (block {})",
+                transfer.token_id, transfer.from, transfer.to, transfer.block_number);
+
+            if let Err(_e) = tx.send(AppEvent::NftTransfer(transfer)) {
+                info!("No clients connected, skipping broadcast");
+            }
+        }
         // ✅ 监听 UserTransfer 事件（来自 HakuToken 合约）
         else if let Ok(decoded) = log.log_decode::<UserTransfer>() {
             let event = decoded.inner;
@@ -1460,6 +2456,7 @@ async fn listen_for_events(
                     timestamp_str: formatted_time,
                     block_number: event.blockNumber.saturating_to::<u64>(),
                     mint_remark,  // ✅ 传递 mint_remark
+                    tx_hash: Some(format!("{:?}", tx_hash)),
                 });
                 
                 if let Err(_e) = tx_sender.send(app_event) {
@@ -1473,7 +2470,7 @@ async fn listen_for_events(
 }
 
 /// Database worker that subscribes to broadcast channel and inserts events into database
-async fn swap_requests_worker(db_pool: PgPool, tx: broadcast::Sender<AppEvent>, cache: Cache<String, (Expiration, (Vec<u8>, Vec<u8>))>) {
+async fn swap_requests_worker(db_pool: PgPool, tx: broadcast::Sender<AppEvent>, cache: AppCache) {
     let mut rx = tx.subscribe();
     info!("Database worker started, listening for events...");
 
@@ -1494,6 +2491,25 @@ async fn swap_requests_worker(db_pool: PgPool, tx: broadcast::Sender<AppEvent>,
                 }
                 Err(e) => {
                     error!("❌ Failed to insert swap request: {:?}", e);
+                    continue;
+                }
+            }
+
+            // 🎯 交易量模式：按累计交易量发放 chips
+            let swap_volume_mode = crate::config::get_chip_ratio_config()
+                .map(|config| config.mode == crate::config::ChipAllocationMode::SwapVolume)
+                .unwrap_or(false);
+            if swap_volume_mode {
+                let user_lower = user_address.to_lowercase();
+                match crate::services::service::receive_chips(&db_pool, &user_lower, &amount_in).await {
+                    Ok(_) => {
+                        let cache_key = format!("mint:{}", user_lower);
+                        cache.invalidate(&cache_key).await;
+                        info!("🗑️  Invalidated mint cache for user: {} (swap volume chips)", user_lower);
+                    }
+                    Err(e) => {
+                        error!("❌ Failed to grant swap volume chips for {}: {:?}", user_lower, e);
+                    }
                 }
             }
         }
@@ -1574,7 +2590,7 @@ async fn user_mint_worker(db_pool: PgPool, tx: broadcast::Sender<AppEvent>) {
 async fn user_transfer_worker(
     db_pool: PgPool, 
     tx: broadcast::Sender<AppEvent>,
-    cache: Cache<String, (Expiration, (Vec<u8>, Vec<u8>))>
+    cache: AppCache
 ) {
     let mut rx = tx.subscribe();
     info!("💸 User Transfer worker started, listening for Transfer events...");
@@ -1596,6 +2612,23 @@ async fn user_transfer_worker(
                 info!("  Mint Remark: None (normal user transfer)");
             }
             
+            // 记录转账历史（用于 TWAB 计算）
+            if let Err(e) = crate::services::service::record_token_transfer(&db_pool, &transfer_event).await {
+                error!("❌ Failed to record token transfer: {:?}", e);
+            }
+
+            // 检测两个地址间的快速来回转账（刷 chips），只记录标记供管理员审核
+            if transfer_event.mint_remark.is_none() {
+                let event_time = Utc.timestamp_opt(transfer_event.timestamp as i64, 0)
+                    .single()
+                    .unwrap_or_else(Utc::now);
+                if let Err(e) = crate::services::anti_reroll::detect_transfer_reroll(
+                    &db_pool, &from_address, &to_address, event_time
+                ).await {
+                    error!("❌ Failed to run reroll detection: {:?}", e);
+                }
+            }
+
             // 调用 service 中的 process_transfer_event
             match crate::services::service::process_transfer_event(
                 &db_pool,
@@ -1626,9 +2659,472 @@ async fn user_transfer_worker(
     }
 }
 
+/// Interval between retries of open pending allocations without an NFT import
+/// (`receive_limit` deficits can be fulfilled while the pool still has free NFTs)
+const PENDING_ALLOCATION_RETRY_SECS: u64 = 300;
+
+/// Pending Allocation worker - 新 NFT 导入后补发未满足的 chips
+///
+/// Listens on the `nfts_imported` channel (fired by INSERT triggers on nfts/chips)
+/// and retries every open pending allocation. Also runs once at startup to catch
+/// imports that happened while the backend was down, and every
+/// `PENDING_ALLOCATION_RETRY_SECS` for deficits capped by the per-receive limit.
+async fn pending_allocation_worker(db_pool: PgPool, cache: AppCache) {
+    info!("📦 Pending Allocation worker started, listening for NFT imports...");
+
+    let fulfill = |db_pool: PgPool, cache: AppCache| async move {
+        match crate::services::service::fulfill_pending_allocations(&db_pool).await {
+            Ok(users) => {
+                for user_address in users {
+                    let cache_key = format!("mint:{}", user_address.to_lowercase());
+                    cache.invalidate(&cache_key).await;
+                    info!("🗑️  Invalidated mint cache for user: {} (pending allocation)", user_address);
+                }
+            }
+            Err(e) => {
+                error!("❌ Failed to fulfill pending allocations: {:?}", e);
+            }
+        }
+    };
+
+    fulfill(db_pool.clone(), cache.clone()).await;
+
+    let mut listener = match PgListener::connect_with(&db_pool).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("❌ Failed to create PgListener: {:?}", e);
+            return;
+        }
+    };
+    if let Err(e) = listener.listen("nfts_imported").await {
+        error!("❌ Failed to LISTEN nfts_imported: {:?}", e);
+        return;
+    }
+
+    let mut retry = tokio::time::interval(Duration::from_secs(PENDING_ALLOCATION_RETRY_SECS));
+    retry.tick().await; // 首次 tick 立即触发，启动时已处理过
+
+    loop {
+        tokio::select! {
+            notification = listener.recv() => match notification {
+                Ok(notification) => {
+                    info!("📦 NFT import detected (table: {})", notification.payload());
+                    // 导入通常是多条语句（nfts + chips），稍等片刻并合并后续通知
+                    tokio::time::sleep(Duration::from_secs(2)).await;
+                    while let Ok(Some(_)) = listener.try_recv().await {}
+                    fulfill(db_pool.clone(), cache.clone()).await;
+                }
+                Err(e) => {
+                    error!("❌ PgListener error: {:?}", e);
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+            },
+            _ = retry.tick() => {
+                fulfill(db_pool.clone(), cache.clone()).await;
+            }
+        }
+    }
+}
+
+/// Time-weighted sync worker - 定时按 TWAB 发放/回收 chips
+///
+/// Only does work when CHIP_ALLOCATION_MODE=time_weighted; the interval is
+/// re-read from config on every tick (TWAB_SYNC_INTERVAL_SECS).
+async fn time_weighted_sync_worker(db_pool: PgPool, cache: AppCache) {
+    info!("⏱️  Time-weighted sync worker started");
+
+    loop {
+        let interval_secs = crate::config::get_chip_ratio_config()
+            .map(|config| config.twab_sync_interval_secs)
+            .unwrap_or(3600);
+        tokio::time::sleep(Duration::from_secs(interval_secs)).await;
+
+        match crate::services::service::sync_time_weighted_chips(&db_pool).await {
+            Ok(addresses) => {
+                if !addresses.is_empty() {
+                    info!("⏱️  Time-weighted sync completed for {} addresses", addresses.len());
+                }
+                for address in addresses {
+                    let cache_key = format!("mint:{}", address);
+                    cache.invalidate(&cache_key).await;
+                }
+            }
+            Err(e) => {
+                error!("❌ Time-weighted sync failed: {:?}", e);
+            }
+        }
+    }
+}
+
+/// Stale mint sweeper: resolves NFTs left in is_mint=1 when the frontend never
+/// reported success or failure (e.g. the browser was closed)
+async fn stale_mint_sweeper_worker(db_pool: PgPool, cache: AppCache) {
+    info!("🧹 Stale mint sweeper worker started");
+
+    loop {
+        let config = crate::config::get_mint_sweep_config();
+        tokio::time::sleep(Duration::from_secs(config.interval_secs)).await;
+
+        match crate::services::mint_sweeper::sweep_stale_mints(&db_pool, &config).await {
+            Ok(users) => {
+                for user in users {
+                    let cache_key = format!("mint:{}", user);
+                    cache.invalidate(&cache_key).await;
+                    info!("🗑️  Invalidated mint cache for user: {} (stale mint sweep)", user);
+                }
+            }
+            Err(e) => {
+                error!("❌ Stale mint sweep failed: {:?}", e);
+            }
+        }
+    }
+}
+
+/// Delay before a mint job that hit a transient error is picked up again
+const MINT_JOB_RETRY_SECS: u64 = 10;
+
+/// Ids of the mint jobs currently being run by a mint job worker
+type MintJobClaims = Arc<Mutex<HashSet<i64>>>;
+
+/// Take the oldest unclaimed pending job (with the rest of its batch) and claim it for this worker
+async fn claim_mint_jobs(db_pool: &PgPool, claims: &MintJobClaims) -> Result<Vec<MintAttempt>, sqlx::Error> {
+    let mut claimed = claims.lock().await;
+    let claimed_ids: Vec<i64> = claimed.iter().copied().collect();
+
+    let Some(job) = next_backend_mint_job(db_pool, &claimed_ids).await? else {
+        return Ok(vec![]);
+    };
+    let jobs = match job.batch_id {
+        Some(batch_id) => match pending_batch_jobs(db_pool, batch_id).await {
+            Ok(jobs) if !jobs.is_empty() => jobs,
+            Ok(_) => vec![job],
+            Err(e) => {
+                error!("❌ Failed to load mint batch {}: {:?}", batch_id, e);
+                vec![job]
+            }
+        },
+        None => vec![job],
+    };
+
+    claimed.extend(jobs.iter().map(|job| job.id));
+    Ok(jobs)
+}
+
+/// Backend mint job worker bound to the signer in `slot`
+/// One worker runs per signer, so each signer has one job in flight and never races on its
+/// nonce, while several signers mint in parallel. Woken by `AppStatus::mint_jobs`, with a
+/// periodic poll as fallback. Jobs of the same batch are picked up together and sent as one
+/// multicall transaction.
+async fn mint_job_worker(
+    slot: usize,
+    db_pool: PgPool,
+    tx: broadcast::Sender<AppEvent>,
+    cache: AppCache,
+    mint_jobs: Arc<Notify>,
+    claims: MintJobClaims,
+) {
+    info!("📬 Mint job worker {} started", slot);
+
+    loop {
+        match claim_mint_jobs(&db_pool, &claims).await {
+            Ok(jobs) if !jobs.is_empty() => {
+                let job_ids: Vec<i64> = jobs.iter().map(|job| job.id).collect();
+                run_mint_job(slot, &db_pool, &tx, &cache, jobs).await;
+                let mut claimed = claims.lock().await;
+                for job_id in job_ids {
+                    claimed.remove(&job_id);
+                }
+                continue;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                error!("❌ Failed to fetch next mint job: {:?}", e);
+            }
+        }
+
+        tokio::select! {
+            _ = mint_jobs.notified() => {}
+            _ = tokio::time::sleep(Duration::from_secs(30)) => {}
+        }
+    }
+}
+
+/// Send, confirm and settle a backend mint job (or all pending jobs of a batch) in one transaction
+async fn run_mint_job(
+    slot: usize,
+    db_pool: &PgPool,
+    tx: &broadcast::Sender<AppEvent>,
+    cache: &AppCache,
+    jobs: Vec<MintAttempt>,
+) {
+    // 仍为 pending 才发送（可能已被超时清理）
+    let mut live_jobs = Vec::with_capacity(jobs.len());
+    for job in jobs {
+        match get_mint_attempt(db_pool, job.id).await {
+            Ok(Some(current)) if current.status == "pending" => live_jobs.push(current),
+            Ok(_) => info!("Mint job {} was settled before sending, skipping", job.id),
+            Err(e) => {
+                let reason = format!("Failed to reload job: {}", e);
+                error!("❌ Mint job {} failed: {}", job.id, reason);
+                settle_failed_mint_job(db_pool, &job, &reason).await;
+                broadcast_mint_job(tx, cache, &job, "failed", None, Some(reason)).await;
+            }
+        }
+    }
+    if live_jobs.is_empty() {
+        return;
+    }
+
+    let nft_ids: Vec<i32> = live_jobs.iter().map(|job| job.nft_id).collect();
+    info!("📬 Running mint jobs {:?} (user={}, nft_ids={:?})",
+        live_jobs.iter().map(|job| job.id).collect::<Vec<_>>(), live_jobs[0].user_address, nft_ids);
+
+    let (status, tx_hash, error_msg) = match submit_mint_job(slot, db_pool, &live_jobs).await {
+        Ok((tx_hash, true)) => ("confirmed", Some(tx_hash), None),
+        Ok((tx_hash, false)) => {
+            let reason = "Transaction reverted".to_string();
+            for job in &live_jobs {
+                settle_failed_mint_job(db_pool, job, &reason).await;
+            }
+            ("failed", Some(tx_hash), Some(reason))
+        }
+        Err(MintJobError::Failed(reason)) => {
+            error!("❌ Mint jobs for nft_ids {:?} failed: {}", nft_ids, reason);
+            for job in &live_jobs {
+                settle_failed_mint_job(db_pool, job, &reason).await;
+            }
+            ("failed", None, Some(reason))
+        }
+        Err(MintJobError::Retry(reason)) => {
+            // 仍持有任务的认领，等待后释放，由 worker 重新领取
+            warn!("⚠️  Mint jobs for nft_ids {:?} not sent, retrying in {}s: {}", nft_ids, MINT_JOB_RETRY_SECS, reason);
+            tokio::time::sleep(Duration::from_secs(MINT_JOB_RETRY_SECS)).await;
+            return;
+        }
+        Err(MintJobError::Unconfirmed(tx_hash, reason)) => {
+            // 交易已发出但未拿到收据：保持 submitted，由 UserMint 监听或超时清理收尾
+            warn!("⚠️  Mint jobs for nft_ids {:?} sent as {} but receipt unavailable: {}", nft_ids, tx_hash, reason);
+            return;
+        }
+    };
+
+    for job in &live_jobs {
+        broadcast_mint_job(tx, cache, job, status, tx_hash.clone(), error_msg.clone()).await;
+    }
+}
+
+/// Invalidate the user's mint cache and broadcast the final state of a mint job
+async fn broadcast_mint_job(
+    tx: &broadcast::Sender<AppEvent>,
+    cache: &AppCache,
+    job: &MintAttempt,
+    status: &str,
+    tx_hash: Option<String>,
+    error_msg: Option<String>,
+) {
+    let cache_key = format!("mint:{}", job.user_address);
+    cache.invalidate(&cache_key).await;
+
+    let event = AppEvent::MintJob(MintJobEvent {
+        job_id: job.id,
+        batch_id: job.batch_id,
+        nft_id: job.nft_id,
+        user: job.user_address.clone(),
+        status: status.to_string(),
+        tx_hash,
+        error: error_msg,
+    });
+    if let Err(_e) = tx.send(event) {
+        info!("No clients connected, skipping broadcast");
+    }
+}
+
+/// Roll the NFT back to unminted and close the job; if the NFT already left the applying
+/// state, only close the job so the worker does not pick it up again
+async fn settle_failed_mint_job(db_pool: &PgPool, job: &MintAttempt, reason: &str) {
+    match fail_mint_attempt(db_pool, &job.user_address, job.nft_id, reason).await {
+        Ok(true) => info!("✅ Rolled back NFT {} is_mint status to 0", job.nft_id),
+        Ok(false) => {
+            if let Err(e) = abandon_mint_attempt(db_pool, job.id, reason).await {
+                error!("Failed to close mint job {}: {:?}", job.id, e);
+            }
+        }
+        Err(e) => error!("Failed to rollback NFT mint status: {:?}", e),
+    }
+}
+
+enum MintJobError {
+    /// Nothing was sent; the attempts should be failed
+    Failed(String),
+    /// Nothing was sent because of a transient error (e.g. RPC); the attempts stay pending
+    Retry(String),
+    /// The transaction was sent but its receipt could not be fetched
+    Unconfirmed(String, String),
+}
+
+/// Send one transaction minting all given jobs (safeMint, or multicall for several) and wait
+/// for its receipt
+/// Returns the tx hash and whether the transaction succeeded
+async fn submit_mint_job(slot: usize, db_pool: &PgPool, jobs: &[MintAttempt]) -> Result<(String, bool), MintJobError> {
+    let contract_address = crate::config::get_pool_config()
+        .map_err(|e| MintJobError::Failed(format!("Configuration error: {}", e)))?
+        .nft_contract;
+
+    let mut calls = Vec::with_capacity(jobs.len());
+    for job in jobs {
+        let to_address: Address = job.user_address.parse()
+            .map_err(|e| MintJobError::Failed(format!("Invalid user address: {}", e)))?;
+        let uint256_param = job.mint_param.unwrap_or(job.nft_id as i64) as u64;
+        calls.push(safe_mint_calldata(to_address, &job.nft_id.to_string(), uint256_param));
+    }
+    let input = mint_calldata(calls);
+
+    // 链上状态可能在排队期间变化，发送前用即将发送的 signer 再模拟一次；只有解码出的 revert 才算失败
+    match simulate_mint_call(Some(slot), contract_address, input.clone()).await {
+        Ok(None) => {}
+        Ok(Some(reason)) => {
+            return Err(MintJobError::Failed(format!("Simulation reverted ({}): {}", reason.kind, reason.message)));
+        }
+        Err(e) => return Err(MintJobError::Retry(format!("Failed to simulate mint: {}", e))),
+    }
+
+    let nft_ids: Vec<i32> = jobs.iter().map(|job| job.nft_id).collect();
+    let mut sent = send_mint_transaction(slot, contract_address, input, &nft_ids)
+        .await
+        .map_err(|e| MintJobError::Failed(format!("Failed to mint: {}", e)))?;
+
+    let signer_pool = get_signer_pool().map_err(MintJobError::Failed)?;
+    let mut recorded_hash = None;
+    let record_hash = |tx_hash: String| async move {
+        for job in jobs {
+            if let Err(e) = mark_attempt_submitted(db_pool, job.id, &tx_hash).await {
+                error!("Failed to record tx hash for mint job {}: {:?}", job.id, e);
+            }
+        }
+    };
+
+    // 等待确认；超时未打包则加价替换（同 nonce），并记录最新的 tx hash
+    info!("Waiting for transaction confirmation...");
+    let receipt = loop {
+        let tx_hash = format!("{:?}", sent.tx_hash);
+        if recorded_hash.as_ref() != Some(&tx_hash) {
+            record_hash(tx_hash.clone()).await;
+            recorded_hash = Some(tx_hash.clone());
+        }
+
+        match signer_pool.wait_or_speed_up(&mut sent).await {
+            Ok(Some(receipt)) => break receipt,
+            Ok(None) => continue,
+            Err(e) => {
+                // 交易可能已被丢弃，本地 nonce 不再可信
+                signer_pool.resync(slot).await;
+                return Err(MintJobError::Unconfirmed(tx_hash, e.to_string()));
+            }
+        }
+    };
+    let tx_hash = format!("{:?}", sent.tx_hash);
+    if recorded_hash.as_ref() != Some(&tx_hash) {
+        record_hash(tx_hash.clone()).await;
+    }
+    info!("Transaction confirmed in block: {:?}", receipt.block_number);
+
+    if !receipt.status() {
+        return Ok((tx_hash, false));
+    }
+
+    // 直接用收据中的 UserMint 完成状态，不必等待事件监听（只认 NFT 合约发出的事件）
+    for receipt_log in receipt.logs().iter().filter(|log| log.address() == contract_address) {
+        if let Ok(decoded) = receipt_log.log_decode::<UserMint>() {
+            let event = decoded.inner;
+            if let Err(e) = crate::services::service::process_user_mint_event(
+                db_pool,
+                &event.user.to_string(),
+                &event.tokenId.to_string(),
+                receipt.block_number.unwrap_or(0),
+                &event.remark,
+                &event.token_url,
+            ).await {
+                error!("❌ Failed to process UserMint from receipt: {:?}", e);
+            }
+        }
+    }
+
+    Ok((tx_hash, true))
+}
+
+/// Image cache worker
+/// Periodically fills `nft_image_cache` for minted NFTs so the gallery never waits on IPFS
+async fn image_cache_worker(db_pool: PgPool) {
+    let client = match crate::config::get_ipfs_config()
+        .map_err(|e| e.to_string())
+        .and_then(|config| IpfsClient::new(config).map_err(|e| e.to_string()))
+    {
+        Ok(client) => client,
+        Err(e) => {
+            error!("❌ Image cache worker disabled: {}", e);
+            return;
+        }
+    };
+    info!("🖼️  Image cache worker started (interval={}s, concurrency={})",
+        client.config().refresh_interval_secs, client.config().fetch_concurrency);
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(client.config().refresh_interval_secs));
+    loop {
+        ticker.tick().await;
+        if let Err(e) = refresh_image_cache(&db_pool, &client).await {
+            error!("❌ Failed to refresh image cache: {:?}", e);
+        }
+    }
+}
+
+/// NFT Transfer worker - records ERC-721 Transfers into the ownership history (burns are redeemed)
+async fn nft_transfer_worker(db_pool: PgPool, tx: broadcast::Sender<AppEvent>, cache: AppCache, redeem: BurnRedeemConfig) {
+    let mut rx = tx.subscribe();
+    info!("🖼️  NFT Transfer worker started, listening for events...");
+
+    while let Ok(msg) = rx.recv().await {
+        if let AppEvent::NftTransfer(transfer) = msg {
+            match record_nft_transfer(&db_pool, &transfer, &redeem).await {
+                Ok(true) => {
+                    info!("✅ Recorded NFT Transfer: token {} -> {}", transfer.token_id, transfer.to);
+                    // 赎回会改变销毁者的 NFT/chips，清除其 mint 缓存
+                    if is_burn(&transfer) {
+                        let user_address = transfer.from.to_lowercase();
+                        cache.invalidate(&format!("mint:{}", user_address)).await;
+                        info!("🗑️  Invalidated mint cache for user: {} (burn)", user_address);
+                    }
+                }
+                Ok(false) => info!("NFT Transfer {}#{} already indexed", transfer.tx_hash, transfer.log_index),
+                Err(e) => error!("❌ Failed to record NFT Transfer: {:?}", e),
+            }
+        }
+    }
+}
+
+/// NFT Transfer catch-up worker - periodically scans the chain for Transfers not yet indexed
+async fn nft_transfer_sync_worker(db_pool: PgPool, cache: AppCache, redeem: BurnRedeemConfig) {
+    let config = crate::config::get_nft_transfer_sync_config();
+    info!("🔁 NFT Transfer sync worker started: start_block={}, chunk={} blocks, interval={}s",
+        config.start_block, config.chunk_blocks, config.interval_secs);
+
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+    loop {
+        interval.tick().await;
+        match sync_nft_transfers(&db_pool, &config, &redeem).await {
+            Ok(burners) => {
+                for user_address in burners {
+                    cache.invalidate(&format!("mint:{}", user_address)).await;
+                    info!("🗑️  Invalidated mint cache for user: {} (burn)", user_address);
+                }
+            }
+            Err(e) => error!("❌ Failed to sync NFT Transfers: {:?}", e),
+        }
+    }
+}
+
 /// Cache invalidation worker that clears mint query cache when data changes
 async fn cache_invalidation_worker(
-    cache: Cache<String, (Expiration, (Vec<u8>, Vec<u8>))>,
+    cache: AppCache,
     tx: broadcast::Sender<AppEvent>
 ) {
     let mut rx = tx.subscribe();
@@ -1659,6 +3155,9 @@ async fn cache_invalidation_worker(
 /// Returns true if:
 /// 1. The NFT belongs to the user (user_address matches and received=true)
 /// 2. All chips of this NFT belong to the user (user_address matches and received=true)
+/// 3. The NFT is neither being minted nor minted (is_mint = 0)
+///
+/// The individual checks live in `mint_eligibility::failed_preconditions`
 async fn verify_nft_mint_eligibility(
     pool: &PgPool,
     user_address: &str,
@@ -1673,130 +3172,156 @@ async fn verify_nft_mint_eligibility(
             format!("Failed to parse nft_id: {}", e)
         ))))?;
 
-    // Step 1: Check if NFT belongs to the user and is_mint status
-    let nft_record = sqlx::query!(
-        r#"
-        SELECT id, user_address, received, is_mint
-        FROM nfts 
-        WHERE id = $1
-        "#,
-        nft_id_num
-    )
-    .fetch_optional(pool)
-    .await?;
-
-    match nft_record {
-        None => {
-            warn!("NFT {} does not exist", nft_id);
-            return Ok(false);
-        }
-        Some(nft) => {
-            // Check if NFT belongs to user and is received
-            if nft.user_address.is_none() {
-                warn!("NFT {} has no owner", nft_id);
-                return Ok(false);
-            }
-            
-            let nft_owner = nft.user_address.unwrap().to_lowercase();
-            if nft_owner != user_address {
-                warn!("NFT {} belongs to {} not {}", nft_id, nft_owner, user_address);
-                return Ok(false);
-            }
-            
-            if !nft.received.unwrap_or(false) {
-                warn!("NFT {} is not received yet", nft_id);
-                return Ok(false);
-            }
-            
-            // 🔒 Check is_mint status to prevent duplicate requests
-            let is_mint_status = nft.is_mint;
-            if is_mint_status == 1 {
-                warn!("NFT {} is already being minted (is_mint=1)", nft_id);
-                return Ok(false);
-            }
-            if is_mint_status == 2 {
-                warn!("NFT {} has already been minted (is_mint=2)", nft_id);
-                return Ok(false);
-            }
-            
-            info!("✅ NFT {} belongs to user {} and is ready to mint (is_mint={})", nft_id, user_address, is_mint_status);
-        }
+    let report = explain_mint_eligibility(pool, user_address, nft_id_num).await?;
+    if !report.eligible {
+        warn!("NFT {} is not mintable by {}: {}", nft_id, user_address, report.summary());
+        return Ok(false);
     }
 
-    // Step 2: Check if all chips of this NFT belong to the user
-    // Count total chips for this NFT
-    let total_chips = sqlx::query!(
-        "SELECT COUNT(*) as count FROM chips WHERE nft_id = $1",
-        nft_id_num
-    )
-    .fetch_one(pool)
-    .await?;
-    
-    let total_count = total_chips.count.unwrap_or(0);
-
-    // Count chips owned by the user for this NFT
-    let owned_chips = sqlx::query!(
-        r#"
-        SELECT COUNT(*) as count FROM chips 
-        WHERE nft_id = $1 AND LOWER(user_address) = $2 AND received = true
-        "#,
-        nft_id_num,
-        user_address
-    )
-    .fetch_one(pool)
-    .await?;
-
-    let owned_count = owned_chips.count.unwrap_or(0);
-
-    info!(
-        "NFT {} chips status: owned={}, total={}", 
-        nft_id, owned_count, total_count
-    );
+    info!("✅ All chips ({}) of NFT {} belong to user {}", report.total_chips, nft_id, user_address);
+    Ok(true)
+}
 
-    if total_count == 0 {
-        warn!("NFT {} has no chips", nft_id);
-        return Ok(false);
+// ✅ API Handler: Explain mint eligibility
+// GET /api/mint-explain?user_address=&nft_id= returns every failed precondition and the missing chips
+async fn mint_explain(
+    Query(params): Query<MintExplainQuery>,
+    State(state): State<Arc<AppStatus>>,
+) -> Response {
+    if params.user_address.parse::<Address>().is_err() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(SimpleResponse {
+                success: false,
+                message: format!("Invalid user_address: {}", params.user_address),
+            })
+        ).into_response();
     }
 
-    if owned_count != total_count {
-        warn!(
-            "NFT {} chips incomplete: user owns {}/{} chips", 
-            nft_id, owned_count, total_count
-        );
-        return Ok(false);
+    match explain_mint_eligibility(&state.db_pool, &params.user_address, params.nft_id).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => {
+            error!("Failed to explain mint eligibility for NFT {}: {:?}", params.nft_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SimpleResponse {
+                    success: false,
+                    message: format!("Database error: {}", e),
+                })
+            ).into_response()
+        }
     }
+}
 
-    info!("✅ All chips ({}) of NFT {} belong to user {}", total_count, nft_id, user_address);
-    Ok(true)
+// ✅ API Handler: Trade directory status of the signed-in user
+// GET /api/trade-directory
+async fn get_trade_directory_status(
+    State(state): State<Arc<AppStatus>>,
+    session: AuthSession,
+) -> Response {
+    match get_trade_listing(&state.db_pool, &session.address).await {
+        Ok(listing) => Json(TradeListingResponse { listed: listing.is_some(), listing }).into_response(),
+        Err(e) => {
+            error!("Failed to load trade listing for {}: {:?}", session.address, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SimpleResponse {
+                    success: false,
+                    message: format!("Database error: {}", e),
+                })
+            ).into_response()
+        }
+    }
 }
 
-/// Call safeMint function on NFT contract
-async fn call_safe_mint_contract(
-    contract_address: Address,
-    to_address: Address,
-    nft_id: String,
-    uint256_param: u64,
-    private_key: String,
-) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-    info!("Calling safeMint contract...");
-    info!("  Contract: {:?}", contract_address);
-    info!("  To: {:?}", to_address);
-    info!("  NFT_id (tokenId string): {}", nft_id);
-    info!("  Uint256 parameter: {}", uint256_param);
+// ✅ API Handler: Mark the signed-in user as open to trade (or update the note)
+// PUT /api/trade-directory
+async fn join_trade_directory(
+    State(state): State<Arc<AppStatus>>,
+    session: AuthSession,
+    axum::extract::Json(request): axum::extract::Json<TradeListingRequest>,
+) -> Response {
+    let note = request.note.as_deref().map(str::trim).filter(|n| !n.is_empty());
+    if note.is_some_and(|n| n.chars().count() > MAX_TRADE_NOTE_LEN) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(SimpleResponse {
+                success: false,
+                message: format!("note must be at most {} characters", MAX_TRADE_NOTE_LEN),
+            })
+        ).into_response();
+    }
 
-    // Parse private key
-    let signer: PrivateKeySigner = private_key.parse()
-        .map_err(|e: alloy::signers::local::LocalSignerError| format!("Failed to parse private key: {:?}", e))?;
-    
-    let wallet = EthereumWallet::from(signer);
+    match open_to_trade(&state.db_pool, &session.address, note).await {
+        Ok(listing) => {
+            info!("🤝 {} is open to trade", session.address);
+            Json(TradeListingResponse { listed: true, listing: Some(listing) }).into_response()
+        }
+        Err(e) => {
+            error!("Failed to list {} in the trade directory: {:?}", session.address, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SimpleResponse {
+                    success: false,
+                    message: format!("Database error: {}", e),
+                })
+            ).into_response()
+        }
+    }
+}
 
-    // Connect to RPC
-    let rpc_url = "https://dream-rpc.somnia.network";
-    let provider = ProviderBuilder::new()
-        .wallet(wallet)
-        .connect_http(rpc_url.parse()?);
+// ✅ API Handler: Remove the signed-in user from the trade directory
+// DELETE /api/trade-directory
+async fn leave_trade_directory(
+    State(state): State<Arc<AppStatus>>,
+    session: AuthSession,
+) -> Response {
+    match close_to_trade(&state.db_pool, &session.address).await {
+        Ok(removed) => Json(SimpleResponse {
+            success: true,
+            message: if removed {
+                "Removed from the trade directory".to_string()
+            } else {
+                "Not listed in the trade directory".to_string()
+            },
+        }).into_response(),
+        Err(e) => {
+            error!("Failed to remove {} from the trade directory: {:?}", session.address, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SimpleResponse {
+                    success: false,
+                    message: format!("Database error: {}", e),
+                })
+            ).into_response()
+        }
+    }
+}
 
+// ✅ API Handler: Opted-in holders of the chips the signed-in user is missing
+// GET /api/missing-chip-holders?nft_id=
+async fn query_missing_chip_holders(
+    Query(params): Query<MissingChipHoldersQuery>,
+    State(state): State<Arc<AppStatus>>,
+    session: AuthSession,
+) -> Response {
+    match missing_chip_holders(&state.db_pool, &session.address, params.nft_id).await {
+        Ok(holders) => Json(holders).into_response(),
+        Err(e) => {
+            error!("Failed to find holders of NFT {} chips for {}: {:?}", params.nft_id, session.address, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SimpleResponse {
+                    success: false,
+                    message: format!("Database error: {}", e),
+                })
+            ).into_response()
+        }
+    }
+}
 
+/// ABI-encode a safeMint(address,string,uint256) call for the NFT contract
+fn safe_mint_calldata(to_address: Address, nft_id: &str, uint256_param: u64) -> Bytes {
     // Define contract ABI for safeMint function
     // Signature: safeMint(address,string,uint256)
     sol! {
@@ -1818,78 +3343,84 @@ async fn call_safe_mint_contract(
         ]"#
     }
 
-    // Create contract instance
-    let contract = NFTContract::new(contract_address, provider);
-
-    // Call safeMint with uint256 parameter
-    info!("Sending safeMint transaction with parameters:");
-    info!("  - to: {:?}", to_address);
-    info!("  - tokenId (string): {}", nft_id);
-    info!("  - uint256 param: {}", uint256_param);
-    
     use alloy::primitives::U256;
-    let uint256_value = U256::from(uint256_param);
-    
-    let tx_builder = contract.safeMint(to_address, nft_id.clone(), uint256_value);
-    
-    let pending_tx = tx_builder.send().await
-        .map_err(|e| {
-            error!("❌ Transaction failed with error: {:?}", e);
-            error!("   tokenId: {}", nft_id);
-            error!("   uint256 param: {}", uint256_param);
-            format!("Failed to send transaction: {:?}", e)
-        })?;
-    
-    let tx_hash = *pending_tx.tx_hash();
-    info!("Transaction hash: {:?}", tx_hash);
+    use alloy::sol_types::SolCall;
+    NFTContract::safeMintCall {
+        to: to_address,
+        tokenId: nft_id.to_string(),
+        param: U256::from(uint256_param),
+    }
+    .abi_encode()
+    .into()
+}
+
+/// ABI-encode the NFT contract call for a set of safeMint calls
+/// A single call is sent as-is; several are bundled with the contract's `multicall(bytes[])`
+/// (OpenZeppelin Multicall, which keeps the backend signer as msg.sender for each call)
+fn mint_calldata(mut calls: Vec<Bytes>) -> Bytes {
+    if calls.len() == 1 {
+        return calls.remove(0);
+    }
+
+    sol! {
+        #[allow(missing_docs)]
+        NFTMulticall,
+        r#"[
+            {
+                "inputs": [
+                    {"internalType": "bytes[]", "name": "data", "type": "bytes[]"}
+                ],
+                "name": "multicall",
+                "outputs": [
+                    {"internalType": "bytes[]", "name": "results", "type": "bytes[]"}
+                ],
+                "stateMutability": "nonpayable",
+                "type": "function"
+            }
+        ]"#
+    }
 
-    // Wait for confirmation (optional, can be commented out for faster response)
-    info!("Waiting for transaction confirmation...");
-    let receipt = pending_tx.get_receipt().await
-        .map_err(|e| format!("Failed to get receipt: {:?}", e))?;
-    
-    info!("Transaction confirmed in block: {:?}", receipt.block_number);
-    
-    Ok(format!("{:?}", tx_hash))
+    use alloy::sol_types::SolCall;
+    NFTMulticall::multicallCall { data: calls }.abi_encode().into()
 }
 
-/// Update NFT mint status in database
-async fn update_nft_mint_status(
-    pool: &PgPool,
-    user_address: &str,
-    nft_id: &str,
-    is_mint: i32,
-) -> Result<(), sqlx::Error> {
-    info!("Updating NFT mint status: user={}, nft_id={}, is_mint={}", user_address, nft_id, is_mint);
-
-    // Parse nft_id to i32 for database query
-    let nft_id_num: i32 = nft_id.parse()
-        .map_err(|e| sqlx::Error::Decode(Box::new(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            format!("Failed to parse nft_id: {}", e)
-        ))))?;
+/// Dry-run a mint call with eth_call from the backend signer in `from_slot`
+/// (`None` before a signer is chosen, e.g. when a request is checked before queueing)
+/// Returns the decoded revert reason if the call would revert; nothing is sent or written
+async fn simulate_mint_call(
+    from_slot: Option<usize>,
+    contract_address: Address,
+    input: Bytes,
+) -> Result<Option<RevertReason>, Box<dyn std::error::Error + Send + Sync>> {
+    let signer_pool = get_signer_pool()?;
+    let from = match from_slot {
+        Some(slot) => signer_pool.signer_address(slot),
+        None => signer_pool.simulation_signer(),
+    };
+    simulate_call(from, contract_address, input).await
+}
 
-    // Update the nfts table
-    let result = sqlx::query!(
-        r#"
-        UPDATE nfts 
-        SET is_mint = $1
-        WHERE LOWER(user_address) = $2 AND id = $3
-        "#,
-        is_mint,
-        user_address.to_lowercase(),
-        nft_id_num
-    )
-    .execute(pool)
-    .await?;
+/// Send a mint transaction to the NFT contract from the signer in `slot` of the shared pool
+async fn send_mint_transaction(
+    slot: usize,
+    contract_address: Address,
+    input: Bytes,
+    nft_ids: &[i32],
+) -> Result<SentTransaction, Box<dyn std::error::Error + Send + Sync>> {
+    info!("Calling safeMint contract...");
+    info!("  Contract: {:?}", contract_address);
+    info!("  NFT_ids: {:?}", nft_ids);
 
-    if result.rows_affected() > 0 {
-        info!("✅ Updated {} NFT record(s)", result.rows_affected());
-    } else {
-        warn!("⚠️  No NFT records were updated for user: {} nft_id: {}", user_address, nft_id);
-    }
+    let signer_pool = get_signer_pool()?;
+    let sent = signer_pool.send_from(slot, contract_address, input).await
+        .map_err(|e| {
+            error!("❌ Transaction failed with error: {:?}", e);
+            error!("   nft_ids: {:?}", nft_ids);
+            format!("Failed to send transaction: {:?}", e)
+        })?;
 
-    Ok(())
+    info!("Transaction hash: {:?} (signer {:?}, nonce {})", sent.tx_hash, sent.signer, sent.nonce);
+    Ok(sent)
 }
 
 // ========================================
@@ -2213,3 +3744,613 @@ async fn get_nft_user_chips_batch(
         chips: chips_with_base64,
     })
 }
+
+/// Preview chips released by selling tokens: GET /api/revert-preview?user_address={address}&amount={tokens}
+/// Runs the revert_chips selection in a read-only transaction that is rolled back; nothing is committed.
+/// `amount` only matters in balance mode; `balance_after` is null in the other allocation modes.
+/// Selection is random, so the result is one possible outcome, not a guarantee.
+async fn revert_preview(
+    Query(params): Query<RevertPreviewQuery>,
+    State(state): State<Arc<AppStatus>>,
+) -> Json<RevertPreviewResponse> {
+    let user_address = params.user_address.to_lowercase();
+    let amount = params.amount.clone();
+    info!("Previewing revert for user: {}, amount: {}", user_address, amount);
+
+    let empty_response = |message: String| RevertPreviewResponse {
+        success: false,
+        message,
+        user_address: user_address.clone(),
+        amount: amount.clone(),
+        token_balance: None,
+        balance_after: None,
+        chips_before: 0,
+        chips_after: 0,
+        released_chips: vec![],
+        released_nfts: vec![],
+    };
+
+    if user_address.parse::<Address>().is_err() {
+        return Json(empty_response(format!("Invalid user address: {}", params.user_address)));
+    }
+
+    // Convert human-readable amount to raw units
+    let token_decimals = match crate::config::get_chip_ratio_config() {
+        Ok(config) => config.token_decimals,
+        Err(e) => {
+            error!("Failed to load chip ratio config: {}", e);
+            return Json(empty_response(format!("Configuration error: {}", e)));
+        }
+    };
+    let amount_raw = match amount.parse::<BigDecimal>() {
+        Ok(value) if !value.is_negative() => value * BigDecimal::from(10u64.pow(token_decimals)),
+        _ => {
+            return Json(empty_response(format!("Invalid amount: {}", amount)));
+        }
+    };
+
+    let preview = match crate::services::service::preview_revert_chips(&state.db_pool, &user_address, &amount_raw).await {
+        Ok(preview) => preview,
+        Err(e) => {
+            error!("Failed to preview revert for {}: {:?}", user_address, e);
+            return Json(empty_response(format!("Failed to preview revert: {}", e)));
+        }
+    };
+
+    let released_chips = sqlx::query_as!(
+        ReleasedChipInfo,
+        r#"
+        SELECT id, nft_id, x, y, w, h, file_name
+        FROM chips
+        WHERE id = ANY($1)
+        ORDER BY nft_id, id
+        "#,
+        &preview.selection.chip_ids
+    )
+    .fetch_all(&state.db_pool)
+    .await
+    .unwrap_or_else(|e| {
+        error!("Failed to fetch released chips info: {:?}", e);
+        vec![]
+    });
+
+    let message = if preview.selection.chip_ids.is_empty() {
+        "No chips would be released".to_string()
+    } else {
+        format!(
+            "{} chips and {} NFTs would be released (random selection, actual result may differ)",
+            preview.selection.chip_ids.len(),
+            preview.selection.nft_ids.len()
+        )
+    };
+
+    Json(RevertPreviewResponse {
+        success: true,
+        message,
+        user_address,
+        amount,
+        token_balance: Some(preview.token_balance.to_string()),
+        balance_after: preview.balance_after.map(|b| b.to_string()),
+        chips_before: preview.chips_before,
+        chips_after: preview.chips_after,
+        released_chips,
+        released_nfts: preview.selection.nft_ids,
+    })
+}
+
+/// Token → chip conversion config: GET /api/chip-ratio
+async fn query_chip_ratio() -> Json<ChipRatioResponse> {
+    match crate::config::get_chip_ratio_config() {
+        Ok(config) => {
+            let mode = match config.ratio {
+                crate::config::ChipRatio::ChipsPerToken(_) => "chips_per_token",
+                crate::config::ChipRatio::TokensPerChip(_) => "tokens_per_chip",
+            };
+            let allocation_mode = match config.mode {
+                crate::config::ChipAllocationMode::Balance => "balance",
+                crate::config::ChipAllocationMode::SwapVolume => "swap_volume",
+                crate::config::ChipAllocationMode::TimeWeighted => "time_weighted",
+            };
+            Json(ChipRatioResponse {
+                success: true,
+                message: "OK".to_string(),
+                allocation_mode: Some(allocation_mode.to_string()),
+                twab_window_secs: Some(config.twab_window_secs),
+                swap_volume_unit_buy: config.swap_volume.buy_unit.as_ref().map(|u| u.normalized().to_string()),
+                swap_volume_unit_sell: config.swap_volume.sell_unit.as_ref().map(|u| u.normalized().to_string()),
+                mode: Some(mode.to_string()),
+                chips_per_token: Some(config.chips_per_token().normalized().to_string()),
+                tokens_per_chip: Some(config.tokens_per_chip().normalized().to_string()),
+                min_holding_tokens: Some(config.min_holding.normalized().to_string()),
+                token_decimals: Some(config.token_decimals),
+            })
+        }
+        Err(e) => {
+            error!("Failed to load chip ratio config: {}", e);
+            Json(ChipRatioResponse {
+                success: false,
+                message: format!("Configuration error: {}", e),
+                allocation_mode: None,
+                twab_window_secs: None,
+                swap_volume_unit_buy: None,
+                swap_volume_unit_sell: None,
+                mode: None,
+                chips_per_token: None,
+                tokens_per_chip: None,
+                min_holding_tokens: None,
+                token_decimals: None,
+            })
+        }
+    }
+}
+
+/// NFT/chip pool inventory: GET /api/inventory
+async fn query_inventory(
+    State(state): State<Arc<AppStatus>>,
+) -> Json<InventoryResponse> {
+    info!("Querying NFT pool inventory");
+
+    let nfts = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) as total,
+            COUNT(*) FILTER (WHERE received = true) as assigned,
+            COUNT(*) FILTER (WHERE received IS NOT TRUE) as free,
+            COUNT(*) FILTER (WHERE is_mint = 2) as minted
+        FROM nfts
+        "#
+    )
+    .fetch_one(&state.db_pool)
+    .await
+    .map(|r| InventoryCounts {
+        total: r.total.unwrap_or(0),
+        assigned: r.assigned.unwrap_or(0),
+        free: r.free.unwrap_or(0),
+        minted: r.minted.unwrap_or(0),
+    })
+    .unwrap_or_else(|e| {
+        error!("Failed to count NFTs: {:?}", e);
+        InventoryCounts { total: 0, assigned: 0, free: 0, minted: 0 }
+    });
+
+    let chips = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) as total,
+            COUNT(*) FILTER (WHERE received = true) as assigned,
+            COUNT(*) FILTER (WHERE received IS NOT TRUE) as free,
+            COUNT(*) FILTER (WHERE is_mint = 2) as minted
+        FROM chips
+        "#
+    )
+    .fetch_one(&state.db_pool)
+    .await
+    .map(|r| InventoryCounts {
+        total: r.total.unwrap_or(0),
+        assigned: r.assigned.unwrap_or(0),
+        free: r.free.unwrap_or(0),
+        minted: r.minted.unwrap_or(0),
+    })
+    .unwrap_or_else(|e| {
+        error!("Failed to count chips: {:?}", e);
+        InventoryCounts { total: 0, assigned: 0, free: 0, minted: 0 }
+    });
+
+    let (pending_users, pending_chips) = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as users, COALESCE(SUM(chips_deficit), 0)::BIGINT as chips
+        FROM pending_allocations
+        WHERE fulfilled_at IS NULL
+        "#
+    )
+    .fetch_one(&state.db_pool)
+    .await
+    .map(|r| (r.users.unwrap_or(0), r.chips.unwrap_or(0)))
+    .unwrap_or_else(|e| {
+        error!("Failed to count pending allocations: {:?}", e);
+        (0, 0)
+    });
+
+    Json(InventoryResponse {
+        nfts,
+        chips,
+        pending_users,
+        pending_chips,
+    })
+}
+
+// ========================================
+// 登录接口 (Sign-In-With-Ethereum)
+// ========================================
+
+/// Signed-in address from the `Authorization: Bearer <token>` header
+/// Required by all state-changing user endpoints
+pub struct AuthSession {
+    pub address: String,
+}
+
+impl FromRequestParts<Arc<AppStatus>> for AuthSession {
+    type Rejection = (StatusCode, Json<serde_json::Value>);
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppStatus>) -> Result<Self, Self::Rejection> {
+        let token = parts.headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": "Missing bearer token, sign in via /api/auth/verify" }))
+            ))?;
+
+        match crate::services::siwe::session_address(&state.db_pool, token).await {
+            Ok(Some(address)) => Ok(AuthSession { address }),
+            Ok(None) => Err((
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": "Invalid or expired session" }))
+            )),
+            Err(e) => {
+                error!("Failed to look up session: {:?}", e);
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": "Failed to look up session" }))
+                ))
+            }
+        }
+    }
+}
+
+/// Issue a SIWE nonce: GET /api/auth/nonce
+async fn auth_nonce(State(state): State<Arc<AppStatus>>) -> Response {
+    let config = match crate::config::get_siwe_config() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("SIWE is not configured: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Sign-in is not configured", "details": e }))
+            ).into_response();
+        }
+    };
+    match crate::services::siwe::issue_nonce(&state.db_pool, &config).await {
+        Ok((nonce, expires_at)) => Json(AuthNonceResponse { nonce, expires_at }).into_response(),
+        Err(e) => {
+            error!("Failed to issue SIWE nonce: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Failed to issue nonce" }))
+            ).into_response()
+        }
+    }
+}
+
+/// Verify a signed SIWE message and open a session: POST /api/auth/verify
+async fn auth_verify(
+    State(state): State<Arc<AppStatus>>,
+    axum::extract::Json(request): axum::extract::Json<AuthVerifyRequest>,
+) -> Response {
+    let config = match crate::config::get_siwe_config() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("SIWE is not configured: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(AuthVerifyResponse {
+                    success: false,
+                    message: format!("Sign-in is not configured: {}", e),
+                    token: None,
+                    address: None,
+                    expires_at: None,
+                })
+            ).into_response();
+        }
+    };
+    match crate::services::siwe::sign_in(&state.db_pool, &config, &request.message, &request.signature).await {
+        Ok(session) => Json(AuthVerifyResponse {
+            success: true,
+            message: "Signed in".to_string(),
+            token: Some(session.token),
+            address: Some(session.address),
+            expires_at: Some(session.expires_at),
+        }).into_response(),
+        Err(SiweError::Invalid(reason)) => {
+            warn!("SIWE sign-in rejected: {}", reason);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(AuthVerifyResponse {
+                    success: false,
+                    message: reason,
+                    token: None,
+                    address: None,
+                    expires_at: None,
+                })
+            ).into_response()
+        }
+        Err(SiweError::Database(e)) => {
+            error!("SIWE sign-in failed: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(AuthVerifyResponse {
+                    success: false,
+                    message: format!("Sign-in failed: {}", e),
+                    token: None,
+                    address: None,
+                    expires_at: None,
+                })
+            ).into_response()
+        }
+    }
+}
+
+/// Revoke the current session: POST /api/auth/logout
+async fn auth_logout(
+    State(state): State<Arc<AppStatus>>,
+    headers: HeaderMap,
+) -> Json<SimpleResponse> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .unwrap_or("");
+
+    match crate::services::siwe::revoke_session(&state.db_pool, token).await {
+        Ok(true) => Json(SimpleResponse {
+            success: true,
+            message: "Signed out".to_string(),
+        }),
+        Ok(false) => Json(SimpleResponse {
+            success: false,
+            message: "No active session".to_string(),
+        }),
+        Err(e) => {
+            error!("Failed to revoke session: {:?}", e);
+            Json(SimpleResponse {
+                success: false,
+                message: format!("Failed to sign out: {}", e),
+            })
+        }
+    }
+}
+
+// ========================================
+// 管理接口
+// ========================================
+
+/// Check the `x-admin-token` header against ADMIN_TOKEN
+/// Admin endpoints are disabled when ADMIN_TOKEN is not configured
+fn check_admin_token(headers: &HeaderMap) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    dotenv::dotenv().ok();
+    let admin_token = match std::env::var("ADMIN_TOKEN") {
+        Ok(token) if !token.is_empty() => token,
+        _ => {
+            warn!("Admin request rejected: ADMIN_TOKEN not set");
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(serde_json::json!({ "error": "Admin API disabled: ADMIN_TOKEN not set" }))
+            ));
+        }
+    };
+
+    let provided = headers
+        .get("x-admin-token")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    if provided != admin_token {
+        warn!("Admin request rejected: invalid token");
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({ "error": "Invalid admin token" }))
+        ));
+    }
+    Ok(())
+}
+
+/// List blacklisted addresses: GET /api/admin/blacklist
+async fn list_blacklist(
+    headers: HeaderMap,
+    State(state): State<Arc<AppStatus>>,
+) -> Response {
+    if let Err(rejection) = check_admin_token(&headers) {
+        return rejection.into_response();
+    }
+
+    match crate::services::blacklist::list_blacklist(&state.db_pool).await {
+        Ok(entries) => Json::<Vec<BlacklistEntry>>(entries).into_response(),
+        Err(e) => {
+            error!("Failed to list blacklist: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Failed to list blacklist", "details": e.to_string() }))
+            ).into_response()
+        }
+    }
+}
+
+/// Add address to blacklist: POST /api/admin/blacklist
+async fn add_blacklist(
+    headers: HeaderMap,
+    State(state): State<Arc<AppStatus>>,
+    axum::extract::Json(request): axum::extract::Json<BlacklistAddRequest>,
+) -> Response {
+    if let Err(rejection) = check_admin_token(&headers) {
+        return rejection.into_response();
+    }
+
+    let address = request.address.to_lowercase();
+    if address.parse::<Address>().is_err() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(SimpleResponse {
+                success: false,
+                message: format!("Invalid address: {}", request.address),
+            })
+        ).into_response();
+    }
+
+    match crate::services::blacklist::add_blacklist_entry(&state.db_pool, &address, request.reason.as_deref()).await {
+        Ok(_) => Json(SimpleResponse {
+            success: true,
+            message: format!("Address {} blacklisted", address),
+        }).into_response(),
+        Err(e) => {
+            error!("Failed to add blacklist entry: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SimpleResponse {
+                    success: false,
+                    message: format!("Failed to add blacklist entry: {}", e),
+                })
+            ).into_response()
+        }
+    }
+}
+
+/// Remove address from blacklist: DELETE /api/admin/blacklist/{address}
+async fn remove_blacklist(
+    headers: HeaderMap,
+    Path(address): Path<String>,
+    State(state): State<Arc<AppStatus>>,
+) -> Response {
+    if let Err(rejection) = check_admin_token(&headers) {
+        return rejection.into_response();
+    }
+
+    let address = address.to_lowercase();
+    match crate::services::blacklist::remove_blacklist_entry(&state.db_pool, &address).await {
+        Ok(true) => Json(SimpleResponse {
+            success: true,
+            message: format!("Address {} removed from blacklist", address),
+        }).into_response(),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(SimpleResponse {
+                success: false,
+                message: format!("Address {} is not blacklisted", address),
+            })
+        ).into_response(),
+        Err(e) => {
+            error!("Failed to remove blacklist entry: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SimpleResponse {
+                    success: false,
+                    message: format!("Failed to remove blacklist entry: {}", e),
+                })
+            ).into_response()
+        }
+    }
+}
+
+/// List reroll flags: GET /api/admin/reroll-flags?status=open
+async fn list_reroll_flags(
+    headers: HeaderMap,
+    Query(params): Query<RerollFlagsQuery>,
+    State(state): State<Arc<AppStatus>>,
+) -> Response {
+    if let Err(rejection) = check_admin_token(&headers) {
+        return rejection.into_response();
+    }
+
+    match crate::services::anti_reroll::list_reroll_flags(&state.db_pool, params.status.as_deref()).await {
+        Ok(flags) => Json::<Vec<RerollFlag>>(flags).into_response(),
+        Err(e) => {
+            error!("Failed to list reroll flags: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Failed to list reroll flags", "details": e.to_string() }))
+            ).into_response()
+        }
+    }
+}
+
+/// List mint anomalies: GET /api/admin/mint-anomalies?kind=chips_not_held
+async fn list_mint_anomalies(
+    headers: HeaderMap,
+    Query(params): Query<MintAnomaliesQuery>,
+    State(state): State<Arc<AppStatus>>,
+) -> Response {
+    if let Err(rejection) = check_admin_token(&headers) {
+        return rejection.into_response();
+    }
+
+    match crate::services::mint_anomaly::list_mint_anomalies(&state.db_pool, params.kind.as_deref()).await {
+        Ok(anomalies) => Json::<Vec<MintAnomaly>>(anomalies).into_response(),
+        Err(e) => {
+            error!("Failed to list mint anomalies: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Failed to list mint anomalies", "details": e.to_string() }))
+            ).into_response()
+        }
+    }
+}
+
+/// List burn redemptions: GET /api/admin/nft-redemptions?status=rejected
+async fn list_nft_redemptions(
+    headers: HeaderMap,
+    Query(params): Query<NftRedemptionsQuery>,
+    State(state): State<Arc<AppStatus>>,
+) -> Response {
+    if let Err(rejection) = check_admin_token(&headers) {
+        return rejection.into_response();
+    }
+
+    match crate::services::nft_redemption::list_redemptions(&state.db_pool, params.status.as_deref()).await {
+        Ok(redemptions) => Json::<Vec<NftRedemption>>(redemptions).into_response(),
+        Err(e) => {
+            error!("Failed to list NFT redemptions: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Failed to list NFT redemptions", "details": e.to_string() }))
+            ).into_response()
+        }
+    }
+}
+
+/// Review a reroll flag: POST /api/admin/reroll-flags/{id}/review
+async fn review_reroll_flag(
+    headers: HeaderMap,
+    Path(flag_id): Path<i64>,
+    State(state): State<Arc<AppStatus>>,
+    axum::extract::Json(request): axum::extract::Json<RerollReviewRequest>,
+) -> Response {
+    if let Err(rejection) = check_admin_token(&headers) {
+        return rejection.into_response();
+    }
+
+    if request.status != "reviewed" && request.status != "dismissed" {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(SimpleResponse {
+                success: false,
+                message: format!("Invalid status: {} (expected reviewed or dismissed)", request.status),
+            })
+        ).into_response();
+    }
+
+    match crate::services::anti_reroll::review_reroll_flag(&state.db_pool, flag_id, &request.status, request.note.as_deref()).await {
+        Ok(true) => Json(SimpleResponse {
+            success: true,
+            message: format!("Reroll flag {} marked as {}", flag_id, request.status),
+        }).into_response(),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(SimpleResponse {
+                success: false,
+                message: format!("Reroll flag {} not found", flag_id),
+            })
+        ).into_response(),
+        Err(e) => {
+            error!("Failed to review reroll flag: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SimpleResponse {
+                    success: false,
+                    message: format!("Failed to review reroll flag: {}", e),
+                })
+            ).into_response()
+        }
+    }
+}
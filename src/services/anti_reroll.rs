@@ -0,0 +1,273 @@
+use tracing::{info, warn};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use crate::config::get_anti_reroll_config;
+use crate::entitys::entity::RerollFlag;
+
+/// Start of the cooldown ending at `now`; None when the cooldown is disabled
+fn cooldown_start(now: DateTime<Utc>, cooldown_secs: i64) -> Option<DateTime<Utc>> {
+    (cooldown_secs > 0).then(|| now - chrono::Duration::seconds(cooldown_secs))
+}
+
+/// Flag key of two addresses: lowercased and sorted; None for a transfer to oneself
+fn reroll_pair(from_address: &str, to_address: &str) -> Option<(String, String)> {
+    let from_lower = from_address.to_lowercase();
+    let to_lower = to_address.to_lowercase();
+    match from_lower.cmp(&to_lower) {
+        std::cmp::Ordering::Less => Some((from_lower, to_lower)),
+        std::cmp::Ordering::Greater => Some((to_lower, from_lower)),
+        std::cmp::Ordering::Equal => None,
+    }
+}
+
+/// Completed back-and-forth trips given the transfer counts in each direction
+fn round_trips(forward: i64, backward: i64) -> i64 {
+    forward.min(backward)
+}
+
+/// Remember chips released from a user so they can be restored during the cooldown
+pub async fn record_released_chips(
+    tx: &mut Transaction<'_, Postgres>,
+    user_address: &str,
+    chip_ids: &[i32],
+) -> Result<(), sqlx::Error> {
+    if chip_ids.is_empty() {
+        return Ok(());
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO released_chips (user_address, chip_id, nft_id)
+        SELECT $1, id, nft_id FROM chips WHERE id = ANY($2)
+        "#,
+        user_address.to_lowercase(),
+        chip_ids
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Give back up to `limit` chips the user released within the cooldown
+///
+/// Only chips that are still free are restored; their NFT is re-assigned to the
+/// user if nobody else has taken it. Returns the number of chips restored.
+pub async fn restore_released_chips(
+    tx: &mut Transaction<'_, Postgres>,
+    user_address: &str,
+    limit: i64,
+) -> Result<i64, sqlx::Error> {
+    let config = get_anti_reroll_config();
+    let Some(cooldown_start) = cooldown_start(Utc::now(), config.cooldown_secs) else {
+        return Ok(0);
+    };
+    if limit <= 0 {
+        return Ok(0);
+    }
+
+    let user_lower = user_address.to_lowercase();
+
+    let restorable = sqlx::query!(
+        r#"
+        SELECT rc.id as released_id, c.id as chip_id, c.nft_id as "nft_id!"
+        FROM released_chips rc
+        JOIN chips c ON c.id = rc.chip_id
+        JOIN nfts n ON n.id = c.nft_id
+        WHERE rc.user_address = $1
+          AND rc.restored_at IS NULL
+          AND rc.released_at > $2
+          AND c.received = false
+          AND (n.received = false OR LOWER(n.user_address) = $1)
+          AND NOT EXISTS (
+              SELECT 1 FROM released_chips newer
+              WHERE newer.chip_id = rc.chip_id AND newer.restored_at IS NULL AND newer.id > rc.id
+          )
+        ORDER BY rc.released_at DESC
+        LIMIT $3
+        FOR UPDATE OF c SKIP LOCKED
+        "#,
+        user_lower,
+        cooldown_start,
+        limit
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+
+    if restorable.is_empty() {
+        return Ok(0);
+    }
+
+    let released_ids: Vec<i64> = restorable.iter().map(|r| r.released_id).collect();
+    let chip_ids: Vec<i32> = restorable.iter().map(|r| r.chip_id).collect();
+    let mut nft_ids: Vec<i32> = restorable.iter().map(|r| r.nft_id).collect();
+    nft_ids.sort_unstable();
+    nft_ids.dedup();
+
+    sqlx::query!(
+        "UPDATE nfts SET user_address = $1, received = true WHERE id = ANY($2) AND received = false",
+        user_address,
+        &nft_ids
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE chips SET user_address = $1, received = true WHERE id = ANY($2)",
+        user_address,
+        &chip_ids
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE released_chips SET restored_at = NOW() WHERE id = ANY($1)",
+        &released_ids
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    info!("♻️  Restored {} previously released chips to user {} (cooldown)", chip_ids.len(), user_address);
+    Ok(chip_ids.len() as i64)
+}
+
+/// Detect rapid back-and-forth transfers between two addresses
+///
+/// Looks at transfers between `from` and `to` within the detection window ending
+/// at `at`; if tokens went both ways, the pair is flagged (or its open flag updated).
+/// Returns true if the pair is flagged.
+pub async fn detect_transfer_reroll(
+    pool: &PgPool,
+    from_address: &str,
+    to_address: &str,
+    at: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    let Some((address_a, address_b)) = reroll_pair(from_address, to_address) else {
+        return Ok(false);
+    };
+
+    let config = get_anti_reroll_config();
+    let since = at - chrono::Duration::seconds(config.detection_window_secs);
+
+    let stats = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE from_address = $1 AND to_address = $2) as "forward!",
+            COUNT(*) FILTER (WHERE from_address = $2 AND to_address = $1) as "backward!",
+            MIN(block_timestamp) as first_seen,
+            MAX(block_timestamp) as last_seen
+        FROM token_transfers
+        WHERE ((from_address = $1 AND to_address = $2) OR (from_address = $2 AND to_address = $1))
+          AND mint_remark IS NULL
+          AND block_timestamp >= $3
+        "#,
+        address_a,
+        address_b,
+        since
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let round_trips = round_trips(stats.forward, stats.backward);
+    if round_trips == 0 {
+        return Ok(false);
+    }
+    let transfer_count = stats.forward + stats.backward;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO reroll_flags (address_a, address_b, round_trips, transfer_count, first_seen_at, last_seen_at)
+        VALUES ($1, $2, $3, $4, COALESCE($5, NOW()), COALESCE($6, NOW()))
+        ON CONFLICT (address_a, address_b) WHERE status = 'open'
+        DO UPDATE SET
+            round_trips = GREATEST(reroll_flags.round_trips, EXCLUDED.round_trips),
+            transfer_count = GREATEST(reroll_flags.transfer_count, EXCLUDED.transfer_count),
+            last_seen_at = EXCLUDED.last_seen_at
+        "#,
+        address_a,
+        address_b,
+        round_trips as i32,
+        transfer_count as i32,
+        stats.first_seen,
+        stats.last_seen
+    )
+    .execute(pool)
+    .await?;
+
+    warn!("🚩 Reroll pattern detected between {} and {}: {} round trips ({} transfers) within {}s",
+        address_a, address_b, round_trips, transfer_count, config.detection_window_secs);
+    Ok(true)
+}
+
+/// List reroll flags, optionally filtered by status
+pub async fn list_reroll_flags(pool: &PgPool, status: Option<&str>) -> Result<Vec<RerollFlag>, sqlx::Error> {
+    sqlx::query_as!(
+        RerollFlag,
+        r#"
+        SELECT id, address_a, address_b, round_trips, transfer_count,
+               first_seen_at, last_seen_at, status, review_note, reviewed_at
+        FROM reroll_flags
+        WHERE $1::VARCHAR IS NULL OR status = $1
+        ORDER BY last_seen_at DESC
+        LIMIT 500
+        "#,
+        status
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Mark a reroll flag as reviewed or dismissed; returns false if the flag does not exist
+pub async fn review_reroll_flag(
+    pool: &PgPool,
+    flag_id: i64,
+    status: &str,
+    note: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE reroll_flags
+        SET status = $2, review_note = $3, reviewed_at = NOW()
+        WHERE id = $1
+        "#,
+        flag_id,
+        status,
+        note
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    #[test]
+    fn test_cooldown_window() {
+        assert_eq!(cooldown_start(at(3600), 600), Some(at(3000)));
+        assert_eq!(cooldown_start(at(3600), 0), None);
+    }
+
+    #[test]
+    fn test_reroll_pair_is_order_independent() {
+        let pair = Some(("0xaa".to_string(), "0xbb".to_string()));
+        assert_eq!(reroll_pair("0xAA", "0xbb"), pair);
+        assert_eq!(reroll_pair("0xbb", "0xaa"), pair);
+        assert_eq!(reroll_pair("0xAA", "0xaa"), None);
+    }
+
+    #[test]
+    fn test_ping_pong_needs_both_directions() {
+        assert_eq!(round_trips(3, 0), 0);
+        assert_eq!(round_trips(0, 2), 0);
+        assert_eq!(round_trips(1, 1), 1);
+        assert_eq!(round_trips(3, 2), 2);
+    }
+}
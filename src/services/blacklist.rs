@@ -0,0 +1,156 @@
+use tracing::{info, warn, error};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+use tokio::sync::RwLock;
+use crate::entitys::entity::BlacklistEntry;
+
+// 黑名单地址列表（合约地址），启动时写入 address_blacklist
+const CONFIG_BLACKLIST_KEYS: [&str; 10] = [
+    "NFT_CONTRACT",
+    "QUOTER_ADDRESS",
+    "SWAP_EXECUTOR",
+    "TOKEN_B",
+    "TOKEN_A",
+    "POOL_MANAGER",
+    "HOOK_CONTRACT",
+    "CURRENCY0_ADDRESS",
+    "CURRENCY1_ADDRESS",
+    "PUBLIC_KEY",
+];
+
+/// In-memory copy of the active blacklist (lowercase addresses)
+/// `None` means not loaded yet or invalidated; reloaded on next lookup
+static BLACKLIST_CACHE: LazyLock<RwLock<Option<HashSet<String>>>> = LazyLock::new(|| RwLock::new(None));
+
+/// Collect configured contract addresses from env (lowercase)
+fn config_blacklist() -> Vec<(String, String)> {
+    dotenv::dotenv().ok();
+    CONFIG_BLACKLIST_KEYS
+        .iter()
+        .filter_map(|key| std::env::var(key).ok().map(|addr| (addr.to_lowercase(), key.to_string())))
+        .collect()
+}
+
+/// Seed address_blacklist from configured contract addresses
+/// Existing rows (including soft-deleted ones) are left untouched
+pub async fn seed_blacklist_from_config(pool: &PgPool) -> Result<(), sqlx::Error> {
+    let entries = config_blacklist();
+    let mut inserted = 0;
+    for (address, key) in &entries {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO address_blacklist (address, reason, source)
+            VALUES ($1, $2, 'config')
+            ON CONFLICT (address) DO NOTHING
+            "#,
+            address,
+            format!("Configured contract address ({})", key)
+        )
+        .execute(pool)
+        .await?;
+        inserted += result.rows_affected();
+    }
+
+    info!("✅ Blacklist seeded from config: {} configured, {} new", entries.len(), inserted);
+    invalidate_blacklist_cache().await;
+    Ok(())
+}
+
+/// Drop the in-memory blacklist so the next lookup reloads it from the database
+pub async fn invalidate_blacklist_cache() {
+    *BLACKLIST_CACHE.write().await = None;
+}
+
+async fn load_blacklist(pool: &PgPool) -> Result<HashSet<String>, sqlx::Error> {
+    let rows = sqlx::query!(
+        "SELECT address FROM address_blacklist WHERE removed_at IS NULL"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|r| r.address.to_lowercase()).collect())
+}
+
+/// Check if address is in blacklist (addresses that should not receive/revert chips)
+pub async fn is_blacklisted(pool: &PgPool, address: &str) -> bool {
+    let addr_lower = address.to_lowercase();
+
+    if let Some(set) = BLACKLIST_CACHE.read().await.as_ref() {
+        return set.contains(&addr_lower);
+    }
+
+    let mut cache = BLACKLIST_CACHE.write().await;
+    if cache.is_none() {
+        match load_blacklist(pool).await {
+            Ok(set) => {
+                info!("🔄 Loaded {} blacklisted addresses", set.len());
+                *cache = Some(set);
+            }
+            Err(e) => {
+                // 数据库不可用时退回到配置中的合约地址，不写入缓存
+                error!("❌ Failed to load blacklist, falling back to config: {:?}", e);
+                return config_blacklist().iter().any(|(addr, _)| *addr == addr_lower);
+            }
+        }
+    }
+    cache.as_ref().is_some_and(|set| set.contains(&addr_lower))
+}
+
+/// List active blacklist entries
+pub async fn list_blacklist(pool: &PgPool) -> Result<Vec<BlacklistEntry>, sqlx::Error> {
+    sqlx::query_as!(
+        BlacklistEntry,
+        r#"
+        SELECT address, reason, source, created_at, updated_at
+        FROM address_blacklist
+        WHERE removed_at IS NULL
+        ORDER BY created_at
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Add (or re-activate) a blacklist entry
+pub async fn add_blacklist_entry(pool: &PgPool, address: &str, reason: Option<&str>) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO address_blacklist (address, reason, source)
+        VALUES ($1, $2, 'admin')
+        ON CONFLICT (address) DO UPDATE
+        SET reason = COALESCE(EXCLUDED.reason, address_blacklist.reason), removed_at = NULL
+        "#,
+        address.to_lowercase(),
+        reason
+    )
+    .execute(pool)
+    .await?;
+
+    info!("🚫 Blacklisted address {} (reason: {:?})", address, reason);
+    invalidate_blacklist_cache().await;
+    Ok(())
+}
+
+/// Remove a blacklist entry (soft delete); returns false if it was not active
+pub async fn remove_blacklist_entry(pool: &PgPool, address: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE address_blacklist
+        SET removed_at = NOW()
+        WHERE address = $1 AND removed_at IS NULL
+        "#,
+        address.to_lowercase()
+    )
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        warn!("⚠️  Address {} is not blacklisted", address);
+        return Ok(false);
+    }
+
+    info!("✅ Removed address {} from blacklist", address);
+    invalidate_blacklist_cache().await;
+    Ok(true)
+}
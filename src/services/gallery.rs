@@ -0,0 +1,220 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use crate::services::mint_state::MintState;
+
+pub const DEFAULT_PAGE_SIZE: i64 = 10;
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+/// Gallery sort order; ties are broken by NFT id in the same direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GallerySort {
+    Newest,
+    Oldest,
+    TokenIdAsc,
+    TokenIdDesc,
+}
+
+impl GallerySort {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Newest => "newest",
+            Self::Oldest => "oldest",
+            Self::TokenIdAsc => "token_id_asc",
+            Self::TokenIdDesc => "token_id_desc",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "newest" => Some(Self::Newest),
+            "oldest" => Some(Self::Oldest),
+            "token_id_asc" => Some(Self::TokenIdAsc),
+            "token_id_desc" => Some(Self::TokenIdDesc),
+            _ => None,
+        }
+    }
+}
+
+/// Position after the last item of a page: the sort key (mint time in µs or token id) and NFT id
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GalleryCursor {
+    pub sort: GallerySort,
+    pub sort_key: i64,
+    pub id: i32,
+}
+
+impl GalleryCursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}:{}:{}", self.sort.as_str(), self.sort_key, self.id))
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let raw = String::from_utf8(URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+        let mut parts = raw.splitn(3, ':');
+        Some(Self {
+            sort: GallerySort::parse(parts.next()?)?,
+            sort_key: parts.next()?.parse().ok()?,
+            id: parts.next()?.parse().ok()?,
+        })
+    }
+}
+
+/// Gallery filters; all optional
+#[derive(Debug, Clone, Default)]
+pub struct GalleryFilter {
+    pub owner: Option<String>,       // current on-chain owner
+    pub token_id_min: Option<i64>,
+    pub token_id_max: Option<i64>,
+    pub minted_from: Option<DateTime<Utc>>,
+    pub minted_to: Option<DateTime<Utc>>,
+    pub file_name: Option<String>,   // case-insensitive substring
+}
+
+#[derive(Debug, Clone)]
+pub struct GalleryItem {
+    pub nft_id: i32,
+    pub token_id: Option<i64>,
+    pub token_url: Option<String>,
+    pub file_name: Option<String>,
+    pub owner: Option<String>,
+    pub block_number: Option<i64>,
+    pub minted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GalleryPage {
+    pub items: Vec<GalleryItem>,
+    pub total: i64,                  // all NFTs matching the filters
+    pub next_cursor: Option<String>,
+}
+
+/// Escape LIKE wildcards so the file_name filter is a plain substring match
+fn like_pattern(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// One page of minted NFTs (keyset pagination on the sort key and id)
+pub async fn query_gallery(
+    pool: &PgPool,
+    filter: &GalleryFilter,
+    sort: GallerySort,
+    cursor: Option<GalleryCursor>,
+    limit: i64,
+) -> Result<GalleryPage, sqlx::Error> {
+    let limit = limit.clamp(1, MAX_PAGE_SIZE);
+    let owner = filter.owner.as_ref().map(|o| o.to_lowercase());
+    let file_name = filter.file_name.as_deref().map(like_pattern);
+    let ascending = matches!(sort, GallerySort::Oldest | GallerySort::TokenIdAsc);
+    let by_token = matches!(sort, GallerySort::TokenIdAsc | GallerySort::TokenIdDesc);
+
+    let total = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM nfts n
+        WHERE n.is_mint = $1 AND n.received = true AND n.burned_at IS NULL
+          AND ($2::VARCHAR IS NULL OR COALESCE(n.owner_address, LOWER(n.user_address)) = $2)
+          AND ($3::BIGINT IS NULL OR n.token_id >= $3)
+          AND ($4::BIGINT IS NULL OR n.token_id <= $4)
+          AND ($5::TIMESTAMPTZ IS NULL OR n.minted_at >= $5)
+          AND ($6::TIMESTAMPTZ IS NULL OR n.minted_at < $6)
+          AND ($7::VARCHAR IS NULL OR n.file_name ILIKE $7)
+        "#,
+        MintState::Minted.as_i32(),
+        owner,
+        filter.token_id_min,
+        filter.token_id_max,
+        filter.minted_from,
+        filter.minted_to,
+        file_name
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let rows = sqlx::query!(
+        r#"
+        WITH filtered AS (
+            SELECT n.id, n.token_id, n.token_url, n.file_name, n.block_number, n.minted_at,
+                   COALESCE(n.owner_address, LOWER(n.user_address)) AS owner,
+                   CASE WHEN $8 THEN COALESCE(n.token_id, 0)
+                        ELSE COALESCE((EXTRACT(EPOCH FROM n.minted_at) * 1000000)::BIGINT, 0)
+                   END AS sort_key
+            FROM nfts n
+            WHERE n.is_mint = $1 AND n.received = true AND n.burned_at IS NULL
+              AND ($2::VARCHAR IS NULL OR COALESCE(n.owner_address, LOWER(n.user_address)) = $2)
+              AND ($3::BIGINT IS NULL OR n.token_id >= $3)
+              AND ($4::BIGINT IS NULL OR n.token_id <= $4)
+              AND ($5::TIMESTAMPTZ IS NULL OR n.minted_at >= $5)
+              AND ($6::TIMESTAMPTZ IS NULL OR n.minted_at < $6)
+              AND ($7::VARCHAR IS NULL OR n.file_name ILIKE $7)
+        )
+        SELECT id, token_id, token_url, file_name, owner, block_number, minted_at,
+               sort_key AS "sort_key!"
+        FROM filtered
+        WHERE $10::BIGINT IS NULL
+           OR ($9 AND (sort_key, id) > ($10, $11))
+           OR (NOT $9 AND (sort_key, id) < ($10, $11))
+        ORDER BY
+            CASE WHEN $9 THEN sort_key END ASC,
+            CASE WHEN $9 THEN id END ASC,
+            CASE WHEN NOT $9 THEN sort_key END DESC,
+            CASE WHEN NOT $9 THEN id END DESC
+        LIMIT $12
+        "#,
+        MintState::Minted.as_i32(),
+        owner,
+        filter.token_id_min,
+        filter.token_id_max,
+        filter.minted_from,
+        filter.minted_to,
+        file_name,
+        by_token,
+        ascending,
+        cursor.map(|c| c.sort_key),
+        cursor.map(|c| c.id),
+        limit + 1
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let has_more = rows.len() as i64 > limit;
+    let rows: Vec<_> = rows.into_iter().take(limit as usize).collect();
+    let next_cursor = rows.last()
+        .filter(|_| has_more)
+        .map(|last| GalleryCursor { sort, sort_key: last.sort_key, id: last.id }.encode());
+
+    Ok(GalleryPage {
+        items: rows.into_iter()
+            .map(|r| GalleryItem {
+                nft_id: r.id,
+                token_id: r.token_id,
+                token_url: r.token_url,
+                file_name: r.file_name,
+                owner: r.owner,
+                block_number: r.block_number,
+                minted_at: r.minted_at,
+            })
+            .collect(),
+        total,
+        next_cursor,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_round_trips() {
+        let cursor = GalleryCursor { sort: GallerySort::TokenIdDesc, sort_key: 1_764_547_200_000_000, id: 42 };
+        assert_eq!(GalleryCursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(GalleryCursor::decode("not-a-cursor"), None);
+    }
+
+    #[test]
+    fn file_name_filter_is_literal() {
+        assert_eq!(like_pattern("a_1%"), "%a\\_1\\%%");
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{info, warn};
+use futures::stream::{self, StreamExt};
+use sqlx::PgPool;
+use crate::config::IpfsConfig;
+
+#[derive(Debug, thiserror::Error)]
+pub enum IpfsError {
+    #[error("IPFS request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("IPFS returned {status} for {path}")]
+    Status { status: u16, path: String },
+    #[error("Invalid metadata JSON: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+}
+
+/// Reads files from IPFS through the HTTP API (`/api/v0/cat`) or an HTTP gateway
+#[derive(Debug, Clone)]
+pub struct IpfsClient {
+    http: reqwest::Client,
+    config: IpfsConfig,
+}
+
+impl IpfsClient {
+    pub fn new(config: IpfsConfig) -> Result<Self, IpfsError> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()?;
+        Ok(Self { http, config })
+    }
+
+    pub fn config(&self) -> &IpfsConfig {
+        &self.config
+    }
+
+    /// Read the file at `path` (`<cid>/<sub path>`)
+    pub async fn cat(&self, path: &str) -> Result<Vec<u8>, IpfsError> {
+        let request = match &self.config.api_url {
+            // Kubo RPC 只接受 POST
+            Some(api_url) => self.http.post(format!("{}/api/v0/cat", api_url)).query(&[("arg", path)]),
+            None => self.http.get(format!("{}/{}", self.config.gateway, path)),
+        };
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(IpfsError::Status {
+                status: response.status().as_u16(),
+                path: path.to_string(),
+            });
+        }
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// Read an NFT's metadata JSON and return its `image` field
+    pub async fn fetch_image_url(&self, token_url: &str) -> Result<Option<String>, IpfsError> {
+        let path = format!("{}/{}.json", self.config.metadata_cid, token_url);
+        let metadata: serde_json::Value = serde_json::from_slice(&self.cat(&path).await?)?;
+        let image = metadata.get("image").and_then(|v| v.as_str()).map(str::to_string);
+        if image.is_none() {
+            warn!("⚠️ No 'image' field found in metadata for token_url: {}", token_url);
+        }
+        Ok(image)
+    }
+}
+
+/// Cached image URLs for the given token URLs (only resolved entries)
+pub async fn cached_image_urls(pool: &PgPool, token_urls: &[String]) -> Result<HashMap<String, String>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT token_url, image_url AS "image_url!"
+        FROM nft_image_cache
+        WHERE token_url = ANY($1) AND image_url IS NOT NULL
+        "#,
+        token_urls
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|r| (r.token_url, r.image_url)).collect())
+}
+
+/// Store the outcome of a metadata fetch; a failure keeps any previously resolved image URL
+pub async fn store_image_result(
+    pool: &PgPool,
+    token_url: &str,
+    result: &Result<Option<String>, IpfsError>,
+) -> Result<(), sqlx::Error> {
+    let (image_url, error) = match result {
+        Ok(Some(image_url)) => (Some(image_url.clone()), None),
+        Ok(None) => (None, Some("Metadata has no image field".to_string())),
+        Err(e) => (None, Some(e.to_string())),
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO nft_image_cache (token_url, image_url, last_error, attempts, fetched_at)
+        VALUES ($1, $2, $3, 1, NOW())
+        ON CONFLICT (token_url) DO UPDATE
+        SET image_url = COALESCE(EXCLUDED.image_url, nft_image_cache.image_url),
+            last_error = EXCLUDED.last_error,
+            attempts = nft_image_cache.attempts + 1,
+            fetched_at = NOW()
+        "#,
+        token_url,
+        image_url,
+        error
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Fetch image URLs for `token_urls` concurrently (bounded by the config) and cache the results
+/// Returns the resolved ones
+pub async fn fetch_and_cache(pool: &PgPool, client: &IpfsClient, token_urls: Vec<String>) -> HashMap<String, String> {
+    let results: Vec<(String, Result<Option<String>, IpfsError>)> = stream::iter(token_urls)
+        .map(|token_url| async move {
+            let result = client.fetch_image_url(&token_url).await;
+            (token_url, result)
+        })
+        .buffer_unordered(client.config().fetch_concurrency)
+        .collect()
+        .await;
+
+    let mut resolved = HashMap::new();
+    for (token_url, result) in results {
+        if let Err(e) = store_image_result(pool, &token_url, &result).await {
+            warn!("Failed to cache image URL for {}: {:?}", token_url, e);
+        }
+        match result {
+            Ok(Some(image_url)) => {
+                resolved.insert(token_url, image_url);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("⚠️ Failed to fetch metadata for {}: {}", token_url, e),
+        }
+    }
+    resolved
+}
+
+/// Image URLs for the gallery: served from the cache, fetching only token URLs never seen before
+pub async fn resolve_image_urls(
+    pool: &PgPool,
+    client: Option<&IpfsClient>,
+    token_urls: &[String],
+) -> Result<HashMap<String, String>, sqlx::Error> {
+    let mut resolved = cached_image_urls(pool, token_urls).await?;
+
+    let Some(client) = client else {
+        return Ok(resolved);
+    };
+
+    // 已有记录但未解析成功的条目交给后台刷新，避免请求时反复访问 IPFS
+    let known = sqlx::query_scalar!(
+        "SELECT token_url FROM nft_image_cache WHERE token_url = ANY($1)",
+        token_urls
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let missing: Vec<String> = token_urls.iter()
+        .filter(|url| !known.contains(url))
+        .cloned()
+        .collect();
+    if !missing.is_empty() {
+        resolved.extend(fetch_and_cache(pool, client, missing).await);
+    }
+    Ok(resolved)
+}
+
+/// Resolve minted NFTs whose image URL is not cached yet (or failed before the last interval)
+/// Returns the number of newly resolved entries
+pub async fn refresh_image_cache(pool: &PgPool, client: &IpfsClient) -> Result<usize, sqlx::Error> {
+    let retry_before = chrono::Utc::now() - chrono::Duration::seconds(client.config().refresh_interval_secs as i64);
+
+    let pending = sqlx::query_scalar!(
+        r#"
+        SELECT DISTINCT n.token_url AS "token_url!"
+        FROM nfts n
+        LEFT JOIN nft_image_cache c ON c.token_url = n.token_url
+        WHERE n.is_mint = 2
+          AND n.token_url IS NOT NULL
+          AND (c.token_url IS NULL OR (c.image_url IS NULL AND c.fetched_at < $1))
+        LIMIT 500
+        "#,
+        retry_before
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    let resolved = fetch_and_cache(pool, client, pending).await.len();
+    info!("🖼️  Image cache refreshed: {} resolved", resolved);
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, extract::{Path, Query}, routing::{get, post}};
+
+    fn config(api_url: Option<String>, gateway: String) -> IpfsConfig {
+        IpfsConfig {
+            api_url,
+            gateway,
+            metadata_cid: "QmMeta".to_string(),
+            timeout_secs: 5,
+            fetch_concurrency: 4,
+            refresh_interval_secs: 300,
+        }
+    }
+
+    async fn serve(router: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn reads_image_through_gateway() {
+        let router = Router::new().route("/ipfs/QmMeta/{file}", get(|Path(file): Path<String>| async move {
+            match file.as_str() {
+                "12.json" => r#"{"name":"Haku","image":"ipfs://QmImg/12.png"}"#.to_string(),
+                _ => r#"{"name":"no image"}"#.to_string(),
+            }
+        }));
+        let base = serve(router).await;
+        let client = IpfsClient::new(config(None, format!("{}/ipfs", base))).unwrap();
+
+        assert_eq!(client.fetch_image_url("12").await.unwrap().as_deref(), Some("ipfs://QmImg/12.png"));
+        assert_eq!(client.fetch_image_url("13").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn reads_through_http_api() {
+        let router = Router::new().route("/api/v0/cat", post(|Query(params): Query<HashMap<String, String>>| async move {
+            assert_eq!(params.get("arg").map(String::as_str), Some("QmMeta/7.json"));
+            r#"{"image":"ipfs://QmImg/7.png"}"#
+        }));
+        let base = serve(router).await;
+        let client = IpfsClient::new(config(Some(base), "http://unused".to_string())).unwrap();
+
+        assert_eq!(client.fetch_image_url("7").await.unwrap().as_deref(), Some("ipfs://QmImg/7.png"));
+    }
+
+    #[tokio::test]
+    async fn reports_missing_files() {
+        let base = serve(Router::new()).await;
+        let client = IpfsClient::new(config(None, base)).unwrap();
+        assert!(matches!(client.fetch_image_url("1").await, Err(IpfsError::Status { status: 404, .. })));
+    }
+}
@@ -0,0 +1,136 @@
+use tracing::warn;
+use sqlx::{PgPool, Postgres, Transaction};
+use crate::entitys::entity::MintAnomaly;
+
+/// Why a UserMint event did not pass validation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MintAnomalyKind {
+    /// The remark could not be parsed into an NFT id
+    InvalidRemark,
+    /// The remark names an NFT that does not exist
+    UnknownNft,
+    /// The minter is not the NFT's recorded owner
+    OwnerMismatch,
+    /// The minter did not hold all of the NFT's chips
+    ChipsNotHeld,
+    /// The NFT is already minted under a different token id
+    TokenMismatch,
+    /// The token was burned and redeemed, its NFT went back to the pool (reset mode)
+    RedeemedToken,
+}
+
+impl MintAnomalyKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::InvalidRemark => "invalid_remark",
+            Self::UnknownNft => "unknown_nft",
+            Self::OwnerMismatch => "owner_mismatch",
+            Self::ChipsNotHeld => "chips_not_held",
+            Self::TokenMismatch => "token_mismatch",
+            Self::RedeemedToken => "redeemed_token",
+        }
+    }
+}
+
+/// A mint anomaly to record
+#[derive(Debug, Clone)]
+pub struct NewMintAnomaly<'a> {
+    pub kind: MintAnomalyKind,
+    pub remark: &'a str,
+    pub nft_id: Option<i32>,
+    pub token_id: Option<i64>,
+    pub minter: &'a str,
+    pub recorded_owner: Option<&'a str>,
+    pub detail: String,
+    pub block_number: i64,
+}
+
+/// Record a mint anomaly inside the transaction processing the event
+pub async fn record_mint_anomaly(
+    tx: &mut Transaction<'_, Postgres>,
+    anomaly: &NewMintAnomaly<'_>,
+) -> Result<(), sqlx::Error> {
+    warn!("🚨 Mint anomaly ({}): nft_id={:?}, minter={}, {}",
+        anomaly.kind.as_str(), anomaly.nft_id, anomaly.minter, anomaly.detail);
+
+    sqlx::query!(
+        r#"
+        INSERT INTO mint_anomalies (kind, remark, nft_id, token_id, minter, recorded_owner, detail, block_number)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        "#,
+        anomaly.kind.as_str(),
+        anomaly.remark,
+        anomaly.nft_id,
+        anomaly.token_id,
+        anomaly.minter.to_lowercase(),
+        anomaly.recorded_owner,
+        anomaly.detail,
+        anomaly.block_number
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Check that `minter` owns the NFT and held all of its chips
+///
+/// Chips already recycled for this minter (`mint_user`) count as held, since the chip
+/// recycling for the same transaction may be processed first.
+pub async fn check_minter(
+    tx: &mut Transaction<'_, Postgres>,
+    nft_id: i32,
+    minter: &str,
+    recorded_owner: Option<&str>,
+) -> Result<Vec<(MintAnomalyKind, String)>, sqlx::Error> {
+    let minter = minter.to_lowercase();
+    let mut anomalies = Vec::new();
+
+    if recorded_owner.map(|owner| owner.to_lowercase()) != Some(minter.clone()) {
+        anomalies.push((
+            MintAnomalyKind::OwnerMismatch,
+            format!("NFT {} is recorded for {:?}, minted by {}", nft_id, recorded_owner, minter),
+        ));
+    }
+
+    let chips = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "total!",
+               COUNT(*) FILTER (
+                   WHERE (LOWER(user_address) = $2 AND received = true) OR LOWER(mint_user) = $2
+               ) AS "held!"
+        FROM chips
+        WHERE nft_id = $1
+        "#,
+        nft_id,
+        minter
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    if chips.total == 0 || chips.held < chips.total {
+        anomalies.push((
+            MintAnomalyKind::ChipsNotHeld,
+            format!("Minter held {}/{} chips of NFT {}", chips.held, chips.total, nft_id),
+        ));
+    }
+
+    Ok(anomalies)
+}
+
+/// List recorded mint anomalies, newest first
+pub async fn list_mint_anomalies(pool: &PgPool, kind: Option<&str>) -> Result<Vec<MintAnomaly>, sqlx::Error> {
+    sqlx::query_as!(
+        MintAnomaly,
+        r#"
+        SELECT id, kind, remark, nft_id, token_id, minter, recorded_owner, detail, block_number, created_at
+        FROM mint_anomalies
+        WHERE $1::VARCHAR IS NULL OR kind = $1
+        ORDER BY id DESC
+        LIMIT 500
+        "#,
+        kind
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,183 @@
+use serde::Serialize;
+use sqlx::PgPool;
+use crate::services::mint_state::MintState;
+
+/// A mint precondition the user does not meet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FailedPrecondition {
+    pub code: &'static str,    // nft_not_found / not_owner / not_received / mint_in_progress / already_minted / no_chips / missing_chips
+    pub message: String,
+}
+
+/// A chip of the NFT the user does not hold
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingChip {
+    pub id: i32,
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    pub w: Option<i32>,
+    pub h: Option<i32>,
+    pub file_name: Option<String>,
+}
+
+/// Why a user can or cannot mint an NFT; `eligible` iff `failures` is empty
+#[derive(Debug, Clone, Serialize)]
+pub struct EligibilityReport {
+    pub nft_id: i32,
+    pub user_address: String,
+    pub eligible: bool,
+    pub owner: Option<String>,
+    pub received: bool,
+    pub mint_state: Option<&'static str>,   // unminted / applying / minted, None if the NFT does not exist
+    pub total_chips: i64,
+    pub held_chips: i64,
+    pub failures: Vec<FailedPrecondition>,
+    pub missing_chips: Vec<MissingChip>,
+}
+
+impl EligibilityReport {
+    /// All failure messages in one line, for handlers that only return a message
+    pub fn summary(&self) -> String {
+        self.failures.iter()
+            .map(|f| f.message.as_str())
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// NFT fields the preconditions are checked against
+#[derive(Debug, Clone)]
+pub struct NftSnapshot {
+    pub owner: Option<String>,
+    pub received: bool,
+    pub is_mint: i32,
+}
+
+/// Every failed precondition, in the order the mint flow checks them
+pub fn failed_preconditions(
+    user_address: &str,
+    nft_id: i32,
+    nft: Option<&NftSnapshot>,
+    total_chips: i64,
+    held_chips: i64,
+) -> Vec<FailedPrecondition> {
+    let fail = |code, message| FailedPrecondition { code, message };
+
+    let Some(nft) = nft else {
+        return vec![fail("nft_not_found", format!("NFT {} does not exist", nft_id))];
+    };
+
+    let mut failures = Vec::new();
+    match nft.owner.as_deref() {
+        Some(owner) if owner.eq_ignore_ascii_case(user_address) => {}
+        Some(_) => failures.push(fail("not_owner", format!("NFT {} belongs to another address", nft_id))),
+        None => failures.push(fail("not_owner", format!("NFT {} has no owner", nft_id))),
+    }
+    if !nft.received {
+        failures.push(fail("not_received", format!("NFT {} has not been received yet", nft_id)));
+    }
+    match MintState::from_i32(nft.is_mint) {
+        Some(MintState::Applying) => failures.push(fail("mint_in_progress", format!("NFT {} is already being minted, please wait", nft_id))),
+        Some(MintState::Minted) => failures.push(fail("already_minted", format!("NFT {} has already been minted", nft_id))),
+        _ => {}
+    }
+    if total_chips == 0 {
+        failures.push(fail("no_chips", format!("NFT {} has no chips", nft_id)));
+    } else if held_chips < total_chips {
+        failures.push(fail(
+            "missing_chips",
+            format!("Missing {} of {} chips of NFT {}", total_chips - held_chips, total_chips, nft_id),
+        ));
+    }
+    failures
+}
+
+/// Check every mint precondition of `nft_id` for `user_address` (same rules as the mint endpoints)
+pub async fn explain_mint_eligibility(
+    pool: &PgPool,
+    user_address: &str,
+    nft_id: i32,
+) -> Result<EligibilityReport, sqlx::Error> {
+    let user_address = user_address.to_lowercase();
+
+    let nft = sqlx::query!(
+        "SELECT user_address, received, is_mint FROM nfts WHERE id = $1",
+        nft_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(|r| NftSnapshot {
+        owner: r.user_address.map(|a| a.to_lowercase()),
+        received: r.received.unwrap_or(false),
+        is_mint: r.is_mint,
+    });
+
+    // 用户已持有的 chip 不算缺失；其余按 id 返回坐标，不暴露持有者
+    let chips = sqlx::query!(
+        r#"
+        SELECT id, x, y, w, h, file_name,
+               (LOWER(user_address) = $2 AND received = true) AS "held!"
+        FROM chips
+        WHERE nft_id = $1
+        ORDER BY id
+        "#,
+        nft_id,
+        user_address
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let total_chips = chips.len() as i64;
+    let missing_chips: Vec<MissingChip> = chips.into_iter()
+        .filter(|c| !c.held)
+        .map(|c| MissingChip { id: c.id, x: c.x, y: c.y, w: c.w, h: c.h, file_name: c.file_name })
+        .collect();
+    let held_chips = total_chips - missing_chips.len() as i64;
+
+    let failures = failed_preconditions(&user_address, nft_id, nft.as_ref(), total_chips, held_chips);
+    Ok(EligibilityReport {
+        nft_id,
+        eligible: failures.is_empty(),
+        owner: nft.as_ref().and_then(|n| n.owner.clone()),
+        received: nft.as_ref().is_some_and(|n| n.received),
+        mint_state: nft.as_ref().and_then(|n| MintState::from_i32(n.is_mint)).map(MintState::as_str),
+        total_chips,
+        held_chips,
+        failures,
+        missing_chips,
+        user_address,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: &str = "0x00000000000000000000000000000000000000aa";
+
+    fn nft(owner: Option<&str>, received: bool, is_mint: i32) -> NftSnapshot {
+        NftSnapshot { owner: owner.map(str::to_string), received, is_mint }
+    }
+
+    fn codes(failures: Vec<FailedPrecondition>) -> Vec<&'static str> {
+        failures.into_iter().map(|f| f.code).collect()
+    }
+
+    #[test]
+    fn eligible_when_everything_holds() {
+        assert!(failed_preconditions(USER, 1, Some(&nft(Some(USER), true, 0)), 4, 4).is_empty());
+    }
+
+    #[test]
+    fn reports_every_failure() {
+        assert_eq!(codes(failed_preconditions(USER, 1, None, 0, 0)), ["nft_not_found"]);
+        assert_eq!(
+            codes(failed_preconditions(USER, 1, Some(&nft(Some("0xbb"), false, 2)), 4, 1)),
+            ["not_owner", "not_received", "already_minted", "missing_chips"]
+        );
+        assert_eq!(
+            codes(failed_preconditions(USER, 1, Some(&nft(None, true, 1)), 0, 0)),
+            ["not_owner", "mint_in_progress", "no_chips"]
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+use std::str::FromStr;
+
+const PREFIX: &str = "MintNFT#";
+
+/// The `remark` carried by UserMint / HakuNFTMint events, identifying the minted NFT
+///
+/// Accepted formats (nothing else):
+/// - `12`
+/// - `MintNFT#12`
+/// - `MintNFT#12:<token url>` or `12:<token url>`
+///
+/// The NFT id must be a positive decimal without sign, leading zeros or whitespace.
+/// Encoding always produces the bare id, which is what the backend passes to the contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MintRemark {
+    pub nft_id: i32,
+    pub token_url: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid mint remark '{remark}': {reason}")]
+pub struct MintRemarkError {
+    pub remark: String,
+    pub reason: String,
+}
+
+impl MintRemark {
+    pub fn new(nft_id: i32) -> Self {
+        Self { nft_id, token_url: None }
+    }
+
+    /// Parse a remark; see the type docs for the accepted formats
+    pub fn parse(remark: &str) -> Result<Self, MintRemarkError> {
+        let invalid = |reason: &str| MintRemarkError {
+            remark: remark.to_string(),
+            reason: reason.to_string(),
+        };
+
+        let body = remark.strip_prefix(PREFIX).unwrap_or(remark);
+        let (id_part, token_url) = match body.split_once(':') {
+            Some((id_part, "")) => return Err(invalid(&format!("empty token url after nft id {}", id_part))),
+            Some((id_part, url)) => (id_part, Some(url.to_string())),
+            None => (body, None),
+        };
+
+        if id_part.is_empty() || !id_part.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid("nft id must be a decimal number"));
+        }
+        if id_part.len() > 1 && id_part.starts_with('0') {
+            return Err(invalid("nft id has leading zeros"));
+        }
+        let nft_id: i32 = id_part.parse()
+            .map_err(|_| invalid("nft id out of range"))?;
+        if nft_id <= 0 {
+            return Err(invalid("nft id must be positive"));
+        }
+
+        Ok(Self { nft_id, token_url })
+    }
+
+    /// Whether this remark refers to `nft_id`
+    pub fn is_for(remark: &str, nft_id: i32) -> bool {
+        Self::parse(remark).is_ok_and(|parsed| parsed.nft_id == nft_id)
+    }
+}
+
+impl FromStr for MintRemark {
+    type Err = MintRemarkError;
+
+    fn from_str(remark: &str) -> Result<Self, Self::Err> {
+        Self::parse(remark)
+    }
+}
+
+impl fmt::Display for MintRemark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.nft_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_supported_formats() {
+        assert_eq!(MintRemark::parse("12").unwrap(), MintRemark::new(12));
+        assert_eq!(MintRemark::parse("MintNFT#12").unwrap(), MintRemark::new(12));
+
+        let with_url = MintRemark::parse("MintNFT#12:ipfs://cid/12.json").unwrap();
+        assert_eq!(with_url.nft_id, 12);
+        assert_eq!(with_url.token_url.as_deref(), Some("ipfs://cid/12.json"));
+        assert_eq!(MintRemark::parse("12:https://x/12.json").unwrap().nft_id, 12);
+    }
+
+    #[test]
+    fn rejects_loose_input() {
+        for remark in ["", " 12", "+12", "-12", "012", "0", "12abc", "Foo#12", "MintNFT#", "12:", "99999999999"] {
+            assert!(MintRemark::parse(remark).is_err(), "accepted {:?}", remark);
+        }
+    }
+
+    #[test]
+    fn encodes_bare_id() {
+        let remark = MintRemark::parse("MintNFT#7:url").unwrap();
+        assert_eq!(remark.to_string(), "7");
+        assert!(MintRemark::is_for("MintNFT#7", 7));
+        assert!(!MintRemark::is_for("7", 8));
+    }
+}
@@ -0,0 +1,152 @@
+use tracing::{info, warn};
+use serde::Serialize;
+use alloy::primitives::{Address, Bytes};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
+use alloy::network::TransactionBuilder;
+use alloy::sol;
+use alloy::sol_types::{Panic, Revert, SolError, SolInterface};
+
+// Custom errors the HakuNFT contract can revert with (OpenZeppelin v5)
+sol! {
+    #[derive(Debug)]
+    interface HakuNFTErrors {
+        error OwnableUnauthorizedAccount(address account);
+        error ERC721InvalidReceiver(address receiver);
+        error ERC721InvalidSender(address sender);
+        error ERC721InvalidOwner(address owner);
+        error ERC721NonexistentToken(uint256 tokenId);
+        error EnforcedPause();
+    }
+}
+
+/// Decoded reason of a reverted simulation, returned to the client as-is
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RevertReason {
+    pub kind: String,               // error_string / panic / custom / unknown
+    pub name: Option<String>,       // custom error name
+    pub message: String,
+    pub selector: Option<String>,   // 4-byte selector (0x...)
+    pub data: String,               // raw revert data (0x...)
+}
+
+/// Decode revert data: `Error(string)`, `Panic(uint256)` or a known custom error
+pub fn decode_revert(data: &[u8]) -> RevertReason {
+    let raw = format!("0x{}", alloy::hex::encode(data));
+    let selector = (data.len() >= 4).then(|| format!("0x{}", alloy::hex::encode(&data[..4])));
+
+    if let Ok(revert) = Revert::abi_decode(data) {
+        return RevertReason {
+            kind: "error_string".to_string(),
+            name: None,
+            message: revert.reason,
+            selector,
+            data: raw,
+        };
+    }
+
+    if let Ok(panic) = Panic::abi_decode(data) {
+        return RevertReason {
+            kind: "panic".to_string(),
+            name: None,
+            message: panic.to_string(),
+            selector,
+            data: raw,
+        };
+    }
+
+    if let Ok(error) = HakuNFTErrors::HakuNFTErrorsErrors::abi_decode(data) {
+        use HakuNFTErrors::HakuNFTErrorsErrors as E;
+        let (name, message) = match error {
+            E::OwnableUnauthorizedAccount(e) => ("OwnableUnauthorizedAccount", format!("Signer {} is not allowed to mint", e.account)),
+            E::ERC721InvalidReceiver(e) => ("ERC721InvalidReceiver", format!("Receiver {} cannot accept NFTs", e.receiver)),
+            E::ERC721InvalidSender(e) => ("ERC721InvalidSender", format!("Invalid sender {}", e.sender)),
+            E::ERC721InvalidOwner(e) => ("ERC721InvalidOwner", format!("Invalid owner {}", e.owner)),
+            E::ERC721NonexistentToken(e) => ("ERC721NonexistentToken", format!("Token {} does not exist", e.tokenId)),
+            E::EnforcedPause(_) => ("EnforcedPause", "Minting is paused".to_string()),
+        };
+        return RevertReason {
+            kind: "custom".to_string(),
+            name: Some(name.to_string()),
+            message,
+            selector,
+            data: raw,
+        };
+    }
+
+    RevertReason {
+        kind: "unknown".to_string(),
+        name: None,
+        message: if data.is_empty() { "Reverted without reason".to_string() } else { "Reverted with unknown error".to_string() },
+        selector,
+        data: raw,
+    }
+}
+
+/// Simulate a call with `eth_call`
+///
+/// Returns `Ok(None)` if it would succeed, `Ok(Some(reason))` if it would revert, and `Err`
+/// if the simulation itself could not be run (RPC unavailable, ...).
+pub async fn simulate_call(
+    from: Address,
+    to: Address,
+    input: Bytes,
+) -> Result<Option<RevertReason>, Box<dyn std::error::Error + Send + Sync>> {
+    dotenv::dotenv().ok();
+    let rpc_url = std::env::var("RPC_URL")
+        .unwrap_or_else(|_| "https://dream-rpc.somnia.network".to_string());
+    let provider = ProviderBuilder::new()
+        .connect_http(rpc_url.parse()?);
+
+    let request = TransactionRequest::default()
+        .with_from(from)
+        .with_to(to)
+        .with_input(input);
+
+    match provider.call(request).await {
+        Ok(_) => {
+            info!("✅ Simulation passed for call to {:?}", to);
+            Ok(None)
+        }
+        Err(e) => match e.as_error_resp() {
+            Some(payload) if payload.message.contains("revert") => {
+                let data = payload.as_revert_data().unwrap_or_default();
+                let reason = decode_revert(&data);
+                warn!("⚠️  Simulation reverted: {} ({})", reason.message, reason.kind);
+                Ok(Some(reason))
+            }
+            _ => Err(e.into()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+
+    #[test]
+    fn decodes_error_string() {
+        let data = Revert::from("Already minted").abi_encode();
+        let reason = decode_revert(&data);
+        assert_eq!(reason.kind, "error_string");
+        assert_eq!(reason.message, "Already minted");
+        assert_eq!(reason.selector.as_deref(), Some("0x08c379a0"));
+    }
+
+    #[test]
+    fn decodes_custom_error() {
+        let data = HakuNFTErrors::ERC721NonexistentToken { tokenId: U256::from(7) }.abi_encode();
+        let reason = decode_revert(&data);
+        assert_eq!(reason.kind, "custom");
+        assert_eq!(reason.name.as_deref(), Some("ERC721NonexistentToken"));
+    }
+
+    #[test]
+    fn unknown_data_is_kept_raw() {
+        let reason = decode_revert(&[0xde, 0xad, 0xbe, 0xef, 0x01]);
+        assert_eq!(reason.kind, "unknown");
+        assert_eq!(reason.data, "0xdeadbeef01");
+        assert_eq!(decode_revert(&[]).message, "Reverted without reason");
+    }
+}
@@ -0,0 +1,548 @@
+use tracing::{info, warn};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use crate::entitys::entity::MintAttempt;
+
+/// NFT mint state stored in `nfts.is_mint`
+///
+/// Allowed transitions (also enforced by the `enforce_nft_mint_transition` trigger):
+/// Unminted → Applying, Applying → Unminted, Applying → Minted, Unminted → Minted, and
+/// Minted → Unminted only together with an edition bump (burn redemption in reset mode).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MintState {
+    /// 0: 未申请
+    Unminted,
+    /// 1: 申请中
+    Applying,
+    /// 2: 已 mint
+    Minted,
+}
+
+impl MintState {
+    pub fn as_i32(self) -> i32 {
+        match self {
+            Self::Unminted => 0,
+            Self::Applying => 1,
+            Self::Minted => 2,
+        }
+    }
+
+    pub fn from_i32(value: i32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Unminted),
+            1 => Some(Self::Applying),
+            2 => Some(Self::Minted),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Unminted => "unminted",
+            Self::Applying => "applying",
+            Self::Minted => "minted",
+        }
+    }
+
+    /// Whether moving from `self` to `next` is a legal transition (staying put is always legal)
+    ///
+    /// `edition_bumped` tells whether the same update increases `nfts.edition`, which is what
+    /// allows a minted NFT to return to Unminted.
+    pub fn can_transition_to(self, next: MintState, edition_bumped: bool) -> bool {
+        matches!(
+            (self, next),
+            (Self::Unminted, Self::Applying)
+                | (Self::Applying, Self::Unminted)
+                | (Self::Applying, Self::Minted)
+                | (Self::Unminted, Self::Minted)
+        ) || (self == Self::Minted && next == Self::Unminted && edition_bumped)
+            || self == next
+    }
+}
+
+/// Who pays for and submits the mint transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MintMode {
+    /// 用户钱包自行调用合约
+    SelfPay,
+    /// 后端私钥代为调用 safeMint
+    Backend,
+}
+
+impl MintMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::SelfPay => "self_pay",
+            Self::Backend => "backend",
+        }
+    }
+}
+
+/// Status of a row in `mint_attempts`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttemptStatus {
+    Pending,
+    Submitted,
+    Succeeded,
+    Failed,
+    Expired,
+}
+
+impl AttemptStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Submitted => "submitted",
+            Self::Succeeded => "succeeded",
+            Self::Failed => "failed",
+            Self::Expired => "expired",
+        }
+    }
+}
+
+/// Move the user's NFT from Unminted to Applying and open a mint attempt
+///
+/// Returns `None` if the NFT is not owned by the user or is not in the Unminted state.
+/// Backend attempts stay `pending` until the mint job worker picks them up.
+pub async fn begin_mint_attempt(
+    pool: &PgPool,
+    user_address: &str,
+    nft_id: i32,
+    mode: MintMode,
+    mint_param: u64,
+) -> Result<Option<i64>, sqlx::Error> {
+    let user_lower = user_address.to_lowercase();
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE nfts
+        SET is_mint = $1, mint_started_at = NOW()
+        WHERE id = $2 AND LOWER(user_address) = $3 AND is_mint = $4
+        "#,
+        MintState::Applying.as_i32(),
+        nft_id,
+        user_lower,
+        MintState::Unminted.as_i32()
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        warn!("⚠️  NFT {} of user {} cannot move to applying (not owned or not unminted)", nft_id, user_lower);
+        tx.rollback().await?;
+        return Ok(None);
+    }
+
+    let attempt = sqlx::query!(
+        r#"
+        INSERT INTO mint_attempts (nft_id, user_address, mode, status, mint_param)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+        nft_id,
+        user_lower,
+        mode.as_str(),
+        AttemptStatus::Pending.as_str(),
+        mint_param as i64
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    info!("📝 Opened mint attempt {} for NFT {} (user={}, mode={})", attempt.id, nft_id, user_lower, mode.as_str());
+    Ok(Some(attempt.id))
+}
+
+/// Outcome of opening a batch of mint attempts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MintBatchStart {
+    /// All NFTs moved to Applying; attempt ids are in the order of the request
+    Started { batch_id: i64, attempt_ids: Vec<i64> },
+    /// Nothing changed; these NFTs are not owned, not complete or not Unminted
+    Ineligible(Vec<i32>),
+}
+
+/// Verify and move several NFTs of a user from Unminted to Applying in one transaction
+///
+/// Each entry is `(nft_id, mint_param)`. Either every NFT is eligible (owned and received,
+/// all chips owned and received, Unminted) and all of them get an attempt sharing a new
+/// batch id, or nothing is changed. The NFTs and their chips stay locked until commit.
+pub async fn begin_mint_batch(
+    pool: &PgPool,
+    user_address: &str,
+    items: &[(i32, u64)],
+    mode: MintMode,
+) -> Result<MintBatchStart, sqlx::Error> {
+    let user_lower = user_address.to_lowercase();
+    let nft_ids: Vec<i32> = items.iter().map(|(nft_id, _)| *nft_id).collect();
+    let mut tx = pool.begin().await?;
+
+    // 先锁定 chips，校验到提交之间不会被转走或回收
+    sqlx::query!(
+        "SELECT id FROM chips WHERE nft_id = ANY($1) ORDER BY id FOR UPDATE",
+        &nft_ids
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let eligible = sqlx::query_scalar!(
+        r#"
+        SELECT n.id
+        FROM nfts n
+        WHERE n.id = ANY($1)
+          AND LOWER(n.user_address) = $2
+          AND n.received = true
+          AND n.is_mint = $3
+          AND EXISTS (SELECT 1 FROM chips c WHERE c.nft_id = n.id)
+          AND NOT EXISTS (
+              SELECT 1 FROM chips c
+              WHERE c.nft_id = n.id
+                AND (c.user_address IS NULL OR LOWER(c.user_address) <> $2 OR c.received IS NOT TRUE)
+          )
+        FOR UPDATE OF n
+        "#,
+        &nft_ids,
+        user_lower,
+        MintState::Unminted.as_i32()
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let ineligible: Vec<i32> = nft_ids.iter()
+        .copied()
+        .filter(|nft_id| !eligible.contains(nft_id))
+        .collect();
+    if !ineligible.is_empty() {
+        warn!("⚠️  Batch mint for user {} rejected, ineligible NFTs: {:?}", user_lower, ineligible);
+        tx.rollback().await?;
+        return Ok(MintBatchStart::Ineligible(ineligible));
+    }
+
+    sqlx::query!(
+        "UPDATE nfts SET is_mint = $1, mint_started_at = NOW() WHERE id = ANY($2)",
+        MintState::Applying.as_i32(),
+        &nft_ids
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let batch_id = sqlx::query_scalar!(r#"SELECT nextval('mint_batch_id_seq') AS "batch_id!""#)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    let mut attempt_ids = Vec::with_capacity(items.len());
+    for (nft_id, mint_param) in items {
+        let attempt_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO mint_attempts (nft_id, user_address, mode, status, mint_param, batch_id)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id
+            "#,
+            nft_id,
+            user_lower,
+            mode.as_str(),
+            AttemptStatus::Pending.as_str(),
+            *mint_param as i64,
+            batch_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        attempt_ids.push(attempt_id);
+    }
+
+    tx.commit().await?;
+    info!("📝 Opened mint batch {} with {} attempts (user={}, mode={})", batch_id, attempt_ids.len(), user_lower, mode.as_str());
+    Ok(MintBatchStart::Started { batch_id, attempt_ids })
+}
+
+/// Pending jobs of a backend mint batch, in id order
+pub async fn pending_batch_jobs(pool: &PgPool, batch_id: i64) -> Result<Vec<MintAttempt>, sqlx::Error> {
+    sqlx::query_as!(
+        MintAttempt,
+        r#"
+        SELECT id, nft_id, user_address, mode, status, tx_hash, token_id, error,
+               mint_param, batch_id, started_at, finished_at
+        FROM mint_attempts
+        WHERE batch_id = $1 AND mode = $2 AND status = $3
+        ORDER BY id
+        "#,
+        batch_id,
+        MintMode::Backend.as_str(),
+        AttemptStatus::Pending.as_str()
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Oldest queued backend mint job that is not in `claimed` (taken by another worker), if any
+pub async fn next_backend_mint_job(pool: &PgPool, claimed: &[i64]) -> Result<Option<MintAttempt>, sqlx::Error> {
+    sqlx::query_as!(
+        MintAttempt,
+        r#"
+        SELECT id, nft_id, user_address, mode, status, tx_hash, token_id, error,
+               mint_param, batch_id, started_at, finished_at
+        FROM mint_attempts
+        WHERE mode = $1 AND status = $2 AND id <> ALL($3)
+        ORDER BY id
+        LIMIT 1
+        "#,
+        MintMode::Backend.as_str(),
+        AttemptStatus::Pending.as_str(),
+        claimed
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Look up a mint attempt (or backend mint job) by id
+pub async fn get_mint_attempt(pool: &PgPool, attempt_id: i64) -> Result<Option<MintAttempt>, sqlx::Error> {
+    sqlx::query_as!(
+        MintAttempt,
+        r#"
+        SELECT id, nft_id, user_address, mode, status, tx_hash, token_id, error,
+               mint_param, batch_id, started_at, finished_at
+        FROM mint_attempts
+        WHERE id = $1
+        "#,
+        attempt_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// The open (pending or submitted) attempt of an NFT, if any
+pub async fn open_mint_attempt(pool: &PgPool, nft_id: i32) -> Result<Option<MintAttempt>, sqlx::Error> {
+    sqlx::query_as!(
+        MintAttempt,
+        r#"
+        SELECT id, nft_id, user_address, mode, status, tx_hash, token_id, error,
+               mint_param, batch_id, started_at, finished_at
+        FROM mint_attempts
+        WHERE nft_id = $1 AND status IN ('pending', 'submitted')
+        ORDER BY id DESC
+        LIMIT 1
+        "#,
+        nft_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// All attempts of an NFT, newest first
+pub async fn list_mint_attempts(pool: &PgPool, nft_id: i32) -> Result<Vec<MintAttempt>, sqlx::Error> {
+    sqlx::query_as!(
+        MintAttempt,
+        r#"
+        SELECT id, nft_id, user_address, mode, status, tx_hash, token_id, error,
+               mint_param, batch_id, started_at, finished_at
+        FROM mint_attempts
+        WHERE nft_id = $1
+        ORDER BY id DESC
+        "#,
+        nft_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// The attempt whose latest submitted transaction is `tx_hash`
+pub async fn find_attempt_by_tx_hash(pool: &PgPool, tx_hash: &str) -> Result<Option<MintAttempt>, sqlx::Error> {
+    sqlx::query_as!(
+        MintAttempt,
+        r#"
+        SELECT id, nft_id, user_address, mode, status, tx_hash, token_id, error,
+               mint_param, batch_id, started_at, finished_at
+        FROM mint_attempts
+        WHERE LOWER(tx_hash) = LOWER($1)
+        ORDER BY id DESC
+        LIMIT 1
+        "#,
+        tx_hash
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Record the transaction hash of a submitted mint attempt
+pub async fn mark_attempt_submitted(pool: &PgPool, attempt_id: i64, tx_hash: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE mint_attempts SET status = $2, tx_hash = $3 WHERE id = $1",
+        attempt_id,
+        AttemptStatus::Submitted.as_str(),
+        tx_hash
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Store the EIP-712 voucher issued for a self-pay attempt (the attempt id is its nonce)
+pub async fn attach_attempt_voucher(
+    pool: &PgPool,
+    attempt_id: i64,
+    deadline: DateTime<Utc>,
+    signature: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE mint_attempts SET voucher_deadline = $2, voucher_signature = $3 WHERE id = $1",
+        attempt_id,
+        deadline,
+        signature
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Roll the user's NFT back from Applying to Unminted and close its attempt as failed
+///
+/// Returns false if the NFT was not in the Applying state for this user.
+pub async fn fail_mint_attempt(
+    pool: &PgPool,
+    user_address: &str,
+    nft_id: i32,
+    error: &str,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE nfts
+        SET is_mint = $1, mint_started_at = NULL
+        WHERE id = $2 AND LOWER(user_address) = $3 AND is_mint = $4
+        "#,
+        MintState::Unminted.as_i32(),
+        nft_id,
+        user_address.to_lowercase(),
+        MintState::Applying.as_i32()
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        tx.rollback().await?;
+        return Ok(false);
+    }
+
+    close_open_attempt(&mut tx, nft_id, AttemptStatus::Failed, Some(error), None).await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Close an attempt as failed without touching the NFT (it already left the Applying state)
+pub async fn abandon_mint_attempt(pool: &PgPool, attempt_id: i64, error: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE mint_attempts
+        SET status = $2, error = $3, finished_at = NOW()
+        WHERE id = $1 AND status IN ('pending', 'submitted')
+        "#,
+        attempt_id,
+        AttemptStatus::Failed.as_str(),
+        error
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Roll a stale Applying NFT back to Unminted and close its attempt as expired
+///
+/// Only applies if the NFT is still in the same application (`mint_started_at` unchanged).
+pub async fn expire_mint_attempt(
+    pool: &PgPool,
+    nft_id: i32,
+    mint_started_at: Option<DateTime<Utc>>,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE nfts
+        SET is_mint = $1, mint_started_at = NULL
+        WHERE id = $2 AND is_mint = $3 AND mint_started_at IS NOT DISTINCT FROM $4
+        "#,
+        MintState::Unminted.as_i32(),
+        nft_id,
+        MintState::Applying.as_i32(),
+        mint_started_at
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        tx.rollback().await?;
+        return Ok(false);
+    }
+
+    close_open_attempt(&mut tx, nft_id, AttemptStatus::Expired, Some("No UserMint found on chain before timeout"), None).await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Close the open attempt of an NFT that has been minted on chain (if any)
+pub async fn complete_mint_attempt(
+    tx: &mut Transaction<'_, Postgres>,
+    nft_id: i32,
+    token_id: i64,
+) -> Result<(), sqlx::Error> {
+    close_open_attempt(tx, nft_id, AttemptStatus::Succeeded, None, Some(token_id)).await
+}
+
+async fn close_open_attempt(
+    tx: &mut Transaction<'_, Postgres>,
+    nft_id: i32,
+    status: AttemptStatus,
+    error: Option<&str>,
+    token_id: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE mint_attempts
+        SET status = $2, error = $3, token_id = $4, finished_at = NOW()
+        WHERE nft_id = $1 AND status IN ('pending', 'submitted')
+        "#,
+        nft_id,
+        status.as_str(),
+        error,
+        token_id
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allowed_transitions() {
+        assert!(MintState::Unminted.can_transition_to(MintState::Applying, false));
+        assert!(MintState::Applying.can_transition_to(MintState::Unminted, false));
+        assert!(MintState::Applying.can_transition_to(MintState::Minted, false));
+        assert!(MintState::Unminted.can_transition_to(MintState::Minted, false));
+    }
+
+    #[test]
+    fn minted_resets_only_with_edition_bump() {
+        assert!(!MintState::Minted.can_transition_to(MintState::Unminted, false));
+        assert!(MintState::Minted.can_transition_to(MintState::Unminted, true));
+        assert!(!MintState::Minted.can_transition_to(MintState::Applying, false));
+        assert!(!MintState::Minted.can_transition_to(MintState::Applying, true));
+        assert!(MintState::Minted.can_transition_to(MintState::Minted, false));
+    }
+
+    #[test]
+    fn round_trips_through_i32() {
+        for state in [MintState::Unminted, MintState::Applying, MintState::Minted] {
+            assert_eq!(MintState::from_i32(state.as_i32()), Some(state));
+        }
+        assert_eq!(MintState::from_i32(3), None);
+    }
+}
@@ -0,0 +1,134 @@
+use tracing::info;
+use serde::Serialize;
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::network::TransactionResponse;
+use alloy::consensus::Transaction;
+use alloy::primitives::{Address, B256};
+use crate::config::get_pool_config;
+use crate::services::mint_sweeper::UserMint;
+
+/// UserMint event emitted by a mint transaction
+#[derive(Debug, Clone, Serialize)]
+pub struct ObservedUserMint {
+    pub token_id: String,
+    pub user: String,
+    pub remark: String,     // 即 nft_id
+    pub token_url: String,
+}
+
+/// On-chain outcome of a mint transaction
+#[derive(Debug, Clone, Serialize)]
+pub struct MintReceiptStatus {
+    pub tx_hash: String,
+    pub mined: bool,                        // false = no receipt yet (pending or unknown hash)
+    pub success: Option<bool>,              // receipt status
+    pub block_number: Option<u64>,
+    pub user_mint: Option<ObservedUserMint>, // UserMint emitted by the NFT contract in this tx
+}
+
+/// Fetch the receipt of a mint transaction and decode its UserMint event, if any
+pub async fn fetch_mint_receipt(tx_hash: &str) -> Result<MintReceiptStatus, Box<dyn std::error::Error + Send + Sync>> {
+    let hash: B256 = tx_hash.parse()?;
+    let nft_contract = get_pool_config()?.nft_contract;
+
+    dotenv::dotenv().ok();
+    let rpc_url = std::env::var("RPC_URL")
+        .unwrap_or_else(|_| "https://dream-rpc.somnia.network".to_string());
+    let provider = ProviderBuilder::new()
+        .connect_http(rpc_url.parse()?);
+
+    let tx_hash = format!("{:?}", hash);
+    let Some(receipt) = provider.get_transaction_receipt(hash).await? else {
+        info!("No receipt yet for {}", tx_hash);
+        return Ok(MintReceiptStatus {
+            tx_hash,
+            mined: false,
+            success: None,
+            block_number: None,
+            user_mint: None,
+        });
+    };
+
+    let user_mint = receipt.inner.logs().iter()
+        .filter(|log| log.address() == nft_contract)
+        .find_map(|log| log.log_decode::<UserMint>().ok())
+        .map(|decoded| {
+            let event = decoded.inner.data;
+            ObservedUserMint {
+                token_id: event.tokenId.to_string(),
+                user: format!("{:?}", event.user).to_lowercase(),
+                remark: event.remark,
+                token_url: event.token_url,
+            }
+        });
+
+    Ok(MintReceiptStatus {
+        tx_hash,
+        mined: true,
+        success: Some(receipt.status()),
+        block_number: receipt.block_number,
+        user_mint,
+    })
+}
+
+/// What the chain says about a mint transaction reported by a client
+#[derive(Debug, Clone)]
+pub enum MintTxOutcome {
+    /// Mined successfully; carries the UserMint event if one was emitted
+    Succeeded(Option<ObservedUserMint>),
+    /// Mined but reverted
+    Reverted,
+    /// Known to the node but not mined yet
+    Pending,
+    /// Neither mined nor in the mempool (dropped or never broadcast)
+    NotFound,
+    /// Not sent by the expected sender to the NFT contract
+    Unrelated,
+}
+
+/// Classify a mint transaction by its receipt, falling back to the node's pending pool
+///
+/// With `sender` set the transaction must have been sent by `sender` to the NFT contract,
+/// otherwise it is `Unrelated` (used for hashes supplied by clients).
+pub async fn check_mint_transaction(
+    tx_hash: &str,
+    sender: Option<Address>,
+) -> Result<MintTxOutcome, Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(sender) = sender {
+        let nft_contract = get_pool_config()?.nft_contract;
+
+        dotenv::dotenv().ok();
+        let rpc_url = std::env::var("RPC_URL")
+            .unwrap_or_else(|_| "https://dream-rpc.somnia.network".to_string());
+        let provider = ProviderBuilder::new()
+            .connect_http(rpc_url.parse()?);
+
+        match provider.get_transaction_by_hash(tx_hash.parse::<B256>()?).await? {
+            None => return Ok(MintTxOutcome::NotFound),
+            Some(tx) if tx.from() != sender || tx.to() != Some(nft_contract) => {
+                info!("Tx {} was sent by {:?} to {:?}, not by {:?} to the NFT contract", tx_hash, tx.from(), tx.to(), sender);
+                return Ok(MintTxOutcome::Unrelated);
+            }
+            Some(_) => {}
+        }
+    }
+
+    let status = fetch_mint_receipt(tx_hash).await?;
+    if status.mined {
+        return Ok(match status.success {
+            Some(true) => MintTxOutcome::Succeeded(status.user_mint),
+            _ => MintTxOutcome::Reverted,
+        });
+    }
+
+    dotenv::dotenv().ok();
+    let rpc_url = std::env::var("RPC_URL")
+        .unwrap_or_else(|_| "https://dream-rpc.somnia.network".to_string());
+    let provider = ProviderBuilder::new()
+        .connect_http(rpc_url.parse()?);
+
+    match provider.get_transaction_by_hash(tx_hash.parse::<B256>()?).await? {
+        Some(_) => Ok(MintTxOutcome::Pending),
+        None => Ok(MintTxOutcome::NotFound),
+    }
+}
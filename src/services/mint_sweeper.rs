@@ -0,0 +1,151 @@
+use tracing::{info, warn, error};
+use chrono::Utc;
+use sqlx::PgPool;
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::primitives::Address;
+use alloy::rpc::types::Filter;
+use alloy::sol;
+use alloy::sol_types::SolEvent;
+use crate::config::{MintSweepConfig, get_pool_config};
+use crate::services::service::process_user_mint_event;
+use crate::services::mint_state::expire_mint_attempt;
+use crate::services::mint_remark::MintRemark;
+use crate::services::nft_redemption::redeemed_token_ids;
+
+// HakuNFT 合约的 UserMint 事件
+sol! {
+    #[derive(Debug)]
+    event UserMint(
+        uint256 indexed tokenId,
+        address indexed user,
+        string remark,
+        string token_url
+    );
+}
+
+/// A UserMint event found on chain for a stale application
+#[derive(Debug, Clone)]
+pub struct ChainMint {
+    pub token_id: String,
+    pub block_number: u64,
+    pub token_url: String,
+}
+
+/// Look for a UserMint event for `nft_id` (the event remark) sent to `user_address`
+/// within the last `lookback_blocks` blocks
+///
+/// Tokens in `skip_tokens` (earlier editions burned and redeemed) are ignored.
+pub async fn find_user_mint_on_chain(
+    user_address: &str,
+    nft_id: i32,
+    lookback_blocks: u64,
+    skip_tokens: &[i64],
+) -> Result<Option<ChainMint>, Box<dyn std::error::Error + Send + Sync>> {
+    dotenv::dotenv().ok();
+    let rpc_url = std::env::var("RPC_URL")
+        .unwrap_or_else(|_| "https://dream-rpc.somnia.network".to_string());
+    let nft_contract = get_pool_config()?.nft_contract;
+    let user: Address = user_address.parse()?;
+
+    let provider = ProviderBuilder::new()
+        .connect_http(rpc_url.parse()?);
+
+    let latest = provider.get_block_number().await?;
+    let filter = Filter::new()
+        .address(nft_contract)
+        .event_signature(UserMint::SIGNATURE_HASH)
+        .topic2(user.into_word())
+        .from_block(latest.saturating_sub(lookback_blocks))
+        .to_block(latest);
+
+    for log in provider.get_logs(&filter).await? {
+        if let Ok(decoded) = log.log_decode::<UserMint>() {
+            let event = decoded.inner;
+            let token_id = event.tokenId.to_string();
+            if MintRemark::is_for(&event.remark, nft_id)
+                && !skip_tokens.iter().any(|skip| skip.to_string() == token_id)
+            {
+                return Ok(Some(ChainMint {
+                    token_id,
+                    block_number: log.block_number.unwrap_or(0),
+                    token_url: event.token_url.clone(),
+                }));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Resolve NFTs stuck in is_mint = 1 longer than the configured timeout
+///
+/// If the chain has a matching UserMint the NFT is completed (is_mint = 2) exactly like
+/// the event worker would; otherwise it is reset to 0. NFTs whose chain lookup fails are
+/// left alone until the next run, as are NFTs whose completion fails. Returns the users whose mint state changed.
+pub async fn sweep_stale_mints(pool: &PgPool, config: &MintSweepConfig) -> Result<Vec<String>, sqlx::Error> {
+    let cutoff = Utc::now() - chrono::Duration::seconds(config.timeout_secs);
+
+    let stale = sqlx::query!(
+        r#"
+        SELECT id, user_address as "user_address!", mint_started_at
+        FROM nfts
+        WHERE is_mint = 1
+          AND user_address IS NOT NULL
+          AND (mint_started_at IS NULL OR mint_started_at < $1)
+        ORDER BY mint_started_at ASC NULLS FIRST
+        LIMIT 100
+        "#,
+        cutoff
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if stale.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    info!("🧹 Found {} NFTs stuck in 'applying' for over {}s", stale.len(), config.timeout_secs);
+
+    let mut affected_users = Vec::new();
+    for nft in stale {
+        let user_address = nft.user_address.to_lowercase();
+
+        let redeemed_tokens = redeemed_token_ids(pool, nft.id).await?;
+        match find_user_mint_on_chain(&user_address, nft.id, config.lookback_blocks, &redeemed_tokens).await {
+            Ok(Some(mint)) => {
+                info!("✅ NFT {} was minted on chain (token_id={}, block={}), completing it",
+                    nft.id, mint.token_id, mint.block_number);
+                // 单个 NFT 处理失败不影响本轮其余 NFT，下次扫描重试
+                if let Err(e) = process_user_mint_event(
+                    pool,
+                    &user_address,
+                    &mint.token_id,
+                    mint.block_number,
+                    &nft.id.to_string(),
+                    &mint.token_url,
+                ).await {
+                    error!("❌ Failed to complete NFT {} from chain mint: {:?}", nft.id, e);
+                    continue;
+                }
+            }
+            Ok(None) => {
+                // 仅在状态未被其他流程修改时回滚
+                let expired = expire_mint_attempt(pool, nft.id, nft.mint_started_at).await?;
+                if !expired {
+                    continue;
+                }
+                warn!("⏱️  NFT {} of user {} had no UserMint on chain, reset is_mint to 0", nft.id, user_address);
+            }
+            Err(e) => {
+                error!("❌ Failed to check chain for NFT {} mint: {:?}", nft.id, e);
+                continue;
+            }
+        }
+
+        if !affected_users.contains(&user_address) {
+            affected_users.push(user_address);
+        }
+    }
+
+    Ok(affected_users)
+}
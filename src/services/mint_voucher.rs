@@ -0,0 +1,154 @@
+use tracing::info;
+use chrono::{DateTime, Utc};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::primitives::{Address, U256};
+use alloy::signers::SignerSync;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::sol;
+use alloy::sol_types::{Eip712Domain, SolStruct};
+use serde::Serialize;
+use crate::config::{VoucherConfig, get_pool_config};
+
+// EIP-712 typed data verified by the NFT contract in self-pay mode
+sol! {
+    #[derive(Debug)]
+    struct MintVoucher {
+        address user;
+        uint256 nftId;
+        uint256 param;
+        uint256 deadline;
+        uint256 nonce;
+    }
+}
+
+/// A voucher signed by the backend, returned to the frontend for the contract call
+#[derive(Debug, Clone, Serialize)]
+pub struct SignedMintVoucher {
+    pub user: String,
+    pub nft_id: String,
+    pub param: String,
+    pub deadline: u64,
+    pub nonce: String,
+    pub chain_id: u64,
+    pub verifying_contract: String,
+    pub signer: String,
+    pub signature: String,
+}
+
+/// Chain and contract a voucher is bound to (the EIP-712 domain besides name and version)
+#[derive(Debug, Clone, Copy)]
+pub struct VoucherDomain {
+    pub chain_id: u64,
+    pub verifying_contract: Address,
+}
+
+impl VoucherDomain {
+    /// The configured NFT contract, and `CHAIN_ID` or the chain id reported by the RPC
+    pub async fn resolve(config: &VoucherConfig) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let verifying_contract = get_pool_config()?.nft_contract;
+
+        let chain_id = match config.chain_id {
+            Some(chain_id) => chain_id,
+            None => {
+                dotenv::dotenv().ok();
+                let rpc_url = std::env::var("RPC_URL")
+                    .unwrap_or_else(|_| "https://dream-rpc.somnia.network".to_string());
+                let provider = ProviderBuilder::new()
+                    .connect_http(rpc_url.parse()?);
+                provider.get_chain_id().await?
+            }
+        };
+
+        Ok(Self { chain_id, verifying_contract })
+    }
+}
+
+/// Sign a mint voucher for `user`; the nonce must be unique per voucher (the mint attempt id)
+pub fn sign_mint_voucher(
+    config: &VoucherConfig,
+    voucher_domain: &VoucherDomain,
+    user: Address,
+    nft_id: i32,
+    param: u64,
+    nonce: i64,
+    deadline: DateTime<Utc>,
+) -> Result<SignedMintVoucher, Box<dyn std::error::Error + Send + Sync>> {
+    let signer: PrivateKeySigner = config.signer_key.parse()
+        .map_err(|e: alloy::signers::local::LocalSignerError| format!("Failed to parse voucher signer key: {:?}", e))?;
+    let VoucherDomain { chain_id, verifying_contract } = *voucher_domain;
+
+    let domain = Eip712Domain::new(
+        Some(config.domain_name.clone().into()),
+        Some(config.domain_version.clone().into()),
+        Some(U256::from(chain_id)),
+        Some(verifying_contract),
+        None,
+    );
+
+    let voucher = MintVoucher {
+        user,
+        nftId: U256::from(nft_id as u64),
+        param: U256::from(param),
+        deadline: U256::from(deadline.timestamp().max(0) as u64),
+        nonce: U256::from(nonce as u64),
+    };
+
+    let hash = voucher.eip712_signing_hash(&domain);
+    let signature = signer.sign_hash_sync(&hash)?;
+
+    info!("✍️  Signed mint voucher for user {:?}, nft_id {}, nonce {}", user, nft_id, nonce);
+
+    Ok(SignedMintVoucher {
+        user: format!("{:?}", user),
+        nft_id: nft_id.to_string(),
+        param: param.to_string(),
+        deadline: deadline.timestamp().max(0) as u64,
+        nonce: nonce.to_string(),
+        chain_id,
+        verifying_contract: format!("{:?}", verifying_contract),
+        signer: format!("{:?}", signer.address()),
+        signature: format!("0x{}", alloy::hex::encode(signature.as_bytes())),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_recovers_to_signer() {
+        let signer = PrivateKeySigner::random();
+        let config = VoucherConfig {
+            signer_key: alloy::hex::encode(signer.to_bytes()),
+            domain_name: "HakuNFT".to_string(),
+            domain_version: "1".to_string(),
+            chain_id: Some(50312),
+            ttl_secs: 600,
+        };
+        let voucher_domain = VoucherDomain {
+            chain_id: 50312,
+            verifying_contract: Address::repeat_byte(0x22),
+        };
+        let user = Address::repeat_byte(0x11);
+        let deadline = Utc::now();
+        let signed = sign_mint_voucher(&config, &voucher_domain, user, 27, 27, 1, deadline).unwrap();
+
+        let domain = Eip712Domain::new(
+            Some("HakuNFT".into()),
+            Some("1".into()),
+            Some(U256::from(50312u64)),
+            Some(Address::repeat_byte(0x22)),
+            None,
+        );
+        let voucher = MintVoucher {
+            user,
+            nftId: U256::from(27u64),
+            param: U256::from(27u64),
+            deadline: U256::from(signed.deadline),
+            nonce: U256::from(1u64),
+        };
+        let signature: alloy::primitives::Signature = signed.signature.parse().unwrap();
+        let recovered = signature.recover_address_from_prehash(&voucher.eip712_signing_hash(&domain)).unwrap();
+        assert_eq!(recovered, signer.address());
+    }
+}
@@ -1,2 +1,21 @@
 pub mod service;
-pub mod time_utils;
\ No newline at end of file
+pub mod time_utils;
+pub mod blacklist;
+pub mod time_weighted;
+pub mod anti_reroll;
+pub mod mint_sweeper;
+pub mod mint_state;
+pub mod mint_voucher;
+pub mod siwe;
+pub mod signer;
+pub mod mint_simulation;
+pub mod mint_status;
+pub mod mint_remark;
+pub mod mint_anomaly;
+pub mod nft_metadata;
+pub mod ipfs;
+pub mod gallery;
+pub mod nft_ownership;
+pub mod nft_redemption;
+pub mod mint_eligibility;
+pub mod trade_directory;
\ No newline at end of file
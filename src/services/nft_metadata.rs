@@ -0,0 +1,173 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::PgPool;
+use crate::config::{AttributeTemplate, MetadataConfig};
+use crate::services::mint_state::MintState;
+
+/// Database fields a minted NFT's metadata is built from
+#[derive(Debug, Clone)]
+pub struct MetadataSource {
+    pub nft_id: i32,
+    pub token_id: i64,
+    pub file_name: Option<String>,
+    pub token_url: Option<String>,
+    pub chip_count: i64,
+    pub mint_block: Option<i64>,
+    pub completed_at: Option<DateTime<Utc>>,   // nfts.minted_at, stable across later updates
+}
+
+/// OpenSea-compatible ERC-721 metadata
+#[derive(Debug, Clone, Serialize)]
+pub struct NftMetadata {
+    pub name: String,
+    pub description: String,
+    pub image: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_url: Option<String>,
+    pub attributes: Vec<MetadataAttribute>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetadataAttribute {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_type: Option<String>,
+    pub trait_type: String,
+    pub value: Value,
+}
+
+/// Load the metadata source of a minted token (`None` if no minted NFT has this token id)
+pub async fn load_metadata_source(pool: &PgPool, token_id: i64) -> Result<Option<MetadataSource>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT n.id, n.token_id AS "token_id!", n.file_name, n.token_url, n.block_number,
+               (SELECT COUNT(*) FROM chips c WHERE c.nft_id = n.id) AS "chip_count!",
+               n.minted_at AS completed_at
+        FROM nfts n
+        WHERE n.token_id = $1 AND n.is_mint = $2
+        LIMIT 1
+        "#,
+        token_id,
+        MintState::Minted.as_i32()
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(record.map(|r| MetadataSource {
+        nft_id: r.id,
+        token_id: r.token_id,
+        file_name: r.file_name,
+        token_url: r.token_url,
+        chip_count: r.chip_count,
+        mint_block: r.block_number,
+        completed_at: r.completed_at,
+    }))
+}
+
+/// Fill `{placeholder}`s of a template from the source
+pub fn render_template(template: &str, source: &MetadataSource, config: &MetadataConfig) -> String {
+    let file_name = source.file_name.clone().unwrap_or_else(|| format!("{}.png", source.nft_id));
+    let file_stem = std::path::Path::new(&file_name)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_string();
+
+    [
+        ("{token_id}", source.token_id.to_string()),
+        ("{nft_id}", source.nft_id.to_string()),
+        ("{file_name}", file_name.clone()),
+        ("{file_stem}", file_stem),
+        ("{token_url}", source.token_url.clone().unwrap_or_default()),
+        ("{chip_count}", source.chip_count.to_string()),
+        ("{mint_block}", source.mint_block.map(|b| b.to_string()).unwrap_or_default()),
+        ("{completed_at}", source.completed_at.map(|t| t.timestamp().to_string()).unwrap_or_default()),
+        ("{completed_date}", source.completed_at.map(|t| t.format("%Y-%m-%d").to_string()).unwrap_or_default()),
+        ("{image_cid}", config.image_cid.clone()),
+    ]
+    .iter()
+    .fold(template.to_string(), |out, (placeholder, value)| out.replace(placeholder, value))
+}
+
+fn render_attribute(template: &AttributeTemplate, source: &MetadataSource, config: &MetadataConfig) -> Option<MetadataAttribute> {
+    let rendered = render_template(&template.value, source, config);
+    if rendered.is_empty() {
+        return None;
+    }
+
+    // 数值型 display_type 需要输出 JSON number
+    let value = match template.display_type.as_deref() {
+        Some("number" | "boost_number" | "boost_percentage" | "date") => rendered.parse::<i64>()
+            .map(Value::from)
+            .or_else(|_| rendered.parse::<f64>().map(Value::from))
+            .unwrap_or(Value::String(rendered)),
+        _ => Value::String(rendered),
+    };
+
+    Some(MetadataAttribute {
+        display_type: template.display_type.clone(),
+        trait_type: template.trait_type.clone(),
+        value,
+    })
+}
+
+/// Build the metadata JSON of a minted NFT; attributes that render empty are omitted
+pub fn build_metadata(source: &MetadataSource, config: &MetadataConfig) -> NftMetadata {
+    NftMetadata {
+        name: render_template(&config.name_template, source, config),
+        description: render_template(&config.description, source, config),
+        image: render_template(&config.image_template, source, config),
+        external_url: config.external_url_template.as_ref()
+            .map(|template| render_template(template, source, config)),
+        attributes: config.attributes.iter()
+            .filter_map(|template| render_attribute(template, source, config))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_attribute_templates;
+    use chrono::TimeZone;
+
+    fn config() -> MetadataConfig {
+        MetadataConfig {
+            name_template: "Haku #{token_id}".to_string(),
+            description: "Puzzle {nft_id}".to_string(),
+            image_template: "ipfs://{image_cid}/{file_name}".to_string(),
+            external_url_template: None,
+            image_cid: "QmImages".to_string(),
+            attributes: parse_attribute_templates(
+                "Chips|number={chip_count};Mint Block|number={mint_block};Completed|date={completed_at};Day={completed_date}",
+            ).unwrap(),
+        }
+    }
+
+    fn source() -> MetadataSource {
+        MetadataSource {
+            nft_id: 27,
+            token_id: 5,
+            file_name: Some("27.png".to_string()),
+            token_url: None,
+            chip_count: 16,
+            mint_block: None,
+            completed_at: Some(Utc.with_ymd_and_hms(2025, 12, 1, 0, 0, 0).unwrap()),
+        }
+    }
+
+    #[test]
+    fn builds_metadata_from_templates() {
+        let metadata = build_metadata(&source(), &config());
+        assert_eq!(metadata.name, "Haku #5");
+        assert_eq!(metadata.description, "Puzzle 27");
+        assert_eq!(metadata.image, "ipfs://QmImages/27.png");
+
+        let json = serde_json::to_value(&metadata).unwrap();
+        assert!(json.get("external_url").is_none());
+        assert_eq!(json["attributes"][0]["value"], Value::from(16));
+        assert_eq!(json["attributes"][1]["trait_type"], "Completed");
+        assert_eq!(json["attributes"][1]["value"], Value::from(1764547200));
+        assert_eq!(json["attributes"][2]["value"], "2025-12-01");
+    }
+}
@@ -0,0 +1,324 @@
+use tracing::info;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{PgPool, Postgres, Transaction};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::primitives::Address;
+use alloy::rpc::types::{Filter, Log};
+use alloy::sol;
+use alloy::sol_types::SolEvent;
+use crate::config::{BurnRedeemConfig, NftTransferSyncConfig, get_pool_config};
+use crate::entitys::entity::{NftTransfer, NftTransferEvent};
+use crate::services::mint_state::MintState;
+use crate::services::nft_redemption::redeem_burned_nft;
+
+// ERC-721 标准 Transfer 事件（tokenId 为 indexed，与 ERC20 Transfer 的 topic0 相同但有 4 个 topic）
+sol! {
+    #[derive(Debug)]
+    event Transfer(
+        address indexed from,
+        address indexed to,
+        uint256 indexed tokenId
+    );
+}
+
+/// Decode an ERC-721 Transfer log emitted by `nft_contract`
+///
+/// Logs from other contracts and ERC-20 Transfers (3 topics) are ignored.
+pub fn decode_nft_transfer(log: &Log, nft_contract: Address) -> Option<NftTransferEvent> {
+    if log.address() != nft_contract || log.topics().len() != 4 {
+        return None;
+    }
+    let event = log.log_decode::<Transfer>().ok()?.inner;
+    Some(NftTransferEvent {
+        from: event.from.to_string(),
+        to: event.to.to_string(),
+        token_id: event.tokenId.to_string(),
+        block_number: log.block_number?,
+        log_index: log.log_index?,
+        tx_hash: format!("{:?}", log.transaction_hash?),
+        block_timestamp: log.block_timestamp,
+    })
+}
+
+/// Whether the Transfer burns the token (recipient is the zero address)
+pub fn is_burn(event: &NftTransferEvent) -> bool {
+    event.to.parse::<Address>().is_ok_and(|to| to == Address::ZERO)
+}
+
+/// When the block containing a Transfer was mined (from the log, or the block header)
+async fn transfer_block_time(event: &NftTransferEvent) -> Result<DateTime<Utc>, Box<dyn std::error::Error + Send + Sync>> {
+    let timestamp = match event.block_timestamp {
+        Some(timestamp) => timestamp,
+        None => {
+            dotenv::dotenv().ok();
+            let rpc_url = std::env::var("RPC_URL")
+                .unwrap_or_else(|_| "https://dream-rpc.somnia.network".to_string());
+            let provider = ProviderBuilder::new()
+                .connect_http(rpc_url.parse()?);
+            provider.get_block_by_number(event.block_number.into())
+                .await?
+                .ok_or_else(|| format!("Block {} not found", event.block_number))?
+                .header
+                .timestamp
+        }
+    };
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .ok_or_else(|| format!("Invalid block timestamp {}", timestamp).into())
+}
+
+/// Point `nfts.owner_address` of a minted token at the recipient of its latest indexed Transfer
+///
+/// Nothing changes while no Transfer of the token is indexed, or while the token is not
+/// minted in the database yet (the UserMint handler calls this again once it is).
+pub async fn sync_current_owner(tx: &mut Transaction<'_, Postgres>, token_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE nfts n
+        SET owner_address = t.to_address
+        FROM (
+            SELECT to_address
+            FROM nft_transfers
+            WHERE token_id = $1
+            ORDER BY block_number DESC, log_index DESC
+            LIMIT 1
+        ) t
+        WHERE n.token_id = $1 AND n.is_mint = $2
+          AND n.owner_address IS DISTINCT FROM t.to_address
+        "#,
+        token_id,
+        MintState::Minted.as_i32()
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Store a Transfer in the ownership history and update the token's current owner
+///
+/// A burn (Transfer to the zero address) is handed to the burn-to-redeem rules in the same
+/// transaction. Returns false if the event was already indexed (live listener and catch-up overlap).
+pub async fn record_nft_transfer(
+    pool: &PgPool,
+    event: &NftTransferEvent,
+    redeem: &BurnRedeemConfig,
+) -> Result<bool, sqlx::Error> {
+    let token_id: i64 = event.token_id.parse()
+        .map_err(|e| sqlx::Error::Decode(Box::new(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Failed to parse token_id: {}", e)
+        ))))?;
+
+    // burn 的 mint 时长按区块时间计算，而不是处理时间（补扫可能晚很久）
+    let is_burn = is_burn(event);
+    let burned_at = if is_burn && redeem.enabled {
+        Some(transfer_block_time(event).await.map_err(sqlx::Error::Decode)?)
+    } else {
+        None
+    };
+
+    let mut tx = pool.begin().await?;
+
+    let inserted = sqlx::query!(
+        r#"
+        INSERT INTO nft_transfers (token_id, from_address, to_address, block_number, log_index, tx_hash)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (tx_hash, log_index) DO NOTHING
+        "#,
+        token_id,
+        event.from.to_lowercase(),
+        event.to.to_lowercase(),
+        event.block_number as i64,
+        event.log_index as i64,
+        event.tx_hash
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected() > 0;
+
+    if inserted {
+        sync_current_owner(&mut tx, token_id).await?;
+        if is_burn {
+            redeem_burned_nft(&mut tx, event, token_id, redeem, burned_at.unwrap_or_else(Utc::now)).await?;
+        }
+    }
+    tx.commit().await?;
+
+    Ok(inserted)
+}
+
+/// Ownership history of a token, oldest first
+pub async fn ownership_history(pool: &PgPool, token_id: i64) -> Result<Vec<NftTransfer>, sqlx::Error> {
+    sqlx::query_as!(
+        NftTransfer,
+        r#"
+        SELECT id, token_id, from_address, to_address, block_number, log_index, tx_hash, created_at
+        FROM nft_transfers
+        WHERE token_id = $1
+        ORDER BY block_number, log_index
+        "#,
+        token_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// A minted NFT with its minter and current owner
+#[derive(Debug, Clone, Serialize)]
+pub struct NftOwnership {
+    pub nft_id: i32,
+    pub token_id: i64,
+    pub token_url: Option<String>,
+    pub file_name: Option<String>,
+    pub minter: Option<String>,           // nfts.user_address
+    pub owner: Option<String>,            // 当前链上持有者
+    pub block_number: Option<i64>,        // mint 区块
+    pub minted_at: Option<DateTime<Utc>>,
+    pub edition: i32,
+    pub burned_at: Option<DateTime<Utc>>, // new_edition 赎回后保留的已销毁版本
+}
+
+/// Minted NFT with the given token id
+pub async fn find_minted_nft(pool: &PgPool, token_id: i64) -> Result<Option<NftOwnership>, sqlx::Error> {
+    sqlx::query_as!(
+        NftOwnership,
+        r#"
+        SELECT id AS nft_id, token_id AS "token_id!", token_url, file_name,
+               LOWER(user_address) AS minter,
+               COALESCE(owner_address, LOWER(user_address)) AS owner,
+               block_number, minted_at, edition, burned_at
+        FROM nfts
+        WHERE token_id = $1 AND is_mint = $2
+        LIMIT 1
+        "#,
+        token_id,
+        MintState::Minted.as_i32()
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Minted NFTs currently held by `owner`, by token id
+pub async fn nfts_owned_by(pool: &PgPool, owner: &str) -> Result<Vec<NftOwnership>, sqlx::Error> {
+    sqlx::query_as!(
+        NftOwnership,
+        r#"
+        SELECT id AS nft_id, token_id AS "token_id!", token_url, file_name,
+               LOWER(user_address) AS minter,
+               COALESCE(owner_address, LOWER(user_address)) AS owner,
+               block_number, minted_at, edition, burned_at
+        FROM nfts
+        WHERE is_mint = $1 AND token_id IS NOT NULL AND burned_at IS NULL
+          AND COALESCE(owner_address, LOWER(user_address)) = $2
+        ORDER BY token_id
+        "#,
+        MintState::Minted.as_i32(),
+        owner.to_lowercase()
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Index Transfer events missed by the live listener (e.g. while the WebSocket was down)
+///
+/// Scans from the last synced block (or `start_block` on first run) to the latest block in
+/// `chunk_blocks` ranges, saving progress after each range. Returns the senders of the newly
+/// indexed burns, whose mint cache is stale once a burn is redeemed.
+pub async fn sync_nft_transfers(
+    pool: &PgPool,
+    config: &NftTransferSyncConfig,
+    redeem: &BurnRedeemConfig,
+) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+    dotenv::dotenv().ok();
+    let rpc_url = std::env::var("RPC_URL")
+        .unwrap_or_else(|_| "https://dream-rpc.somnia.network".to_string());
+    let nft_contract = get_pool_config()?.nft_contract;
+
+    let provider = ProviderBuilder::new()
+        .connect_http(rpc_url.parse()?);
+
+    let last_block = sqlx::query_scalar!("SELECT last_block FROM nft_transfer_sync")
+        .fetch_optional(pool)
+        .await?;
+    let mut from_block = last_block.map(|b| b as u64 + 1).unwrap_or(config.start_block);
+    let latest = provider.get_block_number().await?;
+
+    let mut recorded = 0;
+    let mut burners = Vec::new();
+    while from_block <= latest {
+        let to_block = (from_block + config.chunk_blocks - 1).min(latest);
+        let filter = Filter::new()
+            .address(nft_contract)
+            .event_signature(Transfer::SIGNATURE_HASH)
+            .from_block(from_block)
+            .to_block(to_block);
+
+        for log in provider.get_logs(&filter).await? {
+            if let Some(event) = decode_nft_transfer(&log, nft_contract)
+                && record_nft_transfer(pool, &event, redeem).await?
+            {
+                recorded += 1;
+                if is_burn(&event) {
+                    burners.push(event.from.to_lowercase());
+                }
+            }
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO nft_transfer_sync (id, last_block, updated_at)
+            VALUES (TRUE, $1, NOW())
+            ON CONFLICT (id) DO UPDATE SET last_block = EXCLUDED.last_block, updated_at = NOW()
+            "#,
+            to_block as i64
+        )
+        .execute(pool)
+        .await?;
+
+        from_block = to_block + 1;
+    }
+
+    if recorded > 0 {
+        info!("🔁 Indexed {} missed NFT Transfer events (up to block {})", recorded, latest);
+    }
+    Ok(burners)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{B256, Bytes, LogData, U256, address};
+
+    fn transfer_log(contract: Address, topics: Vec<B256>) -> Log {
+        Log {
+            inner: alloy::primitives::Log { address: contract, data: LogData::new_unchecked(topics, Bytes::new()) },
+            block_number: Some(120),
+            log_index: Some(3),
+            transaction_hash: Some(B256::repeat_byte(0xab)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn decodes_only_erc721_transfers_of_the_nft_contract() {
+        let nft = address!("0x00000000000000000000000000000000000000aa");
+        let from = address!("0x00000000000000000000000000000000000000b1");
+        let to = address!("0x00000000000000000000000000000000000000b2");
+        let topics = vec![
+            Transfer::SIGNATURE_HASH,
+            from.into_word(),
+            to.into_word(),
+            B256::from(U256::from(7)),
+        ];
+
+        let event = decode_nft_transfer(&transfer_log(nft, topics.clone()), nft).unwrap();
+        assert_eq!(event.token_id, "7");
+        assert_eq!(event.to.to_lowercase(), "0x00000000000000000000000000000000000000b2");
+        assert_eq!((event.block_number, event.log_index), (120, 3));
+
+        // 其它合约、ERC20 Transfer（3 个 topic）均忽略
+        assert!(decode_nft_transfer(&transfer_log(from, topics.clone()), nft).is_none());
+        assert!(decode_nft_transfer(&transfer_log(nft, topics[..3].to_vec()), nft).is_none());
+    }
+}
@@ -0,0 +1,276 @@
+use tracing::{info, warn};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use crate::config::{BurnRedeemConfig, BurnRedeemMode};
+use crate::entitys::entity::{NftRedemption, NftTransferEvent};
+use crate::services::mint_state::MintState;
+
+/// Check the configured rules for redeeming a burned NFT; `Err` holds the rejection reason
+pub fn check_redeem_rules(
+    config: &BurnRedeemConfig,
+    edition: i32,
+    minted_at: Option<DateTime<Utc>>,
+    burned_at: DateTime<Utc>,
+) -> Result<(), String> {
+    if !config.enabled {
+        return Err("Burn redemption is disabled".to_string());
+    }
+
+    if config.min_mint_age_secs > 0 {
+        let age = minted_at.map(|t| (burned_at - t).num_seconds()).unwrap_or(0);
+        if age < config.min_mint_age_secs {
+            return Err(format!("Burned {}s after mint, minimum is {}s", age, config.min_mint_age_secs));
+        }
+    }
+
+    if let Some(max_editions) = config.max_editions
+        && edition >= max_editions
+    {
+        return Err(format!("Edition {} reached the limit of {} editions", edition, max_editions));
+    }
+
+    Ok(())
+}
+
+struct RedemptionRecord<'a> {
+    token_id: i64,
+    nft_id: Option<i32>,
+    status: &'a str,
+    reason: Option<String>,
+    new_nft_id: Option<i32>,
+    chips_released: i32,
+}
+
+async fn record_redemption(
+    tx: &mut Transaction<'_, Postgres>,
+    burn: &NftTransferEvent,
+    mode: BurnRedeemMode,
+    record: RedemptionRecord<'_>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO nft_redemptions
+            (token_id, nft_id, burner, mode, status, reason, new_nft_id, chips_released, tx_hash, log_index, block_number)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        ON CONFLICT (tx_hash, log_index) DO NOTHING
+        "#,
+        record.token_id,
+        record.nft_id,
+        burn.from.to_lowercase(),
+        mode.as_str(),
+        record.status,
+        record.reason,
+        record.new_nft_id,
+        record.chips_released,
+        burn.tx_hash,
+        burn.log_index as i64,
+        burn.block_number as i64
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Release the chips of a burned NFT according to the burn-to-redeem rules
+///
+/// Called inside the transaction that indexes the burn (Transfer to the zero address), so a
+/// burn is redeemed at most once whether it comes from the live listener or the catch-up scan.
+/// - `reset`: the NFT and its chips go back to the unassigned pool as the next edition
+/// - `new_edition`: the burned NFT is kept as history and a copy with fresh chips is imported
+///
+/// Rejected burns (rules not met, unknown token) are recorded with the reason and change nothing.
+/// `burned_at` is the timestamp of the burn's block, the mint age is measured up to it.
+pub async fn redeem_burned_nft(
+    tx: &mut Transaction<'_, Postgres>,
+    burn: &NftTransferEvent,
+    token_id: i64,
+    config: &BurnRedeemConfig,
+    burned_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    let rejected = |nft_id: Option<i32>, reason: String| RedemptionRecord {
+        token_id,
+        nft_id,
+        status: "rejected",
+        reason: Some(reason),
+        new_nft_id: None,
+        chips_released: 0,
+    };
+
+    let nft = sqlx::query!(
+        r#"
+        SELECT id, edition, minted_at, burned_at, file_name
+        FROM nfts
+        WHERE token_id = $1 AND is_mint = $2
+        FOR UPDATE
+        "#,
+        token_id,
+        MintState::Minted.as_i32()
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    let Some(nft) = nft else {
+        warn!("🔥 Burn of token {} has no minted NFT, nothing to redeem", token_id);
+        let record = rejected(None, format!("No minted NFT with token id {}", token_id));
+        return record_redemption(tx, burn, config.mode, record).await;
+    };
+    if nft.burned_at.is_some() {
+        let record = rejected(Some(nft.id), "NFT was already redeemed".to_string());
+        return record_redemption(tx, burn, config.mode, record).await;
+    }
+    if let Err(reason) = check_redeem_rules(config, nft.edition, nft.minted_at, burned_at) {
+        info!("🔥 Burn of token {} (NFT {}) not redeemed: {}", token_id, nft.id, reason);
+        let record = rejected(Some(nft.id), reason);
+        return record_redemption(tx, burn, config.mode, record).await;
+    }
+
+    let (new_nft_id, chips_released) = match config.mode {
+        BurnRedeemMode::Reset => {
+            let chips = sqlx::query!(
+                r#"
+                UPDATE chips
+                SET user_address = NULL, received = false, is_mint = 0, mint_user = NULL
+                WHERE nft_id = $1
+                "#,
+                nft.id
+            )
+            .execute(&mut **tx)
+            .await?
+            .rows_affected();
+
+            // edition + 1 是触发器允许 2 -> 0 的前提
+            sqlx::query!(
+                r#"
+                UPDATE nfts
+                SET user_address = NULL,
+                    received = false,
+                    is_mint = $2,
+                    edition = edition + 1,
+                    token_id = NULL,
+                    token_url = NULL,
+                    block_number = NULL,
+                    minted_at = NULL,
+                    mint_started_at = NULL,
+                    owner_address = NULL
+                WHERE id = $1
+                "#,
+                nft.id,
+                MintState::Unminted.as_i32()
+            )
+            .execute(&mut **tx)
+            .await?;
+
+            // UPDATE 不会触发导入通知，手动唤醒 pending allocation worker
+            sqlx::query!("SELECT pg_notify('nfts_imported', 'nfts')")
+                .execute(&mut **tx)
+                .await?;
+
+            (None, chips as i32)
+        }
+        BurnRedeemMode::NewEdition => {
+            sqlx::query!("UPDATE nfts SET burned_at = $2 WHERE id = $1", nft.id, burned_at)
+                .execute(&mut **tx)
+                .await?;
+
+            let new_nft_id = sqlx::query_scalar!(
+                r#"
+                INSERT INTO nfts (file_name, edition, previous_nft_id)
+                VALUES ($1, $2, $3)
+                RETURNING id
+                "#,
+                nft.file_name,
+                nft.edition + 1,
+                nft.id
+            )
+            .fetch_one(&mut **tx)
+            .await?;
+
+            let chips = sqlx::query!(
+                r#"
+                INSERT INTO chips (nft_id, x, y, w, h, file_name)
+                SELECT $1, x, y, w, h, file_name
+                FROM chips
+                WHERE nft_id = $2
+                ORDER BY id
+                "#,
+                new_nft_id,
+                nft.id
+            )
+            .execute(&mut **tx)
+            .await?
+            .rows_affected();
+
+            (Some(new_nft_id), chips as i32)
+        }
+    };
+
+    info!("🔥 Redeemed burned token {} (NFT {}, {}): {} chips back in circulation{}",
+        token_id, nft.id, config.mode.as_str(), chips_released,
+        new_nft_id.map(|id| format!(" as NFT {}", id)).unwrap_or_default());
+
+    record_redemption(tx, burn, config.mode, RedemptionRecord {
+        token_id,
+        nft_id: Some(nft.id),
+        status: "redeemed",
+        reason: None,
+        new_nft_id,
+        chips_released,
+    }).await
+}
+
+/// Token ids `nft_id` was minted as before reset redemptions returned it to the pool
+pub async fn redeemed_token_ids(pool: &PgPool, nft_id: i32) -> Result<Vec<i64>, sqlx::Error> {
+    sqlx::query_scalar!(
+        "SELECT token_id FROM nft_redemptions WHERE nft_id = $1 AND status = 'redeemed' AND mode = $2",
+        nft_id,
+        BurnRedeemMode::Reset.as_str()
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// List burn redemptions, newest first
+pub async fn list_redemptions(pool: &PgPool, status: Option<&str>) -> Result<Vec<NftRedemption>, sqlx::Error> {
+    sqlx::query_as!(
+        NftRedemption,
+        r#"
+        SELECT id, token_id, nft_id, burner, mode, status, reason, new_nft_id, chips_released,
+               tx_hash, log_index, block_number, created_at
+        FROM nft_redemptions
+        WHERE $1::VARCHAR IS NULL OR status = $1
+        ORDER BY id DESC
+        LIMIT 500
+        "#,
+        status
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn config() -> BurnRedeemConfig {
+        BurnRedeemConfig {
+            enabled: true,
+            mode: BurnRedeemMode::Reset,
+            min_mint_age_secs: 3600,
+            max_editions: Some(3),
+        }
+    }
+
+    #[test]
+    fn applies_redeem_rules() {
+        let now = Utc::now();
+        let minted = Some(now - Duration::hours(2));
+
+        assert!(check_redeem_rules(&config(), 1, minted, now).is_ok());
+        assert!(check_redeem_rules(&config(), 2, minted, now).is_ok());
+        assert!(check_redeem_rules(&config(), 3, minted, now).is_err());
+        assert!(check_redeem_rules(&config(), 1, Some(now - Duration::minutes(5)), now).is_err());
+        assert!(check_redeem_rules(&BurnRedeemConfig { enabled: false, ..config() }, 1, minted, now).is_err());
+    }
+}
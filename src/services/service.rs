@@ -1,13 +1,18 @@
 use tracing::{info, warn, error};
-use bigdecimal::BigDecimal;
-use chrono::Utc;
-use sqlx::PgPool;
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{TimeZone, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
 use std::str::FromStr;
-use crate::entitys::entity::KlineUpdateEvent;
+use crate::entitys::entity::{KlineUpdateEvent, TransferEvent};
+use crate::services::{anti_reroll, mint_state, time_weighted};
+use crate::services::mint_state::MintState;
+use crate::services::mint_remark::MintRemark;
+use crate::services::mint_anomaly::{MintAnomalyKind, NewMintAnomaly, check_minter, record_mint_anomaly};
 use alloy::providers::ProviderBuilder;
 use alloy::primitives::Address;
 use alloy::sol;
-use std::collections::HashSet;
+use crate::services::blacklist::is_blacklisted;
+use crate::config::{ChipAllocationMode, ChipRatioConfig, get_chip_ratio_config};
 
 // ERC20 标准 balanceOf 函数
 sol! {
@@ -24,37 +29,6 @@ sol! {
     ]"#
 }
 
-/// Check if address is in blacklist (contract addresses that should not receive/revert chips)
-fn is_blacklisted_address(address: &str) -> bool {
-    dotenv::dotenv().ok();
-    
-    // 黑名单地址列表（合约地址）
-    let blacklist_keys = vec![
-        "NFT_CONTRACT",
-        "QUOTER_ADDRESS", 
-        "SWAP_EXECUTOR",
-        "TOKEN_B",
-        "TOKEN_A",
-        "POOL_MANAGER",
-        "HOOK_CONTRACT",
-        "CURRENCY0_ADDRESS",
-        "CURRENCY1_ADDRESS",
-        "PUBLIC_KEY",
-    ];
-    
-    // 收集所有黑名单地址（转为小写）
-    let mut blacklist: HashSet<String> = HashSet::new();
-    for key in blacklist_keys {
-        if let Ok(addr) = std::env::var(key) {
-            blacklist.insert(addr.to_lowercase());
-        }
-    }
-    
-    // 检查地址是否在黑名单中
-    let addr_lower = address.to_lowercase();
-    blacklist.contains(&addr_lower)
-}
-
 /// Query user's token balance from HakuToken contract
 async fn query_token_balance(user_address: &str) -> Result<BigDecimal, Box<dyn std::error::Error>> {
     // Load config
@@ -89,6 +63,14 @@ async fn query_token_balance(user_address: &str) -> Result<BigDecimal, Box<dyn s
     Ok(balance)
 }
 
+/// Load the token → chip conversion config, mapped to sqlx::Error for the chip functions
+fn load_chip_ratio_config() -> Result<ChipRatioConfig, sqlx::Error> {
+    get_chip_ratio_config().map_err(|e| {
+        error!("❌ Invalid chip ratio config: {}", e);
+        sqlx::Error::Configuration(e.into())
+    })
+}
+
 pub async fn root() -> &'static str {
     info!( "method: {}", "root"  );
     "Hello, World!"
@@ -130,13 +112,183 @@ pub async fn insert_swap_request(
     Ok(rec.id)
 }
 
+/// Chips the user should hold under the configured allocation mode
+async fn chip_target(pool: &PgPool, user_address: &str, ratio_config: &ChipRatioConfig) -> Result<i64, sqlx::Error> {
+    match ratio_config.mode {
+        ChipAllocationMode::Balance => balance_chip_target(user_address, ratio_config).await,
+        ChipAllocationMode::SwapVolume => swap_volume_chip_target(pool, user_address, ratio_config).await,
+        ChipAllocationMode::TimeWeighted => time_weighted_chip_target(pool, user_address, ratio_config).await,
+    }
+}
+
+/// Chips the user should hold based on on-chain token balance (balance mode)
+async fn balance_chip_target(user_address: &str, ratio_config: &ChipRatioConfig) -> Result<i64, sqlx::Error> {
+    info!("Step 1: Querying HakuToken balance from blockchain...");
+
+    let user_balance = match query_token_balance(user_address).await {
+        Ok(balance) => balance,
+        Err(e) => {
+            error!("❌ Failed to query token balance for {}: {:?}", user_address, e);
+            return Err(sqlx::Error::Decode(Box::new(std::io::Error::other(
+                format!("Failed to query token balance: {}", e)
+            ))));
+        }
+    };
+
+    info!("User {} token balance (raw): {}", user_address, user_balance);
+
+    // 按兑换比例换算并向下取整，得到应该拥有的 chips 数量
+    let total_wallet_count = ratio_config.chips_for_balance(&user_balance);
+
+    info!("✅ User should have {} chips based on token balance (floor, {} chips/token)",
+        total_wallet_count, ratio_config.chips_per_token());
+    Ok(total_wallet_count)
+}
+
+/// Chips the user has earned from cumulative swap volume (swap_volume mode)
+async fn swap_volume_chip_target(pool: &PgPool, user_address: &str, ratio_config: &ChipRatioConfig) -> Result<i64, sqlx::Error> {
+    info!("Step 1: Summing swap volume from swap_requests...");
+
+    let volume = sqlx::query!(
+        r#"
+        SELECT
+            COALESCE(SUM(amount_in_raw) FILTER (WHERE zero_for_one = true), 0) as "buy_volume!",
+            COALESCE(SUM(amount_in_raw) FILTER (WHERE zero_for_one = false), 0) as "sell_volume!"
+        FROM swap_requests
+        WHERE LOWER(user_address) = $1
+        "#,
+        user_address.to_lowercase()
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let total_wallet_count = ratio_config.swap_volume.chips_for_volume(
+        &volume.buy_volume,
+        &volume.sell_volume,
+        ratio_config.token_decimals,
+    );
+
+    info!("✅ User {} earned {} chips from swap volume (buy raw: {}, sell raw: {})",
+        user_address, total_wallet_count, volume.buy_volume, volume.sell_volume);
+    Ok(total_wallet_count)
+}
+
+/// Chips the user should hold based on time-weighted average balance (time_weighted mode)
+async fn time_weighted_chip_target(pool: &PgPool, user_address: &str, ratio_config: &ChipRatioConfig) -> Result<i64, sqlx::Error> {
+    info!("Step 1: Computing time-weighted average balance...");
+
+    let user_balance = match query_token_balance(user_address).await {
+        Ok(balance) => balance,
+        Err(e) => {
+            error!("❌ Failed to query token balance for {}: {:?}", user_address, e);
+            return Err(sqlx::Error::Decode(Box::new(std::io::Error::other(
+                format!("Failed to query token balance: {}", e)
+            ))));
+        }
+    };
+
+    let now = Utc::now();
+    let window_start = now - chrono::Duration::seconds(ratio_config.twab_window_secs);
+    let changes = time_weighted::load_balance_changes(pool, user_address, window_start).await?;
+    let twab = time_weighted::time_weighted_average_balance(&user_balance, &changes, window_start, now);
+
+    let total_wallet_count = ratio_config.chips_for_balance(&twab);
+
+    info!("✅ User {} TWAB over {}s: {} (current: {}, {} transfers) -> {} chips",
+        user_address, ratio_config.twab_window_secs, twab.with_scale(0), user_balance, changes.len(), total_wallet_count);
+    Ok(total_wallet_count)
+}
+
+/// Record a HakuToken transfer into token_transfers (transfer history)
+pub async fn record_token_transfer(pool: &PgPool, transfer: &TransferEvent) -> Result<i64, sqlx::Error> {
+    let value_raw = BigDecimal::from_str(&transfer.value)
+        .map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+    let block_timestamp = Utc.timestamp_opt(transfer.timestamp as i64, 0)
+        .single()
+        .unwrap_or_else(Utc::now);
+
+    let rec = sqlx::query!(
+        r#"
+        INSERT INTO token_transfers (from_address, to_address, value_raw, block_number, block_timestamp, tx_hash, mint_remark)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id
+        "#,
+        transfer.from.to_lowercase(),
+        transfer.to.to_lowercase(),
+        value_raw,
+        transfer.block_number as i64,
+        block_timestamp,
+        transfer.tx_hash,
+        transfer.mint_remark
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(rec.id)
+}
+
+/// Scheduled sync for time_weighted mode
+/// Re-evaluates every address whose TWAB may still be changing (transfers within
+/// window + one sync interval) through receive_chips / revert_chips.
+/// Returns the addresses that were synced.
+pub async fn sync_time_weighted_chips(pool: &PgPool) -> Result<Vec<String>, sqlx::Error> {
+    let ratio_config = load_chip_ratio_config()?;
+    if ratio_config.mode != ChipAllocationMode::TimeWeighted {
+        return Ok(vec![]);
+    }
+
+    let lookback = ratio_config.twab_window_secs + ratio_config.twab_sync_interval_secs as i64;
+    let since = Utc::now() - chrono::Duration::seconds(lookback);
+    let addresses = time_weighted::addresses_with_recent_transfers(pool, since).await?;
+
+    info!("⏱️  Time-weighted sync: {} addresses to evaluate", addresses.len());
+
+    let mut synced = Vec::new();
+    for address in addresses {
+        if let Err(e) = receive_chips(pool, &address, "0").await {
+            error!("❌ Time-weighted receive failed for {}: {:?}", address, e);
+            continue;
+        }
+        if let Err(e) = revert_chips(pool, &address, "0", None).await {
+            error!("❌ Time-weighted revert failed for {}: {:?}", address, e);
+            continue;
+        }
+        synced.push(address);
+    }
+
+    Ok(synced)
+}
+
+/// Chips a receive could not hand out, recorded in `pending_allocations`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ChipDeficit {
+    missed: i64,
+    reason: &'static str,   // pool_exhausted / receive_limit
+}
+
+/// Deficit left after a receive; `received_now` counts restored chips too
+/// Anything short of `total_needed` is a deficit: `pool_exhausted` if no free NFT was left,
+/// otherwise `receive_limit` (MAX_CHIPS_PER_RECEIVE cap or loop limit)
+fn chip_deficit(total_needed: i64, received_now: i64, pool_exhausted: bool) -> Option<ChipDeficit> {
+    let missed = total_needed - received_now;
+    (missed > 0).then_some(ChipDeficit {
+        missed,
+        reason: if pool_exhausted { "pool_exhausted" } else { "receive_limit" },
+    })
+}
+
 /// Receive chips logic (Transfer in)
 /// Query user's token balance from HakuToken contract and receive new chips
 pub async fn receive_chips(pool: &PgPool, user_address: &str, _value: &str) -> Result<(), sqlx::Error> {
+    allocate_chips(pool, user_address).await.map(|_| ())
+}
+
+/// Body of `receive_chips`; returns the deficit it recorded, if any
+async fn allocate_chips(pool: &PgPool, user_address: &str) -> Result<Option<ChipDeficit>, sqlx::Error> {
     // 🚫 黑名单检查：合约地址不参与 chips 分配
-    if is_blacklisted_address(user_address) {
+    if is_blacklisted(pool, user_address).await {
         warn!("🚫 receive_chips: Skipping blacklisted address {}", user_address);
-        return Ok(());
+        return Ok(None);
     }
     
     // Load env
@@ -145,42 +297,12 @@ pub async fn receive_chips(pool: &PgPool, user_address: &str, _value: &str) -> R
     let batch_size_str = std::env::var("MAX_NFT_PER_USER").unwrap_or("3".to_string());
     let batch_size: i64 = batch_size_str.parse().unwrap_or(3);
     
-    let token_decimals: u32 = std::env::var("TOKEN_DECIMALS")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(18);
+    let ratio_config = load_chip_ratio_config()?;
 
-    info!("🟢 Receiving chips for user: {}", user_address);
+    info!("🟢 Receiving chips for user: {} (mode: {:?})", user_address, ratio_config.mode);
 
-    // ==================== Step 1: 查询链上 HakuToken 余额 ====================
-    info!("Step 1: Querying HakuToken balance from blockchain...");
-    
-    let user_balance = match query_token_balance(user_address).await {
-        Ok(balance) => balance,
-        Err(e) => {
-            error!("❌ Failed to query token balance for {}: {:?}", user_address, e);
-            return Err(sqlx::Error::Decode(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("Failed to query token balance: {}", e)
-            ))));
-        }
-    };
-    
-    info!("User {} token balance (raw): {}", user_address, user_balance);
-    
-    // 转换为可读格式并向下取整，得到应该拥有的 chips 数量
-    let divisor = BigDecimal::from(10u64.pow(token_decimals));
-    let balance_divided = &user_balance / &divisor;
-    
-    // 向下取整 (floor)
-    let total_wallet_count = balance_divided.to_string()
-        .split('.')
-        .next()
-        .unwrap_or("0")
-        .parse::<i64>()
-        .unwrap_or(0);
-    
-    info!("✅ User should have {} chips based on token balance (floor)", total_wallet_count);
+    // ==================== Step 1: 计算应拥有的 chips 数量 ====================
+    let total_wallet_count = chip_target(pool, user_address, &ratio_config).await?;
 
     // ==================== Step 2: 查询数据库中已领取的 chips ====================
     info!("Step 2: Querying received chips from database...");
@@ -234,13 +356,21 @@ pub async fn receive_chips(pool: &PgPool, user_address: &str, _value: &str) -> R
 
     if n_needed_receive <= 0 {
         info!("No new chips to receive for user {}", user_address);
-        return Ok(());
+        // 余额已满足（或已下降），关闭可能存在的待分配记录
+        let mut tx = pool.begin().await?;
+        clear_pending_allocation(&mut tx, user_address).await?;
+        tx.commit().await?;
+        return Ok(None);
     }
 
     info!("User {} will receive {} chips this time (Batch Size: {})", user_address, n_needed_receive, batch_size);
+    let n_to_receive = n_needed_receive;
 
     let mut tx = pool.begin().await?;
 
+    // 冷却期内先归还用户最近释放的 chips，避免通过转出再转入重新随机分配（防刷）
+    n_needed_receive -= anti_reroll::restore_released_chips(&mut tx, user_address, n_needed_receive).await?;
+
     // Strategy: Loop until satisfied
     // 1. Try to fulfill N chips from ALL currently owned NFTs (randomly distributed).
     // 2. If N > 0, acquire `batch_size` NEW NFTs.
@@ -249,6 +379,7 @@ pub async fn receive_chips(pool: &PgPool, user_address: &str, _value: &str) -> R
     // 🔑 添加最大循环次数限制，防止死循环
     const MAX_LOOP_ITERATIONS: i32 = 100; // 最多循环 100 次
     let mut loop_count = 0;
+    let mut pool_exhausted = false;
 
     loop {
         if n_needed_receive <= 0 {
@@ -326,6 +457,7 @@ pub async fn receive_chips(pool: &PgPool, user_address: &str, _value: &str) -> R
 
         if nfts_acquired == 0 {
             warn!("System ran out of available NFTs! User {} still needs {} chips.", user_address, n_needed_receive);
+            pool_exhausted = true;
             break;
         }
 
@@ -347,51 +479,120 @@ pub async fn receive_chips(pool: &PgPool, user_address: &str, _value: &str) -> R
         // Continue loop to fill chips from these newly acquired NFTs
     }
 
-    if n_needed_receive > 0 {
-        warn!("Transaction finished with partial fill. User {} missed {} chips.", user_address, n_needed_receive);
+    // 未满足的部分（含单次上限截断）记录到 pending_allocations，等待新 NFT 导入后补发
+    let deficit = chip_deficit(total_needed, n_to_receive - n_needed_receive, pool_exhausted);
+    if let Some(deficit) = deficit {
+        warn!("Transaction finished with partial fill. User {} missed {} chips.", user_address, deficit.missed);
+        record_pending_allocation(&mut tx, user_address, deficit.missed, deficit.reason).await?;
     } else {
         info!("User {} successfully received all chips.", user_address);
+        clear_pending_allocation(&mut tx, user_address).await?;
     }
 
     tx.commit().await?;
+    Ok(deficit)
+}
+
+/// Record (or refresh) the user's open chip deficit
+async fn record_pending_allocation(
+    tx: &mut Transaction<'_, Postgres>,
+    user_address: &str,
+    chips_deficit: i64,
+    reason: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO pending_allocations (user_address, chips_deficit, reason)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_address) WHERE fulfilled_at IS NULL
+        DO UPDATE SET chips_deficit = EXCLUDED.chips_deficit, reason = EXCLUDED.reason
+        "#,
+        user_address.to_lowercase(),
+        chips_deficit,
+        reason
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    info!("📝 Recorded pending allocation: user={}, deficit={}, reason={}", user_address, chips_deficit, reason);
     Ok(())
 }
 
-/// Parse nft_id from mint_remark string
-/// Supports multiple formats:
-/// - Pure number: "12"
-/// - Formatted: "MintNFT#12:tokenURL" or "12:tokenURL"
-/// - With prefix: "MintNFT#12"
-fn parse_nft_id_from_remark(remark: &str) -> Result<i32, Box<dyn std::error::Error>> {
-    // Try direct parse first (pure number)
-    if let Ok(nft_id) = remark.parse::<i32>() {
-        return Ok(nft_id);
+/// Mark the user's open chip deficit (if any) as fulfilled
+async fn clear_pending_allocation(
+    tx: &mut Transaction<'_, Postgres>,
+    user_address: &str,
+) -> Result<(), sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE pending_allocations
+        SET fulfilled_at = NOW()
+        WHERE user_address = $1 AND fulfilled_at IS NULL
+        "#,
+        user_address.to_lowercase()
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        info!("✅ Pending allocation fulfilled for user {}", user_address);
     }
-    
-    // Try format: "MintNFT#12:tokenURL" or "MintNFT#12"
-    if let Some(hash_pos) = remark.find('#') {
-        let after_hash = &remark[hash_pos + 1..];
-        // Extract number before ':' if exists
-        if let Some(colon_pos) = after_hash.find(':') {
-            let nft_id_str = &after_hash[..colon_pos];
-            return nft_id_str.parse::<i32>()
-                .map_err(|e| format!("Failed to parse nft_id from '{}' (format: MintNFT#nft_id:tokenURL): {}", remark, e).into());
-        } else {
-            // Format: "MintNFT#12" (no colon)
-            return after_hash.parse::<i32>()
-                .map_err(|e| format!("Failed to parse nft_id from '{}' (format: MintNFT#nft_id): {}", remark, e).into());
-        }
+    Ok(())
+}
+
+/// Retry every open pending allocation (oldest first) through `receive_chips`
+/// Called when new NFTs/chips are imported and periodically (for `receive_limit` deficits);
+/// returns the users that were processed
+pub async fn fulfill_pending_allocations(pool: &PgPool) -> Result<Vec<String>, sqlx::Error> {
+    let pending = sqlx::query!(
+        r#"
+        SELECT user_address, chips_deficit
+        FROM pending_allocations
+        WHERE fulfilled_at IS NULL
+        ORDER BY created_at ASC
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if pending.is_empty() {
+        return Ok(vec![]);
     }
-    
-    // Try format: "12:tokenURL" (no prefix)
-    if let Some(colon_pos) = remark.find(':') {
-        let nft_id_str = &remark[..colon_pos];
-        return nft_id_str.parse::<i32>()
-            .map_err(|e| format!("Failed to parse nft_id from '{}' (format: nft_id:tokenURL): {}", remark, e).into());
+
+    // 没有空闲 NFT 时不重试，等待下一次导入
+    let free_nfts = sqlx::query!("SELECT COUNT(*) as count FROM nfts WHERE received = false")
+        .fetch_one(pool)
+        .await?
+        .count
+        .unwrap_or(0);
+    if free_nfts == 0 {
+        warn!("NFT pool still exhausted, {} pending allocations remain", pending.len());
+        return Ok(vec![]);
     }
-    
-    // If all parsing attempts fail, return error
-    Err(format!("Unable to parse nft_id from remark: '{}'. Expected formats: '12', 'MintNFT#12:tokenURL', '12:tokenURL', or 'MintNFT#12'", remark).into())
+
+    info!("📦 Fulfilling {} pending allocations", pending.len());
+
+    let total_pending = pending.len();
+    let mut processed = Vec::new();
+    for record in pending {
+        info!("📦 Retrying allocation for user {} (deficit: {})", record.user_address, record.chips_deficit);
+        let deficit = match allocate_chips(pool, &record.user_address).await {
+            Ok(deficit) => deficit,
+            Err(e) => {
+                error!("❌ Failed to fulfill pending allocation for {}: {:?}", record.user_address, e);
+                continue;
+            }
+        };
+        processed.push(record.user_address);
+
+        // 池子又被分完，剩余的等待下一次导入
+        if deficit.is_some_and(|d| d.reason == "pool_exhausted") {
+            warn!("NFT pool exhausted again, {} pending allocations remain", total_pending + 1 - processed.len());
+            break;
+        }
+    }
+
+    Ok(processed)
 }
 
 /// Recycle chips for userMint transaction
@@ -404,18 +605,14 @@ async fn recycle_chips_for_mint(
 ) -> Result<(), sqlx::Error> {
     info!("🔄 Recycling chips for userMint: user={}, mint_remark={}", user_address, nft_id_str);
     
-    // Parse nft_id from remark (supports multiple formats)
-    let nft_id = match parse_nft_id_from_remark(nft_id_str) {
-        Ok(id) => {
-            info!("✅ Parsed nft_id: {} from remark: '{}'", id, nft_id_str);
-            id
+    let nft_id = match MintRemark::parse(nft_id_str) {
+        Ok(remark) => {
+            info!("✅ Parsed nft_id: {} from remark: '{}'", remark.nft_id, nft_id_str);
+            remark.nft_id
         }
         Err(e) => {
-            error!("❌ Failed to parse nft_id from mint_remark '{}': {:?}", nft_id_str, e);
-            return Err(sqlx::Error::Decode(Box::new(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("Invalid nft_id in mint_remark: {}", e)
-            ))));
+            error!("❌ {}", e);
+            return Err(sqlx::Error::Decode(Box::new(e)));
         }
     };
     
@@ -474,7 +671,7 @@ pub async fn revert_chips(
     mint_remark: Option<&str>,  // ✅ 新增：如果提供，说明是 userMint 交易
 ) -> Result<(), sqlx::Error> {
     // 🚫 黑名单检查：合约地址不参与 chips 分配
-    if is_blacklisted_address(user_address) {
+    if is_blacklisted(pool, user_address).await {
         warn!("🚫 revert_chips: Skipping blacklisted address {}", user_address);
         return Ok(());
     }
@@ -489,43 +686,18 @@ pub async fn revert_chips(
     } else {
         // ========== Transfer revert logic: 根据链上余额退回 chips ==========
         // Load env
-        dotenv::dotenv().ok();
-        let token_decimals: u32 = std::env::var("TOKEN_DECIMALS")
-            .ok()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(18);
+        let ratio_config = load_chip_ratio_config()?;
 
-        info!("🔴 Reverting chips for user: {}", user_address);
+        // 交易量模式下 chips 按累计交易量发放，转账不回收
+        if ratio_config.mode == ChipAllocationMode::SwapVolume {
+            info!("ℹ️  swap_volume mode: chips are not revoked on transfer (user: {})", user_address);
+            return Ok(());
+        }
 
-        // ==================== Step 1: 查询链上 HakuToken 余额 ====================
-        info!("Step 1: Querying HakuToken balance from blockchain...");
-        
-        let user_balance = match query_token_balance(user_address).await {
-            Ok(balance) => balance,
-            Err(e) => {
-                error!("❌ Failed to query token balance for {}: {:?}", user_address, e);
-                return Err(sqlx::Error::Decode(Box::new(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("Failed to query token balance: {}", e)
-                ))));
-            }
-        };
-        
-        info!("User {} token balance (raw): {}", user_address, user_balance);
-        
-        // 转换为可读格式并向下取整，得到应该拥有的 chips 数量
-        let divisor = BigDecimal::from(10u64.pow(token_decimals));
-        let balance_divided = &user_balance / &divisor;
-        
-        // 向下取整 (floor)
-        let total_wallet_count = balance_divided.to_string()
-            .split('.')
-            .next()
-            .unwrap_or("0")
-            .parse::<i64>()
-            .unwrap_or(0);
-        
-        info!("✅ User should have {} chips based on token balance (floor)", total_wallet_count);
+        info!("🔴 Reverting chips for user: {} (mode: {:?})", user_address, ratio_config.mode);
+
+        // ==================== Step 1: 计算应拥有的 chips 数量 ====================
+        let total_wallet_count = chip_target(pool, user_address, &ratio_config).await?;
 
         // ==================== Step 2: 查询数据库中已领取的 chips ====================
         info!("Step 2: Querying received chips from database...");
@@ -547,7 +719,7 @@ pub async fn revert_chips(
         // ==================== Step 3: 计算需要退回的 chips ====================
         info!("Step 3: Calculating chips to revert...");
         
-        let mut n_needed_revert = n_received - total_wallet_count;
+        let n_needed_revert = n_received - total_wallet_count;
 
         info!("📊 Calculation:");
         info!("  Token balance chips (floor): {}", total_wallet_count);
@@ -561,92 +733,195 @@ pub async fn revert_chips(
         
         info!("User {} needs to revert {} chips", user_address, n_needed_revert);
         let mut tx = pool.begin().await?;
-        // Get all NFTs owned by user
-        // Do not revert nfts whitch is minted by HakuNFTMint event
-        let user_nfts = sqlx::query!(
-            "SELECT id FROM nfts WHERE user_address = $1 AND received = true AND is_mint > 0 ORDER BY RANDOM()",
-            user_address
-        )
-        .fetch_all(&mut *tx)
-        .await?;
-        for nft in user_nfts {
-            if n_needed_revert <= 0 {
-                break;
-            }
-            let nft_id = nft.id;
-            // Count chips owned by user for this NFT (M)
-            let chips_rec = sqlx::query!(
+        let selection = release_excess_chips(&mut tx, user_address, n_needed_revert, false).await?;
+        if selection.shortfall > 0 {
+            warn!("User {} did not have enough chips to revert. Remaining needed: {}", user_address, selection.shortfall);
+        }
+        // 记录释放的 chips，冷却期内重新获得时优先归还原 chips（防刷）
+        anti_reroll::record_released_chips(&mut tx, user_address, &selection.chip_ids).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// Chips and NFTs released by the transfer revert logic
+#[derive(Debug, Clone, Default)]
+pub struct RevertSelection {
+    pub chip_ids: Vec<i32>,
+    pub nft_ids: Vec<i32>,  // NFTs whose every owned chip was released (NFT itself reverted)
+    pub shortfall: i64,     // chips that could not be released
+}
+
+/// Chips of one NFT to release while `needed` chips are still owed, and whether the NFT goes too
+/// (it does once every chip the user holds of it is released)
+fn chips_to_release(owned: &[i32], needed: i64) -> (&[i32], bool) {
+    let take = (needed.max(0) as usize).min(owned.len());
+    (&owned[..take], take == owned.len())
+}
+
+/// Release `n_needed_revert` chips of the user inside the given transaction
+/// With `dry_run` only the selection is computed, so `preview_revert_chips` can run it in a
+/// read-only transaction (row locks are not allowed there, so chips locked by an in-flight
+/// revert are still counted)
+async fn release_excess_chips(
+    tx: &mut Transaction<'_, Postgres>,
+    user_address: &str,
+    mut n_needed_revert: i64,
+    dry_run: bool,
+) -> Result<RevertSelection, sqlx::Error> {
+    let mut selection = RevertSelection::default();
+    // Get all NFTs owned by user
+    // Do not revert nfts whitch is minted by HakuNFTMint event
+    let user_nfts = sqlx::query!(
+        "SELECT id FROM nfts WHERE user_address = $1 AND received = true AND is_mint > 0 ORDER BY RANDOM()",
+        user_address
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+    for nft in user_nfts {
+        if n_needed_revert <= 0 {
+            break;
+        }
+        let nft_id = nft.id;
+        // Count chips owned by user for this NFT (M)
+        let owned: Vec<i32> = if dry_run {
+            sqlx::query_scalar!(
+                "SELECT id FROM chips WHERE nft_id = $1 AND user_address = $2 AND received = true",
+                nft_id,
+                user_address
+            )
+            .fetch_all(&mut **tx)
+            .await?
+        } else {
+            sqlx::query_scalar!(
                 "SELECT id FROM chips WHERE nft_id = $1 AND user_address = $2 AND received = true FOR UPDATE SKIP LOCKED",
                 nft_id,
                 user_address
             )
-            .fetch_all(&mut *tx)
-            .await?;
-            let m_owned = chips_rec.len() as i64;
-            if m_owned == 0 {
-                continue;
-            }
-            if m_owned >= n_needed_revert {
-                // Case 2: M >= N
-                // Cancel N chips (批量更新优化)
-                let chips_to_cancel = &chips_rec[0..n_needed_revert as usize];
-                let chip_ids: Vec<i32> = chips_to_cancel.iter().map(|c| c.id).collect();
-                
-                if !chip_ids.is_empty() {
-                    sqlx::query!(
-                        "UPDATE chips SET user_address = NULL, received = false WHERE id = ANY($1)",
-                        &chip_ids
-                    )
-                    .execute(&mut *tx)
-                    .await?;
-                    
-                    info!("🚀 Batch updated {} chips for NFT {}", chip_ids.len(), nft_id);
-                }
-                // If M == N, cancel NFT
-                if m_owned == n_needed_revert {
-                    sqlx::query!(
-                        "UPDATE nfts SET user_address = NULL, received = false WHERE id = $1",
-                        nft_id
-                    )
-                    .execute(&mut *tx)
-                    .await?;
-                    info!("User {} reverted NFT {} (All chips reverted)", user_address, nft_id);
-                }
-                info!("User {} reverted {} chips from NFT {}", user_address, n_needed_revert, nft_id);
-                n_needed_revert = 0;
-            } else {
-                // Case 3: M < N
-                // Cancel all M chips (批量更新优化)
-                let chip_ids: Vec<i32> = chips_rec.iter().map(|c| c.id).collect();
-                
-                if !chip_ids.is_empty() {
-                    sqlx::query!(
-                        "UPDATE chips SET user_address = NULL, received = false WHERE id = ANY($1)",
-                        &chip_ids
-                    )
-                    .execute(&mut *tx)
-                    .await?;
-                    
-                    info!("🚀 Batch updated {} chips for NFT {}", chip_ids.len(), nft_id);
-                }
-                // Cancel NFT (since all chips are gone)
-                sqlx::query!(
-                    "UPDATE nfts SET user_address = NULL, received = false WHERE id = $1",
-                    nft_id
-                )
-                .execute(&mut *tx)
-                .await?;
-
-                info!("User {} reverted all {} chips from NFT {} (and the NFT itself)", user_address, m_owned, nft_id);
-                n_needed_revert -= m_owned;
-            }
+            .fetch_all(&mut **tx)
+            .await?
+        };
+        if owned.is_empty() {
+            continue;
         }
-        if n_needed_revert > 0 {
-            warn!("User {} did not have enough chips to revert. Remaining needed: {}", user_address, n_needed_revert);
+        // M >= N: cancel N chips (and the NFT if M == N); M < N: cancel all M chips and the NFT
+        let (chip_ids, nft_released) = chips_to_release(&owned, n_needed_revert);
+        n_needed_revert -= chip_ids.len() as i64;
+        selection.chip_ids.extend_from_slice(chip_ids);
+        if nft_released {
+            selection.nft_ids.push(nft_id);
         }
-        tx.commit().await?;
-        Ok(())
+        if dry_run {
+            continue;
+        }
+
+        sqlx::query!(
+            "UPDATE chips SET user_address = NULL, received = false WHERE id = ANY($1)",
+            chip_ids
+        )
+        .execute(&mut **tx)
+        .await?;
+        info!("🚀 Batch updated {} chips for NFT {}", chip_ids.len(), nft_id);
+
+        if nft_released {
+            sqlx::query!(
+                "UPDATE nfts SET user_address = NULL, received = false WHERE id = $1",
+                nft_id
+            )
+            .execute(&mut **tx)
+            .await?;
+            info!("User {} reverted NFT {} (All chips reverted)", user_address, nft_id);
+        }
+        info!("User {} reverted {} chips from NFT {}", user_address, chip_ids.len(), nft_id);
     }
+    selection.shortfall = n_needed_revert.max(0);
+    Ok(selection)
+}
+
+/// Result of a dry-run of the transfer revert logic
+#[derive(Debug, Clone)]
+pub struct RevertPreview {
+    pub token_balance: BigDecimal,      // current on-chain balance (raw)
+    pub balance_after: Option<BigDecimal>, // balance after selling `amount` (raw); None when the mode ignores it
+    pub chips_before: i64,              // chips currently received
+    pub chips_after: i64,               // chips the remaining balance entitles to
+    pub selection: RevertSelection,
+}
+
+/// Preview which chips/NFTs `revert_chips` would release if the user sent out `amount_raw` tokens
+/// Runs the same selection in a read-only REPEATABLE READ transaction that is always rolled back.
+/// Only the balance mode depends on `amount_raw`: swap-volume transfers release nothing and the
+/// time-weighted target comes from the current TWAB, so `balance_after` is None in those modes.
+pub async fn preview_revert_chips(
+    pool: &PgPool,
+    user_address: &str,
+    amount_raw: &BigDecimal,
+) -> Result<RevertPreview, sqlx::Error> {
+    let ratio_config = load_chip_ratio_config()?;
+
+    info!("🔍 Previewing chip revert for user: {}, amount (raw): {}", user_address, amount_raw);
+
+    let token_balance = match query_token_balance(user_address).await {
+        Ok(balance) => balance,
+        Err(e) => {
+            error!("❌ Failed to query token balance for {}: {:?}", user_address, e);
+            return Err(sqlx::Error::Decode(Box::new(std::io::Error::other(
+                format!("Failed to query token balance: {}", e)
+            ))));
+        }
+    };
+
+    let zero = BigDecimal::from(0);
+    let balance_after = if &token_balance > amount_raw {
+        &token_balance - amount_raw
+    } else {
+        zero
+    };
+
+    // ⚠️ 仅预览：只读事务，始终回滚
+    let mut tx = pool.begin().await?;
+    sqlx::query!("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+
+    let received_chips = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as count
+        FROM chips
+        WHERE LOWER(user_address) = $1 AND received = true
+        "#,
+        user_address.to_lowercase()
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    let chips_before = received_chips.count.unwrap_or(0);
+
+    // 交易量模式下转账不回收 chips；时间加权模式下卖出不会立即影响 TWAB
+    let (chips_after, balance_after) = match ratio_config.mode {
+        ChipAllocationMode::Balance => (ratio_config.chips_for_balance(&balance_after), Some(balance_after)),
+        ChipAllocationMode::SwapVolume => (chips_before, None),
+        ChipAllocationMode::TimeWeighted => (time_weighted_chip_target(pool, user_address, &ratio_config).await?, None),
+    };
+
+    let n_needed_revert = chips_before - chips_after;
+    let selection = if n_needed_revert > 0 && !is_blacklisted(pool, user_address).await {
+        release_excess_chips(&mut tx, user_address, n_needed_revert, true).await?
+    } else {
+        RevertSelection::default()
+    };
+
+    tx.rollback().await?;
+
+    info!("🔍 Revert preview for {}: {} chips / {} NFTs would be released", 
+        user_address, selection.chip_ids.len(), selection.nft_ids.len());
+
+    Ok(RevertPreview {
+        token_balance,
+        balance_after,
+        chips_before,
+        chips_after,
+        selection,
+    })
 }
 
 /// Update K-line data
@@ -680,13 +955,13 @@ pub async fn update_kline(
     // zero_for_one = false => TokenB -> STT (Input: TokenB, Output: STT) => Price = STT/TokenB = AmountOut / AmountIn
     
     let price = if zero_for_one {
-        if amount_out_readable == BigDecimal::from(0) { 
+        if amount_out_readable.is_zero() { 
             BigDecimal::from(0) 
         } else { 
             &amount_in_readable / &amount_out_readable 
         }
     } else {
-        if amount_in_readable == BigDecimal::from(0) { 
+        if amount_in_readable.is_zero() { 
             BigDecimal::from(0) 
         } else { 
             &amount_out_readable / &amount_in_readable 
@@ -764,6 +1039,11 @@ pub async fn update_kline(
 
 /// Process UserMint event and update NFT status
 /// Called when UserMint event is received from blockchain
+/// The remark is parsed with `MintRemark`; events that fail validation (bad remark, unknown
+/// NFT, minter not the owner or not holding the chips) are recorded in `mint_anomalies`
+/// A repeated event for an already minted NFT is ignored, or recorded as `token_mismatch`
+/// when it carries a different token id; the minted token is never overwritten. An event for
+/// a token that was burned and redeemed is recorded as `redeemed_token`
 pub async fn process_user_mint_event(
     pool: &PgPool,
     user_address: &str,
@@ -775,13 +1055,6 @@ pub async fn process_user_mint_event(
     info!("Processing UserMint event: user={}, token_id={}, block_number={}, remark={}, token_url={}", 
         user_address, token_id, block_number, remark, token_url);
 
-    // Parse remark as nft_id
-    let nft_id: i32 = remark.parse()
-        .map_err(|e| sqlx::Error::Decode(Box::new(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            format!("Failed to parse remark as nft_id: {}", e)
-        ))))?;
-
     // Parse token_id to i64 for database
     let token_id_num: i64 = token_id.parse()
         .map_err(|e| sqlx::Error::Decode(Box::new(std::io::Error::new(
@@ -791,33 +1064,154 @@ pub async fn process_user_mint_event(
 
     // Parse block_number to i64
     let block_number_i64 = block_number as i64;
+    let minter = user_address.to_lowercase();
+
+    let mut tx = pool.begin().await?;
+
+    let nft_id = match MintRemark::parse(remark) {
+        Ok(parsed) => parsed.nft_id,
+        Err(e) => {
+            record_mint_anomaly(&mut tx, &NewMintAnomaly {
+                kind: MintAnomalyKind::InvalidRemark,
+                remark,
+                nft_id: None,
+                token_id: Some(token_id_num),
+                minter: &minter,
+                recorded_owner: None,
+                detail: e.reason,
+                block_number: block_number_i64,
+            }).await?;
+            tx.commit().await?;
+            return Ok(());
+        }
+    };
+
+    let current = sqlx::query!(
+        "SELECT is_mint, user_address, token_id FROM nfts WHERE id = $1 FOR UPDATE",
+        nft_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(current) = current else {
+        record_mint_anomaly(&mut tx, &NewMintAnomaly {
+            kind: MintAnomalyKind::UnknownNft,
+            remark,
+            nft_id: Some(nft_id),
+            token_id: Some(token_id_num),
+            minter: &minter,
+            recorded_owner: None,
+            detail: format!("No NFT record found with id={}", nft_id),
+            block_number: block_number_i64,
+        }).await?;
+        tx.commit().await?;
+        return Ok(());
+    };
+
+    // reset 赎回后 NFT id 不变：已销毁 token 的 UserMint（补扫、重放）不能完成新的 edition
+    let redeemed = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM nft_redemptions
+            WHERE nft_id = $1 AND token_id = $2 AND status = 'redeemed'
+        ) AS "redeemed!"
+        "#,
+        nft_id,
+        token_id_num
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if redeemed {
+        record_mint_anomaly(&mut tx, &NewMintAnomaly {
+            kind: MintAnomalyKind::RedeemedToken,
+            remark,
+            nft_id: Some(nft_id),
+            token_id: Some(token_id_num),
+            minter: &minter,
+            recorded_owner: current.user_address.as_deref(),
+            detail: format!("Token {} of NFT {} was burned and redeemed", token_id_num, nft_id),
+            block_number: block_number_i64,
+        }).await?;
+        tx.commit().await?;
+        return Ok(());
+    }
+
+    let state = MintState::from_i32(current.is_mint).unwrap_or(MintState::Unminted);
+
+    // 已 mint：同一 token 的重复事件（实时监听与补扫重叠）直接忽略，不同 token 记录异常，均不覆盖
+    if state == MintState::Minted {
+        if current.token_id == Some(token_id_num) {
+            info!("NFT {} already minted as token {}, ignoring duplicate UserMint event", nft_id, token_id_num);
+            return Ok(());
+        }
+        record_mint_anomaly(&mut tx, &NewMintAnomaly {
+            kind: MintAnomalyKind::TokenMismatch,
+            remark,
+            nft_id: Some(nft_id),
+            token_id: Some(token_id_num),
+            minter: &minter,
+            recorded_owner: current.user_address.as_deref(),
+            detail: format!("NFT {} is already minted as token {:?}", nft_id, current.token_id),
+            block_number: block_number_i64,
+        }).await?;
+        tx.commit().await?;
+        return Ok(());
+    }
+
+    if !state.can_transition_to(MintState::Minted, false) {
+        warn!("⚠️  NFT {} cannot move from {:?} to Minted, ignoring UserMint event", nft_id, state);
+        return Ok(());
+    }
+
+    // 校验铸造者：持有者不一致或未持有全部 chips 时记录异常，不覆盖 nfts.user_address
+    let anomalies = check_minter(&mut tx, nft_id, &minter, current.user_address.as_deref()).await?;
+    for (kind, detail) in &anomalies {
+        record_mint_anomaly(&mut tx, &NewMintAnomaly {
+            kind: *kind,
+            remark,
+            nft_id: Some(nft_id),
+            token_id: Some(token_id_num),
+            minter: &minter,
+            recorded_owner: current.user_address.as_deref(),
+            detail: detail.clone(),
+            block_number: block_number_i64,
+        }).await?;
+    }
+    let owner = if anomalies.is_empty() { Some(minter.clone()) } else { current.user_address.clone() };
 
     // Update the NFT record (including token_url)
-    let result = sqlx::query!(
+    sqlx::query!(
         r#"
         UPDATE nfts 
         SET user_address = $1, 
             token_id = $2, 
-            is_mint = 2,
-            block_number = $3,
-            token_url = $4
-        WHERE id = $5
+            is_mint = $3,
+            mint_started_at = NULL,
+            minted_at = NOW(),
+            block_number = $4,
+            token_url = $5,
+            owner_address = $6
+        WHERE id = $7
         "#,
-        user_address.to_lowercase(),
+        owner,
         token_id_num,
+        MintState::Minted.as_i32(),
         block_number_i64,
         token_url,
+        minter,
         nft_id
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
 
-    if result.rows_affected() > 0 {
-        info!("✅ Successfully updated NFT {} - is_mint=2 (mint successful), token_id={}, block_number={}, token_url={}", 
-            nft_id, token_id, block_number, token_url);
-    } else {
-        warn!("⚠️  No NFT record found with id={} (remark={})", nft_id, remark);
-    }
+    // Transfer 事件可能先于 UserMint 被索引（包括 mint 之后立即转手）
+    crate::services::nft_ownership::sync_current_owner(&mut tx, token_id_num).await?;
+
+    mint_state::complete_mint_attempt(&mut tx, nft_id, token_id_num).await?;
+    tx.commit().await?;
+
+    info!("✅ Successfully updated NFT {} - is_mint=2 (mint successful), token_id={}, block_number={}, token_url={}", 
+        nft_id, token_id, block_number, token_url);
 
     Ok(())
 }
@@ -830,6 +1224,7 @@ pub async fn process_user_mint_event(
 /// - ❓ 是否需要记录转账历史到数据库？
 /// - ❓ 转账是否会触发缓存失效？
 /// - ❓ 其他业务逻辑？
+///
 /// Process Transfer event from blockchain
 /// This function handles both sender (revert) and receiver (receive) logic
 pub async fn process_transfer_event(
@@ -887,3 +1282,33 @@ pub async fn process_transfer_event(
     info!("✅ Transfer event processed successfully");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chip_deficit() {
+        assert_eq!(chip_deficit(10, 10, false), None);
+        assert_eq!(chip_deficit(10, 10, true), None);
+        // Pool ran out after 3 of 10 chips
+        assert_eq!(chip_deficit(10, 3, true), Some(ChipDeficit { missed: 7, reason: "pool_exhausted" }));
+        // MAX_CHIPS_PER_RECEIVE capped a 1500 chip receive at 1000
+        assert_eq!(chip_deficit(1500, 1000, false), Some(ChipDeficit { missed: 500, reason: "receive_limit" }));
+        // 2 restored (cooldown) + 3 newly allocated chips cover a need of 5
+        assert_eq!(chip_deficit(5, 2 + 3, false), None);
+        assert_eq!(chip_deficit(5, 2 + 1, true), Some(ChipDeficit { missed: 2, reason: "pool_exhausted" }));
+    }
+
+    #[test]
+    fn test_chips_to_release() {
+        let owned = [1, 2, 3];
+        assert_eq!(chips_to_release(&owned, 0), (&owned[..0], false));
+        assert_eq!(chips_to_release(&owned, -2), (&owned[..0], false));
+        // M > N: part of the chips, the NFT stays
+        assert_eq!(chips_to_release(&owned, 2), (&owned[..2], false));
+        // M == N and M < N: every chip and the NFT
+        assert_eq!(chips_to_release(&owned, 3), (&owned[..], true));
+        assert_eq!(chips_to_release(&owned, 5), (&owned[..], true));
+    }
+}
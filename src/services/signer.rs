@@ -0,0 +1,295 @@
+use tracing::{info, warn, error};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use alloy::eips::eip1559::Eip1559Estimation;
+use alloy::network::{EthereumWallet, TransactionBuilder};
+use alloy::primitives::{Address, Bytes, B256};
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::rpc::types::{TransactionReceipt, TransactionRequest};
+use alloy::signers::local::PrivateKeySigner;
+use crate::config::{SignerConfig, get_signer_config};
+
+static SIGNER_POOL: OnceLock<SignerPool> = OnceLock::new();
+
+/// One backend signer with its own provider and locally tracked nonce
+struct SignerSlot {
+    address: Address,
+    provider: DynProvider,
+    /// Next nonce to use; `None` means resync from the chain (pending count)
+    next_nonce: Mutex<Option<u64>>,
+}
+
+/// Shared pool of backend signers
+///
+/// Each signer sends one transaction at a time under its nonce lock, so concurrent callers
+/// never reuse a nonce. Callers pick the signer by slot (one mint job worker per signer).
+pub struct SignerPool {
+    slots: Vec<SignerSlot>,
+    config: SignerConfig,
+}
+
+/// A transaction sent by the pool, tracked until one of its versions is mined
+#[derive(Debug, Clone)]
+pub struct SentTransaction {
+    pub signer: Address,
+    pub nonce: u64,
+    /// Hash of the latest version (after speed-ups)
+    pub tx_hash: B256,
+    /// All versions sent for this nonce, oldest first
+    pub hashes: Vec<B256>,
+    pub replacements: u32,
+    slot: usize,
+    request: TransactionRequest,
+    fees: Eip1559Estimation,
+}
+
+/// Get the shared signer pool, building it from the config on first use
+pub fn get_signer_pool() -> Result<&'static SignerPool, String> {
+    if let Some(pool) = SIGNER_POOL.get() {
+        return Ok(pool);
+    }
+    let pool = SignerPool::new(get_signer_config()?)?;
+    Ok(SIGNER_POOL.get_or_init(|| pool))
+}
+
+/// Cap the estimated fees to the configured maximums
+pub fn cap_fees(estimate: Eip1559Estimation, config: &SignerConfig) -> Eip1559Estimation {
+    let mut max_fee_per_gas = estimate.max_fee_per_gas;
+    let mut max_priority_fee_per_gas = estimate.max_priority_fee_per_gas;
+    if let Some(cap) = config.max_fee_per_gas_cap {
+        max_fee_per_gas = max_fee_per_gas.min(cap);
+    }
+    if let Some(cap) = config.max_priority_fee_cap {
+        max_priority_fee_per_gas = max_priority_fee_per_gas.min(cap);
+    }
+    Eip1559Estimation {
+        max_fee_per_gas,
+        max_priority_fee_per_gas: max_priority_fee_per_gas.min(max_fee_per_gas),
+    }
+}
+
+/// Raise the fees of a replacement by `bump_percent` (rounded up), respecting the caps
+/// Returns `None` if the caps leave no room for a valid replacement
+pub fn bump_fees(fees: Eip1559Estimation, config: &SignerConfig) -> Option<Eip1559Estimation> {
+    let bump = |fee: u128| fee + (fee * config.fee_bump_percent as u128).div_ceil(100).max(1);
+    let bumped = cap_fees(
+        Eip1559Estimation {
+            max_fee_per_gas: bump(fees.max_fee_per_gas),
+            max_priority_fee_per_gas: bump(fees.max_priority_fee_per_gas),
+        },
+        config,
+    );
+    // 节点要求两项费用都至少提高 10%
+    let min_bump = |fee: u128| fee + fee.div_ceil(10);
+    if bumped.max_fee_per_gas < min_bump(fees.max_fee_per_gas)
+        || bumped.max_priority_fee_per_gas < min_bump(fees.max_priority_fee_per_gas) {
+        return None;
+    }
+    Some(bumped)
+}
+
+impl SignerPool {
+    fn new(config: SignerConfig) -> Result<Self, String> {
+        let mut slots = Vec::with_capacity(config.keys.len());
+        for key in &config.keys {
+            let signer: PrivateKeySigner = key.parse()
+                .map_err(|e| format!("Failed to parse signer key: {:?}", e))?;
+            let address = signer.address();
+            let url = config.rpc_url.parse()
+                .map_err(|e| format!("Invalid RPC_URL: {:?}", e))?;
+            let provider = ProviderBuilder::new()
+                .wallet(EthereumWallet::from(signer))
+                .connect_http(url)
+                .erased();
+            info!("🔑 Loaded backend signer {:?}", address);
+            slots.push(SignerSlot {
+                address,
+                provider,
+                next_nonce: Mutex::new(None),
+            });
+        }
+
+        Ok(Self {
+            slots,
+            config,
+        })
+    }
+
+    /// Number of configured signers (slots `0..signer_count()`)
+    pub fn signer_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Address of the signer in `slot`
+    pub fn signer_address(&self, slot: usize) -> Address {
+        self.slots[slot].address
+    }
+
+    /// Address used as `from` when simulating calls before a signer is chosen (the first signer)
+    pub fn simulation_signer(&self) -> Address {
+        self.signer_address(0)
+    }
+
+    /// Forget the tracked nonce of `slot` so the next send reads the pending count from the chain
+    ///
+    /// Call this when a sent transaction could not be confirmed: it may have been dropped,
+    /// in which case the locally tracked nonce would leave a gap.
+    pub async fn resync(&self, slot: usize) {
+        *self.slots[slot].next_nonce.lock().await = None;
+        warn!("🔄 Nonce of signer {:?} will be resynced from the chain", self.slots[slot].address);
+    }
+
+    /// Send a contract call from the signer in `slot` with a tracked nonce and capped EIP-1559 fees
+    pub async fn send_from(
+        &self,
+        slot_index: usize,
+        to: Address,
+        input: Bytes,
+    ) -> Result<SentTransaction, Box<dyn std::error::Error + Send + Sync>> {
+        let slot = &self.slots[slot_index];
+        let mut nonce_guard = slot.next_nonce.lock().await;
+
+        let nonce = match *nonce_guard {
+            Some(nonce) => nonce,
+            None => slot.provider.get_transaction_count(slot.address).pending().await?,
+        };
+
+        let fees = cap_fees(slot.provider.estimate_eip1559_fees().await?, &self.config);
+        let mut request = TransactionRequest::default()
+            .with_from(slot.address)
+            .with_to(to)
+            .with_input(input)
+            .with_nonce(nonce);
+        let gas = slot.provider.estimate_gas(request.clone()).await?;
+        request.set_gas_limit(gas + gas * self.config.gas_limit_buffer_percent / 100);
+
+        let tx = request.clone()
+            .with_max_fee_per_gas(fees.max_fee_per_gas)
+            .with_max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
+
+        match slot.provider.send_transaction(tx).await {
+            Ok(pending) => {
+                *nonce_guard = Some(nonce + 1);
+                let tx_hash = *pending.tx_hash();
+                info!("📤 Sent tx {:?} from {:?} (nonce {}, maxFee {}, priority {})",
+                    tx_hash, slot.address, nonce, fees.max_fee_per_gas, fees.max_priority_fee_per_gas);
+                Ok(SentTransaction {
+                    signer: slot.address,
+                    nonce,
+                    tx_hash,
+                    hashes: vec![tx_hash],
+                    replacements: 0,
+                    slot: slot_index,
+                    request,
+                    fees,
+                })
+            }
+            Err(e) => {
+                // 发送失败时 nonce 状态未知，下次从链上重新同步
+                *nonce_guard = None;
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Wait up to the stuck timeout for any version of the transaction to be mined
+    ///
+    /// Returns the receipt once mined. If the wait times out the transaction is sped up
+    /// (same nonce, bumped fees) and `Ok(None)` is returned so the caller can record the
+    /// new hash and wait again. Fails once the replacement budget is exhausted.
+    pub async fn wait_or_speed_up(
+        &self,
+        sent: &mut SentTransaction,
+    ) -> Result<Option<TransactionReceipt>, Box<dyn std::error::Error + Send + Sync>> {
+        let slot = &self.slots[sent.slot];
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(self.config.stuck_tx_timeout_secs);
+
+        while tokio::time::Instant::now() < deadline {
+            for hash in sent.hashes.iter().rev() {
+                if let Some(receipt) = slot.provider.get_transaction_receipt(*hash).await? {
+                    if *hash != sent.tx_hash {
+                        info!("⛏️  Earlier version {:?} of nonce {} was mined", hash, sent.nonce);
+                        sent.tx_hash = *hash;
+                    }
+                    return Ok(Some(receipt));
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
+
+        if sent.replacements >= self.config.max_replacements {
+            return Err(format!("Transaction {:?} not mined after {} replacements", sent.tx_hash, sent.replacements).into());
+        }
+        let Some(fees) = bump_fees(sent.fees, &self.config) else {
+            warn!("⚠️  Fee caps reached, cannot speed up {:?}; waiting without replacement", sent.tx_hash);
+            sent.replacements += 1;
+            return Ok(None);
+        };
+
+        let tx = sent.request.clone()
+            .with_max_fee_per_gas(fees.max_fee_per_gas)
+            .with_max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
+        match slot.provider.send_transaction(tx).await {
+            Ok(pending) => {
+                let tx_hash = *pending.tx_hash();
+                warn!("🚀 Sped up nonce {} of {:?}: {:?} -> {:?} (maxFee {}, priority {})",
+                    sent.nonce, sent.signer, sent.tx_hash, tx_hash, fees.max_fee_per_gas, fees.max_priority_fee_per_gas);
+                sent.tx_hash = tx_hash;
+                sent.hashes.push(tx_hash);
+                sent.fees = fees;
+            }
+            Err(e) => {
+                // 常见原因是原交易刚好被打包（nonce too low），下一轮查询收据即可
+                error!("❌ Failed to speed up {:?}: {:?}", sent.tx_hash, e);
+            }
+        }
+        sent.replacements += 1;
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_fee_cap: Option<u128>, priority_cap: Option<u128>) -> SignerConfig {
+        SignerConfig {
+            keys: vec![],
+            rpc_url: String::new(),
+            max_fee_per_gas_cap: max_fee_cap,
+            max_priority_fee_cap: priority_cap,
+            gas_limit_buffer_percent: 20,
+            stuck_tx_timeout_secs: 120,
+            fee_bump_percent: 15,
+            max_replacements: 3,
+        }
+    }
+
+    fn fees(max_fee: u128, priority: u128) -> Eip1559Estimation {
+        Eip1559Estimation { max_fee_per_gas: max_fee, max_priority_fee_per_gas: priority }
+    }
+
+    #[test]
+    fn caps_estimated_fees() {
+        let capped = cap_fees(fees(100, 30), &config(Some(50), Some(40)));
+        assert_eq!(capped.max_fee_per_gas, 50);
+        assert_eq!(capped.max_priority_fee_per_gas, 30);
+
+        // priority fee never exceeds max fee
+        let capped = cap_fees(fees(100, 80), &config(Some(50), None));
+        assert_eq!(capped.max_priority_fee_per_gas, 50);
+    }
+
+    #[test]
+    fn bumps_fees_by_percent() {
+        let bumped = bump_fees(fees(100, 10), &config(None, None)).unwrap();
+        assert_eq!(bumped.max_fee_per_gas, 115);
+        assert_eq!(bumped.max_priority_fee_per_gas, 12);
+    }
+
+    #[test]
+    fn no_bump_when_capped() {
+        assert!(bump_fees(fees(100, 10), &config(Some(105), None)).is_none());
+    }
+}
@@ -0,0 +1,305 @@
+use tracing::{info, warn};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use alloy::primitives::{Address, B256, Signature, keccak256};
+use crate::config::SiweConfig;
+
+const HEADER_SUFFIX: &str = " wants you to sign in with your Ethereum account:";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SiweError {
+    #[error("{0}")]
+    Invalid(String),
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+/// Fields of an EIP-4361 message that the backend checks
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiweMessage {
+    pub domain: String,
+    pub address: Address,
+    pub statement: Option<String>,
+    pub uri: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub nonce: String,
+    pub issued_at: DateTime<Utc>,
+    pub expiration_time: Option<DateTime<Utc>>,
+    pub not_before: Option<DateTime<Utc>>,
+}
+
+/// Session issued after a successful sign-in
+#[derive(Debug, Clone)]
+pub struct SiweSession {
+    pub token: String,
+    pub address: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+fn parse_time(field: &str, value: &str) -> Result<DateTime<Utc>, SiweError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| SiweError::Invalid(format!("Invalid {}: {}", field, e)))
+}
+
+/// Parse an EIP-4361 message
+pub fn parse_siwe_message(message: &str) -> Result<SiweMessage, SiweError> {
+    let mut lines = message.lines();
+
+    let domain = lines.next()
+        .and_then(|line| line.strip_suffix(HEADER_SUFFIX))
+        .ok_or_else(|| SiweError::Invalid("Missing SIWE header".to_string()))?
+        .to_string();
+
+    let address: Address = lines.next()
+        .ok_or_else(|| SiweError::Invalid("Missing address".to_string()))?
+        .trim()
+        .parse()
+        .map_err(|e| SiweError::Invalid(format!("Invalid address: {}", e)))?;
+
+    let mut statement = None;
+    let mut uri = None;
+    let mut version = None;
+    let mut chain_id = None;
+    let mut nonce = None;
+    let mut issued_at = None;
+    let mut expiration_time = None;
+    let mut not_before = None;
+
+    for line in lines {
+        if line.is_empty() || line.starts_with("- ") || line == "Resources:" {
+            continue;
+        }
+        match line.split_once(": ") {
+            Some(("URI", value)) => uri = Some(value.to_string()),
+            Some(("Version", value)) => version = Some(value.to_string()),
+            Some(("Chain ID", value)) => chain_id = Some(value.parse::<u64>()
+                .map_err(|e| SiweError::Invalid(format!("Invalid Chain ID: {}", e)))?),
+            Some(("Nonce", value)) => nonce = Some(value.to_string()),
+            Some(("Issued At", value)) => issued_at = Some(parse_time("Issued At", value)?),
+            Some(("Expiration Time", value)) => expiration_time = Some(parse_time("Expiration Time", value)?),
+            Some(("Not Before", value)) => not_before = Some(parse_time("Not Before", value)?),
+            Some(("Request ID", _)) => {}
+            _ if uri.is_none() && statement.is_none() => statement = Some(line.to_string()),
+            _ => return Err(SiweError::Invalid(format!("Unexpected line: {}", line))),
+        }
+    }
+
+    let missing = |field: &str| SiweError::Invalid(format!("Missing {}", field));
+    Ok(SiweMessage {
+        domain,
+        address,
+        statement,
+        uri: uri.ok_or_else(|| missing("URI"))?,
+        version: version.ok_or_else(|| missing("Version"))?,
+        chain_id: chain_id.ok_or_else(|| missing("Chain ID"))?,
+        nonce: nonce.ok_or_else(|| missing("Nonce"))?,
+        issued_at: issued_at.ok_or_else(|| missing("Issued At"))?,
+        expiration_time,
+        not_before,
+    })
+}
+
+/// Check the message fields against the config and the current time
+pub fn validate_siwe_message(message: &SiweMessage, config: &SiweConfig, now: DateTime<Utc>) -> Result<(), SiweError> {
+    if message.version != "1" {
+        return Err(SiweError::Invalid(format!("Unsupported SIWE version: {}", message.version)));
+    }
+    if message.domain != config.domain {
+        return Err(SiweError::Invalid(format!("Domain mismatch: {}", message.domain)));
+    }
+    let uri_matches = message.uri.strip_prefix(config.uri.as_str())
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+    if !uri_matches {
+        return Err(SiweError::Invalid(format!("URI mismatch: {}", message.uri)));
+    }
+    if message.chain_id != config.chain_id {
+        return Err(SiweError::Invalid(format!("Chain ID mismatch: {}", message.chain_id)));
+    }
+    if let Some(expiration_time) = message.expiration_time
+        && now >= expiration_time {
+        return Err(SiweError::Invalid("Message has expired".to_string()));
+    }
+    if let Some(not_before) = message.not_before
+        && now < not_before {
+        return Err(SiweError::Invalid("Message is not yet valid".to_string()));
+    }
+    Ok(())
+}
+
+fn hash_token(token: &str) -> String {
+    format!("{:?}", keccak256(token.as_bytes()))
+}
+
+/// Issue a one-time nonce for a SIWE message
+///
+/// Expired nonces are purged first, so unauthenticated callers cannot grow the table without bound.
+pub async fn issue_nonce(pool: &PgPool, config: &SiweConfig) -> Result<(String, DateTime<Utc>), sqlx::Error> {
+    sqlx::query!("DELETE FROM siwe_nonces WHERE expires_at <= NOW()")
+        .execute(pool)
+        .await?;
+
+    // 32 hex chars, alphanumeric as EIP-4361 requires
+    let nonce = alloy::hex::encode(&B256::random()[..16]);
+    let expires_at = Utc::now() + chrono::Duration::seconds(config.nonce_ttl_secs);
+
+    sqlx::query!(
+        "INSERT INTO siwe_nonces (nonce, expires_at) VALUES ($1, $2)",
+        nonce,
+        expires_at
+    )
+    .execute(pool)
+    .await?;
+
+    Ok((nonce, expires_at))
+}
+
+/// Verify a signed SIWE message, consume its nonce and open a session
+pub async fn sign_in(
+    pool: &PgPool,
+    config: &SiweConfig,
+    message: &str,
+    signature: &str,
+) -> Result<SiweSession, SiweError> {
+    let parsed = parse_siwe_message(message)?;
+    validate_siwe_message(&parsed, config, Utc::now())?;
+
+    let signature: Signature = signature.parse()
+        .map_err(|e| SiweError::Invalid(format!("Invalid signature: {}", e)))?;
+    let recovered = signature.recover_address_from_msg(message.as_bytes())
+        .map_err(|e| SiweError::Invalid(format!("Invalid signature: {}", e)))?;
+    if recovered != parsed.address {
+        warn!("SIWE signature mismatch: message address {:?}, signer {:?}", parsed.address, recovered);
+        return Err(SiweError::Invalid("Signature does not match address".to_string()));
+    }
+
+    let consumed = sqlx::query!(
+        r#"
+        UPDATE siwe_nonces
+        SET used_at = NOW()
+        WHERE nonce = $1 AND used_at IS NULL AND expires_at > NOW()
+        "#,
+        parsed.nonce
+    )
+    .execute(pool)
+    .await?;
+    if consumed.rows_affected() == 0 {
+        return Err(SiweError::Invalid("Unknown, used or expired nonce".to_string()));
+    }
+
+    let token = alloy::hex::encode(B256::random());
+    let address = format!("{:?}", parsed.address).to_lowercase();
+    let expires_at = Utc::now() + chrono::Duration::seconds(config.session_ttl_secs);
+
+    sqlx::query!(
+        "INSERT INTO user_sessions (token_hash, user_address, expires_at) VALUES ($1, $2, $3)",
+        hash_token(&token),
+        address,
+        expires_at
+    )
+    .execute(pool)
+    .await?;
+
+    info!("🔐 SIWE sign-in for {}", address);
+    Ok(SiweSession { token, address, expires_at })
+}
+
+/// Address of the session behind a bearer token, if it is valid
+pub async fn session_address(pool: &PgPool, token: &str) -> Result<Option<String>, sqlx::Error> {
+    let session = sqlx::query!(
+        r#"
+        SELECT user_address
+        FROM user_sessions
+        WHERE token_hash = $1 AND revoked_at IS NULL AND expires_at > NOW()
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(session.map(|s| s.user_address))
+}
+
+/// Revoke a session; returns false if the token was unknown or already revoked
+pub async fn revoke_session(pool: &PgPool, token: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "UPDATE user_sessions SET revoked_at = NOW() WHERE token_hash = $1 AND revoked_at IS NULL",
+        hash_token(token)
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "haku.example.com wants you to sign in with your Ethereum account:\n\
+        0x1111111111111111111111111111111111111111\n\
+        \n\
+        Sign in to Haku\n\
+        \n\
+        URI: https://haku.example.com\n\
+        Version: 1\n\
+        Chain ID: 50312\n\
+        Nonce: 0123456789abcdef\n\
+        Issued At: 2025-12-01T00:00:00Z\n\
+        Expiration Time: 2025-12-01T01:00:00Z";
+
+    fn config() -> SiweConfig {
+        SiweConfig {
+            domain: "haku.example.com".to_string(),
+            uri: "https://haku.example.com".to_string(),
+            chain_id: 50312,
+            nonce_ttl_secs: 600,
+            session_ttl_secs: 86400,
+        }
+    }
+
+    #[test]
+    fn parses_message() {
+        let message = parse_siwe_message(MESSAGE).unwrap();
+        assert_eq!(message.domain, "haku.example.com");
+        assert_eq!(message.address, Address::repeat_byte(0x11));
+        assert_eq!(message.statement.as_deref(), Some("Sign in to Haku"));
+        assert_eq!(message.chain_id, 50312);
+        assert_eq!(message.nonce, "0123456789abcdef");
+        assert!(message.expiration_time.is_some());
+    }
+
+    #[test]
+    fn rejects_wrong_domain_and_expired() {
+        let message = parse_siwe_message(MESSAGE).unwrap();
+        let issued = message.issued_at;
+        assert!(validate_siwe_message(&message, &config(), issued).is_ok());
+
+        let expired = issued + chrono::Duration::hours(2);
+        assert!(validate_siwe_message(&message, &config(), expired).is_err());
+
+        let other_domain = SiweConfig { domain: "evil.example.com".to_string(), ..config() };
+        assert!(validate_siwe_message(&message, &other_domain, issued).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_uri_and_chain() {
+        let message = parse_siwe_message(MESSAGE).unwrap();
+        let issued = message.issued_at;
+
+        let other_uri = SiweConfig { uri: "https://haku.example.com.evil.io".to_string(), ..config() };
+        assert!(validate_siwe_message(&message, &other_uri, issued).is_err());
+
+        let prefix = SiweConfig { uri: "https://haku.example".to_string(), ..config() };
+        assert!(validate_siwe_message(&message, &prefix, issued).is_err());
+
+        let other_chain = SiweConfig { chain_id: 1, ..config() };
+        assert!(validate_siwe_message(&message, &other_chain, issued).is_err());
+    }
+
+    #[test]
+    fn rejects_missing_header() {
+        assert!(parse_siwe_message("hello\n0x1111111111111111111111111111111111111111").is_err());
+    }
+}
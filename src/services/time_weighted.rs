@@ -0,0 +1,133 @@
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// A signed balance change of one address (raw units)
+#[derive(Debug, Clone)]
+pub struct BalanceChange {
+    pub at: DateTime<Utc>,
+    pub delta: BigDecimal,
+}
+
+/// Time-weighted average balance over [window_start, now]
+///
+/// Replays `changes` (sorted ascending by time) backwards from the current balance,
+/// so only transfers inside the window are needed. Negative intermediate balances
+/// (incomplete history) are counted as zero.
+pub fn time_weighted_average_balance(
+    current_balance: &BigDecimal,
+    changes: &[BalanceChange],
+    window_start: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> BigDecimal {
+    let window_secs = (now - window_start).num_seconds();
+    if window_secs <= 0 {
+        return current_balance.clone();
+    }
+
+    let zero = BigDecimal::zero();
+    let mut weighted = BigDecimal::zero();
+    let mut balance = current_balance.clone();
+    let mut segment_end = now;
+
+    for change in changes.iter().rev() {
+        if change.at <= window_start {
+            break;
+        }
+        let at = change.at.min(now);
+        let secs = (segment_end - at).num_seconds().max(0);
+        weighted += balance.clone().max(zero.clone()) * BigDecimal::from(secs);
+        balance -= &change.delta;
+        segment_end = at;
+    }
+
+    let secs = (segment_end - window_start).num_seconds().max(0);
+    weighted += balance.max(zero) * BigDecimal::from(secs);
+
+    weighted / BigDecimal::from(window_secs)
+}
+
+/// Load the user's balance changes after `since` from token_transfers
+pub async fn load_balance_changes(
+    pool: &PgPool,
+    user_address: &str,
+    since: DateTime<Utc>,
+) -> Result<Vec<BalanceChange>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            block_timestamp,
+            (CASE WHEN to_address = $1 THEN value_raw ELSE 0 END)
+              - (CASE WHEN from_address = $1 THEN value_raw ELSE 0 END) as "delta!"
+        FROM token_transfers
+        WHERE (from_address = $1 OR to_address = $1) AND block_timestamp > $2
+        ORDER BY block_timestamp, id
+        "#,
+        user_address.to_lowercase(),
+        since
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| BalanceChange { at: r.block_timestamp, delta: r.delta })
+        .collect())
+}
+
+/// Addresses that sent or received tokens since `since` (their TWAB may still be changing)
+pub async fn addresses_with_recent_transfers(
+    pool: &PgPool,
+    since: DateTime<Utc>,
+) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT DISTINCT address as "address!" FROM (
+            SELECT from_address as address FROM token_transfers WHERE block_timestamp >= $1
+            UNION
+            SELECT to_address as address FROM token_transfers WHERE block_timestamp >= $1
+        ) recent
+        "#,
+        since
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|r| r.address).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    #[test]
+    fn test_twab_without_changes_is_current_balance() {
+        let balance = BigDecimal::from(100);
+        let twab = time_weighted_average_balance(&balance, &[], at(0), at(1000));
+        assert_eq!(twab, BigDecimal::from(100));
+    }
+
+    #[test]
+    fn test_twab_flash_buy_counts_only_held_time() {
+        // Bought 100 tokens 10% into the end of the window
+        let changes = vec![BalanceChange { at: at(900), delta: BigDecimal::from(100) }];
+        let twab = time_weighted_average_balance(&BigDecimal::from(100), &changes, at(0), at(1000));
+        assert_eq!(twab, BigDecimal::from(10));
+    }
+
+    #[test]
+    fn test_twab_ignores_changes_before_window() {
+        let changes = vec![
+            BalanceChange { at: at(-500), delta: BigDecimal::from(50) },
+            BalanceChange { at: at(500), delta: BigDecimal::from(-50) },
+        ];
+        // 100 held for the first half, 50 for the second half
+        let twab = time_weighted_average_balance(&BigDecimal::from(50), &changes, at(0), at(1000));
+        assert_eq!(twab, BigDecimal::from(75));
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::{BTreeMap, HashMap};
+use serde::Serialize;
+use sqlx::PgPool;
+use crate::entitys::entity::TradeListing;
+
+pub const MAX_TRADE_NOTE_LEN: usize = 280;
+
+/// List `user_address` in the trade directory (or update its note)
+pub async fn open_to_trade(pool: &PgPool, user_address: &str, note: Option<&str>) -> Result<TradeListing, sqlx::Error> {
+    sqlx::query_as!(
+        TradeListing,
+        r#"
+        INSERT INTO trade_listings (user_address, note)
+        VALUES ($1, $2)
+        ON CONFLICT (user_address) DO UPDATE SET note = EXCLUDED.note
+        RETURNING user_address, note, created_at, updated_at
+        "#,
+        user_address.to_lowercase(),
+        note
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Remove `user_address` from the trade directory; returns false if it was not listed
+pub async fn close_to_trade(pool: &PgPool, user_address: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM trade_listings WHERE user_address = $1",
+        user_address.to_lowercase()
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_trade_listing(pool: &PgPool, user_address: &str) -> Result<Option<TradeListing>, sqlx::Error> {
+    sqlx::query_as!(
+        TradeListing,
+        "SELECT user_address, note, created_at, updated_at FROM trade_listings WHERE user_address = $1",
+        user_address.to_lowercase()
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// An opted-in address holding some of the caller's missing chips
+#[derive(Debug, Clone, Serialize)]
+pub struct ChipHolder {
+    pub address: String,
+    pub note: Option<String>,
+    pub chip_ids: Vec<i32>,
+}
+
+/// Who holds the chips of `nft_id` that `user_address` is missing
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingChipHolders {
+    pub nft_id: i32,
+    pub total_chips: i64,
+    pub missing_chips: i64,
+    pub holders: Vec<ChipHolder>,   // 仅包含已加入交易目录的地址，持有缺失 chips 最多的在前
+    pub unlisted_chips: i64,        // 缺失但持有者未加入目录（或尚未分配）的 chips
+}
+
+/// Group the chips of `nft_id` the caller does not hold by opted-in holder
+///
+/// `chips` are `(chip id, current holder)` pairs, the holder being None while the chip is not
+/// received by anyone; `listings` maps opted-in addresses to their note. Holders that are not
+/// listed are only counted in `unlisted_chips`.
+fn group_missing_chips(
+    nft_id: i32,
+    user_address: &str,
+    chips: Vec<(i32, Option<String>)>,
+    listings: &HashMap<String, Option<String>>,
+) -> MissingChipHolders {
+    let total_chips = chips.len() as i64;
+    let mut missing_chips = 0;
+    let mut unlisted_chips = 0;
+    let mut by_holder: BTreeMap<String, Vec<i32>> = BTreeMap::new();
+
+    for (chip_id, holder) in chips {
+        if holder.as_deref() == Some(user_address) {
+            continue;
+        }
+        missing_chips += 1;
+        match holder {
+            Some(holder) if listings.contains_key(&holder) => by_holder.entry(holder).or_default().push(chip_id),
+            _ => unlisted_chips += 1,
+        }
+    }
+
+    let mut holders: Vec<ChipHolder> = by_holder.into_iter()
+        .map(|(address, chip_ids)| ChipHolder { note: listings[&address].clone(), address, chip_ids })
+        .collect();
+    // 持有缺失 chips 最多的在前，相同时按地址
+    holders.sort_by(|a, b| b.chip_ids.len().cmp(&a.chip_ids.len()).then_with(|| a.address.cmp(&b.address)));
+
+    MissingChipHolders { nft_id, total_chips, missing_chips, holders, unlisted_chips }
+}
+
+/// Opted-in holders of the chips of `nft_id` that `user_address` does not hold
+///
+/// Addresses that have not opted in are never returned, only counted in `unlisted_chips`.
+pub async fn missing_chip_holders(pool: &PgPool, user_address: &str, nft_id: i32) -> Result<MissingChipHolders, sqlx::Error> {
+    let user_address = user_address.to_lowercase();
+
+    let chips = sqlx::query!(
+        r#"
+        SELECT id, CASE WHEN received THEN LOWER(user_address) END AS holder
+        FROM chips
+        WHERE nft_id = $1
+        ORDER BY id
+        "#,
+        nft_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let listings = sqlx::query!(
+        r#"
+        SELECT user_address, note
+        FROM trade_listings
+        WHERE user_address IN (SELECT LOWER(user_address) FROM chips WHERE nft_id = $1 AND received = true)
+        "#,
+        nft_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let listings: HashMap<String, Option<String>> = listings.into_iter()
+        .map(|r| (r.user_address, r.note))
+        .collect();
+    let chips = chips.into_iter().map(|c| (c.id, c.holder)).collect();
+
+    Ok(group_missing_chips(nft_id, &user_address, chips, &listings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: &str = "0x00000000000000000000000000000000000000aa";
+    const LISTED: &str = "0x00000000000000000000000000000000000000b1";
+    const LISTED_2: &str = "0x00000000000000000000000000000000000000b2";
+    const HIDDEN: &str = "0x00000000000000000000000000000000000000c1";
+
+    fn chip(id: i32, holder: Option<&str>) -> (i32, Option<String>) {
+        (id, holder.map(str::to_string))
+    }
+
+    #[test]
+    fn only_opted_in_holders_are_returned() {
+        let listings = HashMap::from([
+            (USER.to_string(), None),
+            (LISTED.to_string(), Some("swap anything".to_string())),
+            (LISTED_2.to_string(), None),
+        ]);
+        let chips = vec![
+            chip(1, Some(USER)),
+            chip(2, Some(LISTED_2)),
+            chip(3, Some(HIDDEN)),
+            chip(4, Some(LISTED)),
+            chip(5, None),
+            chip(6, Some(LISTED)),
+            chip(7, Some(HIDDEN)),
+        ];
+
+        let result = group_missing_chips(9, USER, chips, &listings);
+        assert_eq!((result.total_chips, result.missing_chips, result.unlisted_chips), (7, 6, 3));
+
+        let holders: Vec<(&str, &[i32])> = result.holders.iter()
+            .map(|h| (h.address.as_str(), h.chip_ids.as_slice()))
+            .collect();
+        assert_eq!(holders, [(LISTED, &[4, 6][..]), (LISTED_2, &[2][..])]);
+        assert_eq!(result.holders[0].note.as_deref(), Some("swap anything"));
+        // 调用者自己和未加入目录的地址都不会出现
+        assert!(result.holders.iter().all(|h| h.address != USER && h.address != HIDDEN));
+    }
+
+    #[test]
+    fn nothing_missing_when_the_caller_holds_every_chip() {
+        let result = group_missing_chips(9, USER, vec![chip(1, Some(USER)), chip(2, Some(USER))], &HashMap::new());
+        assert_eq!((result.total_chips, result.missing_chips, result.unlisted_chips), (2, 0, 0));
+        assert!(result.holders.is_empty());
+    }
+}